@@ -550,6 +550,7 @@ async fn main() -> RecorderResult<()> {
             ..Default::default()
         },
         base_url: Url::parse("https://mikanani.me")?,
+        mirrors: Default::default(),
     })
     .await?;
 
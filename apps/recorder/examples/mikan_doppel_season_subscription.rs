@@ -39,6 +39,7 @@ async fn main() -> Result<()> {
             ..Default::default()
         },
         base_url: Url::parse("https://mikanani.me")?,
+        mirrors: Default::default(),
     })
     .await?;
 
@@ -139,7 +140,7 @@ async fn main() -> Result<()> {
                 );
                 if !poster_doppel_path.exists_any() {
                     let poster_data = fetch_image(&mikan_scrape_client, poster_url.clone()).await?;
-                    poster_doppel_path.write(&poster_data)?;
+                    poster_doppel_path.write(&poster_data.bytes)?;
                     tracing::info!(title = bangumi_meta.bangumi_title, "Bangumi poster saved");
                 } else {
                     tracing::info!(
@@ -35,6 +35,7 @@ async fn main() -> RecorderResult<()> {
             ..Default::default()
         },
         base_url: Url::parse("https://mikanani.me")?,
+        mirrors: Default::default(),
     })
     .await?;
 
@@ -111,7 +112,7 @@ async fn main() -> RecorderResult<()> {
                 if !episode_poster_doppel_path.exists_any() {
                     let episode_poster_data =
                         fetch_image(&mikan_scrape_client, episode_poster_url.clone()).await?;
-                    episode_poster_doppel_path.write(&episode_poster_data)?;
+                    episode_poster_doppel_path.write(&episode_poster_data.bytes)?;
                     tracing::info!(title = rss_item.title, "Episode poster saved");
                 } else {
                     tracing::info!(title = rss_item.title, "Episode poster already exists");
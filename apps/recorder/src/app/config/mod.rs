@@ -15,9 +15,9 @@ use serde::{Deserialize, Serialize};
 use super::env::Environment;
 use crate::{
     auth::AuthConfig, cache::CacheConfig, crypto::CryptoConfig, database::DatabaseConfig,
-    errors::RecorderResult, extract::mikan::MikanConfig, graphql::GraphQLConfig,
-    logger::LoggerConfig, media::MediaConfig, message::MessageConfig, storage::StorageConfig,
-    task::TaskConfig, web::WebServerConfig,
+    download::DownloaderConfig, errors::RecorderResult, extract::mikan::MikanConfig,
+    graphql::GraphQLConfig, logger::LoggerConfig, media::MediaConfig, message::MessageConfig,
+    storage::StorageConfig, task::TaskConfig, web::WebServerConfig,
 };
 
 const DEFAULT_CONFIG_MIXIN: &str = include_str!("./default_mixin.toml");
@@ -37,6 +37,7 @@ pub struct AppConfig {
     pub database: DatabaseConfig,
     pub task: TaskConfig,
     pub message: MessageConfig,
+    pub downloader: DownloaderConfig,
 }
 
 impl AppConfig {
@@ -5,9 +5,9 @@ use tokio::sync::OnceCell;
 use super::{Environment, config::AppConfig};
 use crate::{
     auth::AuthService, cache::CacheService, crypto::CryptoService, database::DatabaseService,
-    errors::RecorderResult, extract::mikan::MikanClient, graphql::GraphQLService,
-    logger::LoggerService, media::MediaService, message::MessageService, storage::StorageService,
-    task::TaskService,
+    download::DownloaderService, errors::RecorderResult, extract::mikan::MikanClient,
+    graphql::GraphQLService, logger::LoggerService, media::MediaService, message::MessageService,
+    storage::StorageService, task::TaskService,
 };
 
 pub trait AppContextTrait: Send + Sync + Debug {
@@ -25,6 +25,7 @@ pub trait AppContextTrait: Send + Sync + Debug {
     fn task(&self) -> &TaskService;
     fn message(&self) -> &MessageService;
     fn media(&self) -> &MediaService;
+    fn downloader(&self) -> &DownloaderService;
 }
 
 pub struct AppContext {
@@ -40,6 +41,7 @@ pub struct AppContext {
     environment: Environment,
     message: MessageService,
     media: MediaService,
+    downloader: DownloaderService,
     task: OnceCell<TaskService>,
     graphql: OnceCell<GraphQLService>,
 }
@@ -61,6 +63,8 @@ impl AppContext {
         let mikan = MikanClient::from_config(config.mikan).await?;
         let crypto = CryptoService::from_config(config.crypto).await?;
         let media = MediaService::from_config(config.media).await?;
+        let downloader =
+            DownloaderService::from_config(config.downloader, &storage.data_dir).await?;
 
         let ctx = Arc::new(AppContext {
             config: config_cloned,
@@ -75,6 +79,7 @@ impl AppContext {
             crypto,
             message,
             media,
+            downloader,
             task: OnceCell::new(),
             graphql: OnceCell::new(),
         });
@@ -144,4 +149,7 @@ impl AppContextTrait for AppContext {
     fn media(&self) -> &MediaService {
         &self.media
     }
+    fn downloader(&self) -> &DownloaderService {
+        &self.downloader
+    }
 }
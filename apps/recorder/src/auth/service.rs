@@ -4,7 +4,7 @@ use async_trait::async_trait;
 use axum::http::request::Parts;
 use fetch::{
     HttpClient, HttpClientConfig,
-    client::{HttpClientCacheBackendConfig, HttpClientCachePresetConfig},
+    client::{HttpClientCacheBackendConfig, HttpClientCachePresetConfig, HttpClientRedirectConfig},
 };
 use http::header::HeaderValue;
 use jwtk::jwk::RemoteJwksVerifier;
@@ -52,6 +52,7 @@ impl AuthService {
                         exponential_backoff_max_retries: Some(3),
                         cache_backend: Some(HttpClientCacheBackendConfig::Moka { cache_size: 1 }),
                         cache_preset: Some(HttpClientCachePresetConfig::RFC7234),
+                        redirect_policy: Some(HttpClientRedirectConfig::None),
                         ..Default::default()
                     })
                     .context(OidcProviderHttpClientSnafu)?,
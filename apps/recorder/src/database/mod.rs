@@ -2,4 +2,4 @@ pub mod config;
 pub mod service;
 
 pub use config::DatabaseConfig;
-pub use service::DatabaseService;
+pub use service::{DatabasePoolStats, DatabaseService};
@@ -10,6 +10,13 @@ use sea_orm_migration::MigratorTrait;
 use super::DatabaseConfig;
 use crate::{errors::RecorderResult, migrations::Migrator};
 
+#[derive(Debug, Clone, Copy)]
+pub struct DatabasePoolStats {
+    pub size: u32,
+    pub idle: usize,
+    pub max_connections: u32,
+}
+
 pub struct DatabaseService {
     pub config: DatabaseConfig,
     connection: DatabaseConnection,
@@ -81,6 +88,15 @@ impl DatabaseService {
         }
         Ok(())
     }
+
+    pub fn pool_stats(&self) -> DatabasePoolStats {
+        let pool = self.get_postgres_connection_pool();
+        DatabasePoolStats {
+            size: pool.size(),
+            idle: pool.num_idle(),
+            max_connections: self.config.max_connections,
+        }
+    }
 }
 
 impl Deref for DatabaseService {
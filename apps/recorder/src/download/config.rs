@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DownloaderConfig {
+    /// Recorder-local folder for the state of remote downloaders, such as
+    /// torrent labels, `<storage.data_dir>/downloaders` if not set
+    #[serde(default)]
+    pub data_dir: Option<String>,
+}
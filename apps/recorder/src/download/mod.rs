@@ -0,0 +1,5 @@
+mod config;
+mod service;
+
+pub use config::DownloaderConfig;
+pub use service::DownloaderService;
@@ -0,0 +1,250 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use downloader::{
+    aria2::{Aria2Downloader, Aria2DownloaderCreation},
+    bittorrent::handle::TorrentDownloaderHandle,
+    dandanplay::{DandanplayDownloader, DandanplayDownloaderCreation},
+    qbit::{QBittorrentDownloader, QBittorrentDownloaderCreation},
+    rqbit::downloader::{RqbitDownloader, RqbitDownloaderCreation},
+    transmission::{TransmissionDownloader, TransmissionDownloaderCreation},
+};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use tokio::sync::{Mutex, RwLock};
+
+use crate::{
+    app::AppContextTrait,
+    download::DownloaderConfig,
+    errors::{RecorderError, RecorderResult},
+    models::downloaders::{self, DownloaderCategory},
+};
+
+struct DownloaderEntry {
+    model: downloaders::Model,
+    handle: TorrentDownloaderHandle,
+}
+
+/// Keeps one live downloader instance per `downloaders` row, built on first use
+/// and rebuilt whenever the row it was built from changes.
+pub struct DownloaderService {
+    pub config: DownloaderConfig,
+    /// Holds one state folder per downloader row
+    data_dir: PathBuf,
+    downloaders: RwLock<HashMap<i32, DownloaderEntry>>,
+    /// Serializes building per downloader row, so a slow or unreachable
+    /// downloader only blocks lookups of itself.
+    build_locks: std::sync::Mutex<HashMap<i32, Arc<Mutex<()>>>>,
+}
+
+impl std::fmt::Debug for DownloaderService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DownloaderService")
+            .field("config", &self.config)
+            .field("data_dir", &self.data_dir)
+            .finish()
+    }
+}
+
+impl DownloaderService {
+    pub async fn from_config(
+        config: DownloaderConfig,
+        storage_data_dir: &str,
+    ) -> RecorderResult<Self> {
+        let data_dir = config
+            .data_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| Path::new(storage_data_dir).join("downloaders"));
+
+        Ok(Self {
+            config,
+            data_dir,
+            downloaders: RwLock::new(HashMap::new()),
+            build_locks: std::sync::Mutex::new(HashMap::new()),
+        })
+    }
+
+    async fn get_cached(&self, model: &downloaders::Model) -> Option<TorrentDownloaderHandle> {
+        let downloaders = self.downloaders.read().await;
+        downloaders
+            .get(&model.id)
+            .filter(|entry| entry.model == *model)
+            .map(|entry| entry.handle.clone())
+    }
+
+    #[tracing::instrument(err, skip(self, model), fields(downloader_id = model.id))]
+    pub async fn get_or_build(
+        &self,
+        model: &downloaders::Model,
+    ) -> RecorderResult<TorrentDownloaderHandle> {
+        if let Some(handle) = self.get_cached(model).await {
+            return Ok(handle);
+        }
+
+        let build_lock = self
+            .build_locks
+            .lock()
+            .expect("downloader build locks poisoned")
+            .entry(model.id)
+            .or_default()
+            .clone();
+        let _build_guard = build_lock.lock().await;
+
+        if let Some(handle) = self.get_cached(model).await {
+            return Ok(handle);
+        }
+
+        // The outdated instance is shut down before its replacement is built,
+        // embedded downloaders would otherwise share their state folder.
+        let outdated = self.downloaders.write().await.remove(&model.id);
+        if let Some(outdated) = outdated {
+            Self::shutdown_downloader(model.id, outdated.handle).await;
+        }
+
+        let handle = self.build_downloader(model).await?;
+
+        self.downloaders.write().await.insert(
+            model.id,
+            DownloaderEntry {
+                model: model.clone(),
+                handle: handle.clone(),
+            },
+        );
+
+        Ok(handle)
+    }
+
+    pub async fn get_by_id(
+        &self,
+        ctx: &dyn AppContextTrait,
+        subscriber_id: i32,
+        downloader_id: i32,
+    ) -> RecorderResult<TorrentDownloaderHandle> {
+        let model = downloaders::Entity::find_by_id(downloader_id)
+            .filter(downloaders::Column::SubscriberId.eq(subscriber_id))
+            .one(ctx.db())
+            .await?
+            .ok_or_else(|| {
+                RecorderError::from_entity_not_found_detail::<downloaders::Entity, _>(format!(
+                    "downloader {downloader_id} not found for subscriber {subscriber_id}"
+                ))
+            })?;
+
+        self.get_or_build(&model).await
+    }
+
+    /// Serves `handle` for the downloader row instead of building one from its
+    /// category, until the row changes.
+    pub async fn register(&self, model: downloaders::Model, handle: TorrentDownloaderHandle) {
+        let downloader_id = model.id;
+        let replaced = self.downloaders.write().await.insert(
+            downloader_id,
+            DownloaderEntry {
+                model,
+                handle: handle.clone(),
+            },
+        );
+        if let Some(replaced) = replaced
+            && !Arc::ptr_eq(&replaced.handle, &handle)
+        {
+            Self::shutdown_downloader(downloader_id, replaced.handle).await;
+        }
+    }
+
+    /// Drops the downloader of the row and shuts it down, returns whether one
+    /// was registered.
+    pub async fn remove(&self, downloader_id: i32) -> bool {
+        let removed = self.downloaders.write().await.remove(&downloader_id);
+        if let Some(removed) = removed {
+            Self::shutdown_downloader(downloader_id, removed.handle).await;
+            true
+        } else {
+            false
+        }
+    }
+
+    async fn shutdown_downloader(downloader_id: i32, handle: TorrentDownloaderHandle) {
+        if let Err(err) = handle.shutdown().await {
+            tracing::error!(
+                err = ?err,
+                downloader_id,
+                "Failed to shutdown downloader"
+            );
+        }
+    }
+
+    /// Remote downloaders keep their state here rather than next to their
+    /// save path, which is a path on the remote host.
+    async fn build_state_dir(&self, downloader_id: i32) -> RecorderResult<String> {
+        let state_dir = self.data_dir.join(downloader_id.to_string());
+        tokio::fs::create_dir_all(&state_dir).await?;
+        Ok(state_dir.to_string_lossy().into_owned())
+    }
+
+    async fn build_downloader(
+        &self,
+        model: &downloaders::Model,
+    ) -> RecorderResult<TorrentDownloaderHandle> {
+        let handle: TorrentDownloaderHandle = match model.category {
+            DownloaderCategory::QBittorrent => {
+                QBittorrentDownloader::from_creation(QBittorrentDownloaderCreation {
+                    endpoint: model.endpoint.clone(),
+                    username: model.username.clone(),
+                    password: model.password.clone(),
+                    save_path: model.save_path.clone(),
+                    subscriber_id: model.subscriber_id,
+                    downloader_id: model.id,
+                    wait_sync_timeout: None,
+                })
+                .await?
+            }
+            DownloaderCategory::Rqbit => {
+                RqbitDownloader::from_creation(RqbitDownloaderCreation {
+                    save_path: model.save_path.clone(),
+                    data_dir: None,
+                    subscriber_id: model.subscriber_id,
+                    downloader_id: model.id,
+                })
+                .await?
+            }
+            DownloaderCategory::Aria2 => {
+                Aria2Downloader::from_creation(Aria2DownloaderCreation {
+                    endpoint: model.endpoint.clone(),
+                    secret: Some(model.password.clone()),
+                    save_path: model.save_path.clone(),
+                    data_dir: Some(self.build_state_dir(model.id).await?),
+                    subscriber_id: model.subscriber_id,
+                    downloader_id: model.id,
+                })
+                .await?
+            }
+            DownloaderCategory::Transmission => {
+                TransmissionDownloader::from_creation(TransmissionDownloaderCreation {
+                    endpoint: model.endpoint.clone(),
+                    username: model.username.clone(),
+                    password: model.password.clone(),
+                    save_path: model.save_path.clone(),
+                    subscriber_id: model.subscriber_id,
+                    downloader_id: model.id,
+                })
+                .await?
+            }
+            DownloaderCategory::Dandanplay => {
+                DandanplayDownloader::from_creation(DandanplayDownloaderCreation {
+                    endpoint: model.endpoint.clone(),
+                    token: Some(model.password.clone()),
+                    save_path: model.save_path.clone(),
+                    data_dir: Some(self.build_state_dir(model.id).await?),
+                    subscriber_id: model.subscriber_id,
+                    downloader_id: model.id,
+                })
+                .await?
+            }
+        };
+
+        Ok(handle)
+    }
+}
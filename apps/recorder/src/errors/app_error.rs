@@ -51,6 +51,8 @@ pub enum RecorderError {
     NetAddrParseError { source: std::net::AddrParseError },
     #[snafu(transparent)]
     RegexError { source: regex::Error },
+    #[snafu(transparent)]
+    PrometheusError { source: prometheus::Error },
     #[snafu(display("Invalid method"))]
     InvalidMethodError,
     #[snafu(display("Invalid header value"))]
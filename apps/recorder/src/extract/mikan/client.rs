@@ -1,6 +1,8 @@
-use std::{fmt::Debug, ops::Deref};
+use std::{fmt::Debug, ops::Deref, sync::Arc};
 
-use fetch::{HttpClient, HttpClientTrait};
+use fetch::{
+    HttpClient, HttpClientTrait, client::HttpClientRedirectConfig, reqwest::cookie::CookieStore,
+};
 use maplit::hashmap;
 use scraper::{Html, Selector};
 use sea_orm::{
@@ -9,7 +11,11 @@ use sea_orm::{
 use url::Url;
 use util::OptDynErr;
 
-use super::{MikanConfig, MikanCredentialForm, constants::MIKAN_ACCOUNT_MANAGE_PAGE_PATH};
+use super::{
+    MikanConfig, MikanCredentialForm,
+    constants::MIKAN_ACCOUNT_MANAGE_PAGE_PATH,
+    mirror::{MikanMirrorMiddleware, MikanMirrorStatus, MikanMirrors},
+};
 use crate::{
     app::AppContextTrait,
     crypto::UserPassCredential,
@@ -23,18 +29,36 @@ pub struct MikanClient {
     http_client: HttpClient,
     base_url: Url,
     origin_url: Url,
+    mirrors: Arc<MikanMirrors>,
     userpass_credential: Option<UserPassCredential>,
 }
 
 impl MikanClient {
-    pub async fn from_config(config: MikanConfig) -> Result<Self, RecorderError> {
-        let http_client = HttpClient::from_config(config.http_client)?;
+    pub async fn from_config(mut config: MikanConfig) -> Result<Self, RecorderError> {
+        // login and session checks rely on seeing mikan's redirects
+        config
+            .http_client
+            .redirect_policy
+            .get_or_insert(HttpClientRedirectConfig::None);
+        let mut http_client = HttpClient::from_config(config.http_client)?;
         let base_url = config.base_url;
         let origin_url = Url::parse(&base_url.origin().unicode_serialization())?;
+        let mirrors = Arc::new(MikanMirrors::new(base_url.clone(), config.mirrors));
+
+        if mirrors.has_mirrors() {
+            let mut fork = http_client.fork();
+            fork.middleware_stack.insert(
+                0,
+                Arc::new(MikanMirrorMiddleware::new(mirrors.clone(), None)),
+            );
+            http_client = HttpClient::from_fork(fork)?;
+        }
+
         Ok(Self {
             http_client,
             base_url,
             origin_url,
+            mirrors,
             userpass_credential: None,
         })
     }
@@ -194,12 +218,26 @@ impl MikanClient {
             fork = fork.attach_user_agent(user_agent);
         }
 
+        if self.mirrors.has_mirrors() {
+            // the mirror middleware inserted first has to send the cookies of
+            // this fork to mirrors
+            let cookie_store = fork
+                .cookie_store
+                .clone()
+                .map(|cookie_store| cookie_store as Arc<dyn CookieStore>);
+            fork.middleware_stack[0] = Arc::new(MikanMirrorMiddleware::new(
+                self.mirrors.clone(),
+                cookie_store,
+            ));
+        }
+
         let userpass_credential_opt = Some(userpass_credential);
 
         Ok(Self {
             http_client: HttpClient::from_fork(fork)?,
             base_url: self.base_url.clone(),
             origin_url: self.origin_url.clone(),
+            mirrors: self.mirrors.clone(),
             userpass_credential: userpass_credential_opt,
         })
     }
@@ -243,6 +281,19 @@ impl MikanClient {
     pub fn client(&self) -> &HttpClient {
         &self.http_client
     }
+
+    pub fn mirror_status(&self) -> Vec<MikanMirrorStatus> {
+        self.mirrors.status()
+    }
+
+    /// Maps a link scraped from any mirror back to `base_url`
+    pub fn normalize_url(&self, url: &Url) -> Url {
+        self.mirrors.normalize_url(url)
+    }
+
+    pub fn normalize_link(&self, link: &str) -> String {
+        self.mirrors.normalize_link(link)
+    }
 }
 
 impl Deref for MikanClient {
@@ -2,8 +2,12 @@ use fetch::HttpClientConfig;
 use serde::{Deserialize, Serialize};
 use url::Url;
 
+use super::mirror::MikanMirrorConfig;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MikanConfig {
     pub http_client: HttpClientConfig,
     pub base_url: Url,
+    #[serde(default)]
+    pub mirrors: MikanMirrorConfig,
 }
@@ -0,0 +1,421 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use fetch::{
+    reqwest::{
+        Request, Response,
+        cookie::CookieStore,
+        header::{COOKIE, SET_COOKIE},
+    },
+    reqwest_middleware::{self, Middleware, Next},
+};
+use http::Extensions;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use url::Url;
+
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MikanMirrorConfig {
+    /// Mirrors of `base_url`, tried in order when the preferred one fails
+    pub urls: Vec<Url>,
+    /// Consecutive failures before a mirror is skipped
+    pub failure_threshold: u32,
+    /// How long a failed mirror is skipped before it is tried again
+    #[serde_as(as = "serde_with::DurationSeconds")]
+    pub cooldown: Duration,
+    /// Timeout applied to requests sent to mikan when mirrors are configured
+    #[serde_as(as = "Option<serde_with::DurationSeconds>")]
+    pub request_timeout: Option<Duration>,
+}
+
+impl Default for MikanMirrorConfig {
+    fn default() -> Self {
+        Self {
+            urls: vec![],
+            failure_threshold: 1,
+            cooldown: Duration::from_secs(60),
+            request_timeout: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MikanMirrorStatus {
+    pub url: Url,
+    pub healthy: bool,
+    pub consecutive_failures: u32,
+}
+
+#[derive(Debug)]
+struct MikanMirror {
+    url: Url,
+    consecutive_failures: AtomicU32,
+    unhealthy_until: Mutex<Option<Instant>>,
+}
+
+impl MikanMirror {
+    fn new(url: Url) -> Self {
+        Self {
+            url,
+            consecutive_failures: AtomicU32::new(0),
+            unhealthy_until: Mutex::new(None),
+        }
+    }
+
+    fn unhealthy_until(&self) -> Option<Instant> {
+        let until = *self.unhealthy_until.lock().unwrap();
+        until.filter(|until| *until > Instant::now())
+    }
+}
+
+fn normalize_base_url(mut url: Url) -> Url {
+    if !url.path().ends_with('/') {
+        let path = format!("{}/", url.path());
+        url.set_path(&path);
+    }
+    url
+}
+
+/// Path of `url` relative to `base`, `None` if `url` is not below `base`.
+fn relative_path<'a>(url: &'a Url, base: &Url) -> Option<&'a str> {
+    if url.origin() != base.origin() {
+        return None;
+    }
+    url.path()
+        .strip_prefix(base.path())
+        .or_else(|| (format!("{}/", url.path()) == base.path()).then_some(""))
+}
+
+/// Moves `url` from below `from` to below `to`, keeping the relative path,
+/// query and fragment.
+fn rebase_url(url: &Url, from: &Url, to: &Url) -> Option<Url> {
+    let mut rebased = to.join(relative_path(url, from)?).ok()?;
+    rebased.set_query(url.query());
+    rebased.set_fragment(url.fragment());
+    Some(rebased)
+}
+
+/// Health of the canonical mikan base url and its mirrors. Urls are always
+/// built against the canonical base url, [`MikanMirrorMiddleware`] moves
+/// requests to the preferred healthy mirror at send time.
+#[derive(Debug)]
+pub struct MikanMirrors {
+    mirrors: Vec<MikanMirror>,
+    config: MikanMirrorConfig,
+}
+
+impl MikanMirrors {
+    pub fn new(base_url: Url, config: MikanMirrorConfig) -> Self {
+        let mirrors = std::iter::once(base_url)
+            .chain(config.urls.iter().cloned())
+            .map(normalize_base_url)
+            .map(MikanMirror::new)
+            .collect();
+        Self { mirrors, config }
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.mirrors[0].url
+    }
+
+    pub fn has_mirrors(&self) -> bool {
+        self.mirrors.len() > 1
+    }
+
+    pub fn status(&self) -> Vec<MikanMirrorStatus> {
+        self.mirrors
+            .iter()
+            .map(|m| MikanMirrorStatus {
+                url: m.url.clone(),
+                healthy: m.unhealthy_until().is_none(),
+                consecutive_failures: m.consecutive_failures.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+
+    /// Mirror indexes in the order they should be tried, healthy mirrors in
+    /// configured order first, then the rest by how soon they recover.
+    fn candidates(&self) -> Vec<usize> {
+        let mut healthy = vec![];
+        let mut unhealthy = vec![];
+        for (idx, mirror) in self.mirrors.iter().enumerate() {
+            match mirror.unhealthy_until() {
+                None => healthy.push(idx),
+                Some(until) => unhealthy.push((until, idx)),
+            }
+        }
+        unhealthy.sort();
+        healthy
+            .into_iter()
+            .chain(unhealthy.into_iter().map(|(_, idx)| idx))
+            .collect()
+    }
+
+    fn record_success(&self, idx: usize) {
+        let mirror = &self.mirrors[idx];
+        mirror.consecutive_failures.store(0, Ordering::Relaxed);
+        *mirror.unhealthy_until.lock().unwrap() = None;
+    }
+
+    fn record_failure(&self, idx: usize) {
+        let mirror = &self.mirrors[idx];
+        let failures = mirror.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures >= self.config.failure_threshold {
+            *mirror.unhealthy_until.lock().unwrap() = Some(Instant::now() + self.config.cooldown);
+        }
+    }
+
+    fn mirror_index_of(&self, url: &Url) -> Option<usize> {
+        self.mirrors
+            .iter()
+            .position(|m| relative_path(url, &m.url).is_some())
+    }
+
+    /// Maps a url served by any mirror back to the canonical base url, so
+    /// stored links don't depend on the mirror that was active.
+    pub fn normalize_url(&self, url: &Url) -> Url {
+        self.mirrors
+            .iter()
+            .skip(1)
+            .find_map(|m| rebase_url(url, &m.url, self.base_url()))
+            .unwrap_or_else(|| url.clone())
+    }
+
+    pub fn normalize_link(&self, link: &str) -> String {
+        match Url::parse(link) {
+            Ok(url) => self.normalize_url(&url).to_string(),
+            Err(_) => link.to_string(),
+        }
+    }
+}
+
+fn is_mirror_failure(result: &reqwest_middleware::Result<Response>) -> bool {
+    match result {
+        Ok(response) => response.status().is_server_error(),
+        Err(reqwest_middleware::Error::Reqwest(err)) => err.is_connect() || err.is_timeout(),
+        Err(_) => false,
+    }
+}
+
+/// Sends requests to the preferred healthy mirror. Cookies are kept for the
+/// canonical base url, so the logged in session is sent along to mirrors and
+/// cookies set by a mirror are stored back for the canonical base url.
+pub struct MikanMirrorMiddleware {
+    mirrors: Arc<MikanMirrors>,
+    cookie_store: Option<Arc<dyn CookieStore>>,
+}
+
+impl MikanMirrorMiddleware {
+    pub fn new(mirrors: Arc<MikanMirrors>, cookie_store: Option<Arc<dyn CookieStore>>) -> Self {
+        Self {
+            mirrors,
+            cookie_store,
+        }
+    }
+
+    fn attach_cookies(&self, mirror_req: &mut Request, source_url: &Url) {
+        if let Some(cookie_store) = self.cookie_store.as_ref()
+            && !mirror_req.headers().contains_key(COOKIE)
+            && let Some(cookies) = cookie_store.cookies(source_url)
+        {
+            mirror_req.headers_mut().insert(COOKIE, cookies);
+        }
+    }
+
+    fn store_cookies(&self, response: &Response, source_url: &Url) {
+        if let Some(cookie_store) = self.cookie_store.as_ref() {
+            cookie_store.set_cookies(
+                &mut response.headers().get_all(SET_COOKIE).iter(),
+                source_url,
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl Middleware for MikanMirrorMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &'_ mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let Some(source_idx) = self.mirrors.mirror_index_of(req.url()) else {
+            return next.run(req, extensions).await;
+        };
+        let source_base_url = &self.mirrors.mirrors[source_idx].url;
+
+        let candidates = self.mirrors.candidates();
+        let last = candidates.len() - 1;
+
+        for (attempt, idx) in candidates.into_iter().enumerate() {
+            let mirror = &self.mirrors.mirrors[idx];
+            let Some(mut mirror_req) = req.try_clone() else {
+                return next.run(req, extensions).await;
+            };
+            let rebased = idx != source_idx;
+            if rebased && let Some(url) = rebase_url(req.url(), source_base_url, &mirror.url) {
+                *mirror_req.url_mut() = url;
+                self.attach_cookies(&mut mirror_req, req.url());
+            }
+            if let Some(timeout) = self.mirrors.config.request_timeout
+                && mirror_req.timeout().is_none()
+            {
+                *mirror_req.timeout_mut() = Some(timeout);
+            }
+
+            let result = next.clone().run(mirror_req, extensions).await;
+
+            if rebased && let Ok(response) = &result {
+                self.store_cookies(response, req.url());
+            }
+
+            if !is_mirror_failure(&result) {
+                self.mirrors.record_success(idx);
+                return result;
+            }
+
+            self.mirrors.record_failure(idx);
+            if attempt == last {
+                return result;
+            }
+            tracing::warn!(
+                mirror = %mirror.url,
+                url = %req.url(),
+                "Mikan mirror failed, trying the next one"
+            );
+        }
+
+        unreachable!("mikan mirrors always contain the base url")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fetch::{HttpClient, HttpClientConfig};
+    use http::HeaderValue;
+
+    use super::*;
+    use crate::errors::RecorderResult;
+
+    fn build_mirrors() -> MikanMirrors {
+        MikanMirrors::new(
+            Url::parse("https://mikanani.me").unwrap(),
+            MikanMirrorConfig {
+                urls: vec![
+                    Url::parse("https://mikanime.tv").unwrap(),
+                    Url::parse("https://proxy.example.com/mikan").unwrap(),
+                ],
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_mikan_mirrors_normalize_url() {
+        let mirrors = build_mirrors();
+
+        assert_eq!(
+            mirrors.normalize_link("https://mikanime.tv/Home/Episode/abc?x=1#y"),
+            "https://mikanani.me/Home/Episode/abc?x=1#y"
+        );
+        assert_eq!(
+            mirrors.normalize_link("https://proxy.example.com/mikan/Download/a.torrent"),
+            "https://mikanani.me/Download/a.torrent"
+        );
+        assert_eq!(
+            mirrors.normalize_link("https://proxy.example.com/other/a.torrent"),
+            "https://proxy.example.com/other/a.torrent"
+        );
+        assert_eq!(
+            mirrors.normalize_link("https://mikanani.me/Home/Bangumi/1"),
+            "https://mikanani.me/Home/Bangumi/1"
+        );
+    }
+
+    #[test]
+    fn test_mikan_mirrors_failover_order() {
+        let mirrors = build_mirrors();
+        assert_eq!(mirrors.candidates(), vec![0, 1, 2]);
+
+        mirrors.record_failure(0);
+        assert_eq!(mirrors.candidates(), vec![1, 2, 0]);
+        assert!(!mirrors.status()[0].healthy);
+
+        mirrors.record_failure(1);
+        assert_eq!(mirrors.candidates(), vec![2, 0, 1]);
+
+        mirrors.record_success(0);
+        assert_eq!(mirrors.candidates(), vec![0, 2, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_mikan_mirror_middleware_fails_over_with_cookies() -> RecorderResult<()> {
+        let mut primary = mockito::Server::new_async().await;
+        let mut mirror = mockito::Server::new_async().await;
+        let primary_url = Url::parse(&primary.url())?;
+
+        let primary_mock = primary
+            .mock("GET", "/Home/MyBangumi")
+            .with_status(503)
+            .create_async()
+            .await;
+        let mirror_mock = mirror
+            .mock("GET", "/Home/MyBangumi")
+            .match_header("cookie", "session=abc")
+            .with_header("set-cookie", "refreshed=1; Path=/")
+            .with_body("logged in")
+            .create_async()
+            .await;
+
+        let mirrors = Arc::new(MikanMirrors::new(
+            primary_url.clone(),
+            MikanMirrorConfig {
+                urls: vec![Url::parse(&mirror.url())?],
+                ..Default::default()
+            },
+        ));
+
+        let mut fork = HttpClient::from_config(HttpClientConfig::default())?
+            .fork()
+            .attach_cookies(None)?;
+        let cookie_store = fork.cookie_store.clone().expect("should have cookie store");
+        cookie_store.set_cookies(
+            &mut std::iter::once(&HeaderValue::from_static("session=abc; Path=/")),
+            &primary_url,
+        );
+        fork.middleware_stack.insert(
+            0,
+            Arc::new(MikanMirrorMiddleware::new(
+                mirrors.clone(),
+                Some(cookie_store.clone() as Arc<dyn CookieStore>),
+            )),
+        );
+        let client = HttpClient::from_fork(fork)?;
+
+        let response = client
+            .get(primary_url.join("/Home/MyBangumi")?)
+            .send()
+            .await?;
+
+        assert_eq!(response.text().await?, "logged in");
+        primary_mock.assert_async().await;
+        mirror_mock.assert_async().await;
+        assert!(!mirrors.status()[0].healthy);
+
+        let cookies = cookie_store
+            .cookies(&primary_url)
+            .expect("should keep cookies for the canonical url");
+        assert!(cookies.to_str().unwrap().contains("refreshed=1"));
+
+        Ok(())
+    }
+}
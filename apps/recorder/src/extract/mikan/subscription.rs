@@ -32,7 +32,7 @@ use crate::{
         },
     },
     models::{
-        bangumi, episodes, subscription_bangumi, subscription_episode,
+        bangumi, downloads, episodes, subscription_bangumi, subscription_episode,
         subscriptions::{self, SubscriptionTrait},
     },
 };
@@ -196,8 +196,13 @@ impl SubscriptionTrait for MikanSubscriberSubscription {
         .await
     }
 
-    async fn sync_sources(&self, _ctx: Arc<dyn AppContextTrait>) -> RecorderResult<()> {
-        Ok(())
+    async fn sync_sources(&self, ctx: Arc<dyn AppContextTrait>) -> RecorderResult<()> {
+        downloads::Model::add_downloads_for_subscription(
+            ctx.as_ref(),
+            self.get_subscriber_id(),
+            self.get_subscription_id(),
+        )
+        .await
     }
 
     fn try_from_model(model: &subscriptions::Model) -> RecorderResult<Self> {
@@ -319,37 +324,17 @@ impl SubscriptionTrait for MikanSeasonSubscription {
     }
 
     async fn sync_feeds_full(&self, ctx: Arc<dyn AppContextTrait>) -> RecorderResult<()> {
-        self.sync_sources(ctx.clone()).await?;
+        self.sync_bangumi_list_from_source_url(ctx.clone()).await?;
         self.sync_feeds_incremental(ctx).await
     }
 
     async fn sync_sources(&self, ctx: Arc<dyn AppContextTrait>) -> RecorderResult<()> {
-        let bangumi_meta_list = self.get_bangumi_meta_stream_from_source_url(ctx.clone());
-
-        pin_mut!(bangumi_meta_list);
-
-        while let Some(bangumi_meta) = bangumi_meta_list.try_next().await? {
-            let bangumi_hash = bangumi_meta.bangumi_hash();
-            bangumi::Model::get_or_insert_from_mikan(
-                ctx.as_ref(),
-                bangumi_hash,
-                self.get_subscriber_id(),
-                self.get_subscription_id(),
-                async || {
-                    let bangumi_am = bangumi::ActiveModel::from_mikan_bangumi_meta(
-                        ctx.as_ref(),
-                        bangumi_meta,
-                        self.get_subscriber_id(),
-                        self.get_subscription_id(),
-                    )
-                    .await?;
-                    Ok(bangumi_am)
-                },
-            )
-            .await?;
-        }
-
-        Ok(())
+        downloads::Model::add_downloads_for_subscription(
+            ctx.as_ref(),
+            self.get_subscriber_id(),
+            self.get_subscription_id(),
+        )
+        .await
     }
 
     fn try_from_model(model: &subscriptions::Model) -> RecorderResult<Self> {
@@ -385,6 +370,39 @@ impl SubscriptionTrait for MikanSeasonSubscription {
 }
 
 impl MikanSeasonSubscription {
+    #[tracing::instrument(err, skip(ctx))]
+    async fn sync_bangumi_list_from_source_url(
+        &self,
+        ctx: Arc<dyn AppContextTrait>,
+    ) -> RecorderResult<()> {
+        let bangumi_meta_list = self.get_bangumi_meta_stream_from_source_url(ctx.clone());
+
+        pin_mut!(bangumi_meta_list);
+
+        while let Some(bangumi_meta) = bangumi_meta_list.try_next().await? {
+            let bangumi_hash = bangumi_meta.bangumi_hash();
+            bangumi::Model::get_or_insert_from_mikan(
+                ctx.as_ref(),
+                bangumi_hash,
+                self.get_subscriber_id(),
+                self.get_subscription_id(),
+                async || {
+                    let bangumi_am = bangumi::ActiveModel::from_mikan_bangumi_meta(
+                        ctx.as_ref(),
+                        bangumi_meta,
+                        self.get_subscriber_id(),
+                        self.get_subscription_id(),
+                    )
+                    .await?;
+                    Ok(bangumi_am)
+                },
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
     pub fn get_bangumi_meta_stream_from_source_url(
         &self,
         ctx: Arc<dyn AppContextTrait>,
@@ -490,8 +508,13 @@ impl SubscriptionTrait for MikanBangumiSubscription {
         self.sync_feeds_incremental(_ctx).await
     }
 
-    async fn sync_sources(&self, _ctx: Arc<dyn AppContextTrait>) -> RecorderResult<()> {
-        Ok(())
+    async fn sync_sources(&self, ctx: Arc<dyn AppContextTrait>) -> RecorderResult<()> {
+        downloads::Model::add_downloads_for_subscription(
+            ctx.as_ref(),
+            self.get_subscriber_id(),
+            self.get_subscription_id(),
+        )
+        .await
     }
 
     fn try_from_model(model: &subscriptions::Model) -> RecorderResult<Self> {
@@ -547,23 +570,26 @@ impl MikanBangumiSubscription {
 #[allow(unused_variables)]
 mod tests {
 
+    use std::{assert_matches::assert_matches, collections::HashSet};
+
+    use downloader::fake::FakeOperation;
     use rstest::{fixture, rstest};
     use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait};
     use tracing::Level;
 
     use crate::{
-        errors::RecorderResult,
+        errors::{RecorderError, RecorderResult},
         extract::mikan::{
             MikanBangumiHash, MikanSeasonFlowUrlMeta, MikanSeasonStr,
             MikanSubscriberSubscriptionUrlMeta,
         },
         models::{
-            bangumi, episodes,
+            bangumi, downloaders, downloads, episodes,
             subscriptions::{self, SubscriptionTrait},
         },
         test_utils::{
-            app::TestingPreset, mikan::build_testing_mikan_credential_form,
-            tracing::try_init_testing_tracing,
+            app::TestingPreset, downloader::register_testing_fake_downloader,
+            mikan::build_testing_mikan_credential_form, tracing::try_init_testing_tracing,
         },
     };
 
@@ -753,4 +779,193 @@ mod tests {
 
         Ok(())
     }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_mikan_subscriber_subscription_sync_sources_without_downloader(
+        before_each: (),
+    ) -> RecorderResult<()> {
+        let mut preset = TestingPreset::default().await?;
+
+        let app_ctx = preset.app_ctx.clone();
+
+        let mikan_server = &mut preset.mikan_server;
+
+        let _resources_mock = mikan_server.mock_resources_with_doppel();
+
+        let _login_mock = mikan_server.mock_get_login_page();
+
+        let subscriber_id = 1;
+
+        let subscription_am = subscriptions::ActiveModel {
+            display_name: ActiveValue::Set("test subscription".to_string()),
+            subscriber_id: ActiveValue::Set(subscriber_id),
+            category: ActiveValue::Set(subscriptions::SubscriptionCategory::MikanSubscriber),
+            source_url: ActiveValue::Set(
+                MikanSubscriberSubscriptionUrlMeta {
+                    mikan_subscription_token: "test".into(),
+                }
+                .build_rss_url(mikan_server.base_url().clone())
+                .to_string(),
+            ),
+            enabled: ActiveValue::Set(true),
+            ..Default::default()
+        };
+
+        let subscription_model = subscription_am.insert(app_ctx.db()).await?;
+
+        let subscription = subscriptions::Subscription::try_from_model(&subscription_model)?;
+
+        subscription.sync_feeds_incremental(app_ctx.clone()).await?;
+
+        let undownloaded_episode_list =
+            episodes::Model::get_undownloaded_episode_list_from_subscription(
+                app_ctx.as_ref(),
+                subscription_model.id,
+            )
+            .await?;
+
+        assert!(!undownloaded_episode_list.is_empty());
+        assert!(
+            undownloaded_episode_list
+                .iter()
+                .all(|(episode, bangumi)| episode.enclosure_url().is_some() && bangumi.is_some())
+        );
+
+        let result = subscription.sync_sources(app_ctx.clone()).await;
+
+        assert_matches!(result, Err(RecorderError::ModelEntityNotFound { .. }));
+
+        let download_list = downloads::Entity::find().all(app_ctx.db()).await?;
+
+        assert!(download_list.is_empty());
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_mikan_subscriber_subscription_sync_sources_with_fake_downloader(
+        before_each: (),
+    ) -> RecorderResult<()> {
+        let mut preset = TestingPreset::default().await?;
+
+        let app_ctx = preset.app_ctx.clone();
+
+        let mikan_server = &mut preset.mikan_server;
+
+        let _resources_mock = mikan_server.mock_resources_with_doppel();
+
+        let _login_mock = mikan_server.mock_get_login_page();
+
+        let subscriber_id = 1;
+
+        let downloader_model = downloaders::ActiveModel {
+            category: ActiveValue::Set(downloaders::DownloaderCategory::QBittorrent),
+            endpoint: ActiveValue::Set("http://127.0.0.1:8080".to_string()),
+            username: ActiveValue::Set("admin".to_string()),
+            password: ActiveValue::Set("adminadmin".to_string()),
+            subscriber_id: ActiveValue::Set(subscriber_id),
+            save_path: ActiveValue::Set("/downloads".to_string()),
+            ..Default::default()
+        }
+        .insert(app_ctx.db())
+        .await?;
+
+        let fake_downloader =
+            register_testing_fake_downloader(app_ctx.downloader(), &downloader_model).await;
+
+        let subscription_am = subscriptions::ActiveModel {
+            display_name: ActiveValue::Set("test subscription".to_string()),
+            subscriber_id: ActiveValue::Set(subscriber_id),
+            category: ActiveValue::Set(subscriptions::SubscriptionCategory::MikanSubscriber),
+            source_url: ActiveValue::Set(
+                MikanSubscriberSubscriptionUrlMeta {
+                    mikan_subscription_token: "test".into(),
+                }
+                .build_rss_url(mikan_server.base_url().clone())
+                .to_string(),
+            ),
+            enabled: ActiveValue::Set(true),
+            ..Default::default()
+        };
+
+        let subscription_model = subscription_am.insert(app_ctx.db()).await?;
+
+        let subscription = subscriptions::Subscription::try_from_model(&subscription_model)?;
+
+        subscription.sync_feeds_incremental(app_ctx.clone()).await?;
+
+        {
+            fake_downloader.fail_next(FakeOperation::Add, "downloader is unreachable");
+
+            let result = subscription.sync_sources(app_ctx.clone()).await;
+
+            assert!(result.is_err());
+            assert!(fake_downloader.tasks().is_empty());
+
+            let download_list = downloads::Entity::find().all(app_ctx.db()).await?;
+
+            assert!(download_list.is_empty());
+        }
+
+        subscription.sync_sources(app_ctx.clone()).await?;
+
+        let download_list = downloads::Entity::find().all(app_ctx.db()).await?;
+
+        assert!(!download_list.is_empty());
+
+        let fake_tasks = fake_downloader.tasks();
+
+        assert_eq!(
+            download_list
+                .iter()
+                .filter_map(|d| d.hash.clone())
+                .collect::<HashSet<_>>(),
+            fake_tasks
+                .iter()
+                .map(|t| t.hash_info.clone())
+                .collect::<HashSet<_>>()
+        );
+
+        for download in &download_list {
+            assert_eq!(download.status, downloads::DownloadStatus::Pending);
+            assert_eq!(download.downloader_id, downloader_model.id);
+            assert_eq!(download.subscriber_id, subscriber_id);
+
+            let save_path = download
+                .save_path
+                .as_deref()
+                .expect("should have save path");
+            assert!(save_path.starts_with("/downloads/"));
+
+            let fake_task = fake_downloader
+                .task(download.hash.as_deref().expect("should have hash"))
+                .expect("should have been added to the downloader");
+            assert_eq!(fake_task.save_path.as_str(), save_path);
+        }
+
+        // episodes already downloading are not added again
+        subscription.sync_sources(app_ctx.clone()).await?;
+
+        assert_eq!(
+            downloads::Entity::find().all(app_ctx.db()).await?.len(),
+            download_list.len()
+        );
+        // the failed add and one add per bangumi save path
+        assert_eq!(
+            fake_downloader
+                .history()
+                .iter()
+                .filter(|(operation, _)| *operation == FakeOperation::Add)
+                .count(),
+            1 + download_list
+                .iter()
+                .map(|d| d.save_path.clone())
+                .collect::<HashSet<_>>()
+                .len()
+        );
+
+        Ok(())
+    }
 }
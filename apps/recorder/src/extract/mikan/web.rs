@@ -1,10 +1,12 @@
 use std::{borrow::Cow, fmt, str::FromStr, sync::Arc};
 
 use async_stream::try_stream;
-use bytes::Bytes;
 use chrono::{DateTime, Utc};
 use downloader::bittorrent::defs::BITTORRENT_MIME_TYPE;
-use fetch::{html::fetch_html, image::fetch_image};
+use fetch::{
+    FetchImageOptions, FetchStreamOptions, FetchedImage, ImageHeader, fetch_image_stream,
+    fetch_image_with_options, html::fetch_html, image::ImageFormat,
+};
 use futures::{Stream, TryStreamExt, pin_mut};
 use html_escape::decode_html_entities;
 use scraper::{Html, Selector};
@@ -24,10 +26,11 @@ use crate::{
             MIKAN_BANGUMI_EXPAND_SUBSCRIBED_PAGE_PATH, MIKAN_BANGUMI_HOMEPAGE_PATH,
             MIKAN_BANGUMI_ID_QUERY_KEY, MIKAN_BANGUMI_POSTER_PATH, MIKAN_BANGUMI_RSS_PATH,
             MIKAN_EPISODE_HOMEPAGE_PATH, MIKAN_FANSUB_HOMEPAGE_PATH, MIKAN_FANSUB_ID_QUERY_KEY,
-            MIKAN_POSTER_BUCKET_KEY, MIKAN_SEASON_FLOW_PAGE_PATH, MIKAN_SEASON_STR_QUERY_KEY,
-            MIKAN_SUBSCRIBER_SUBSCRIPTION_RSS_PATH, MIKAN_SUBSCRIBER_SUBSCRIPTION_TOKEN_QUERY_KEY,
-            MIKAN_UNKNOWN_FANSUB_ID, MIKAN_YEAR_QUERY_KEY, MikanClient,
-            build_mikan_bangumi_subscription_rss_url, build_mikan_subscriber_subscription_rss_url,
+            MIKAN_POSTER_BUCKET_KEY, MIKAN_POSTER_MAX_SIZE, MIKAN_SEASON_FLOW_PAGE_PATH,
+            MIKAN_SEASON_STR_QUERY_KEY, MIKAN_SUBSCRIBER_SUBSCRIPTION_RSS_PATH,
+            MIKAN_SUBSCRIBER_SUBSCRIPTION_TOKEN_QUERY_KEY, MIKAN_UNKNOWN_FANSUB_ID,
+            MIKAN_YEAR_QUERY_KEY, MikanClient, build_mikan_bangumi_subscription_rss_url,
+            build_mikan_subscriber_subscription_rss_url,
         },
     },
     media::{
@@ -275,6 +278,10 @@ impl MikanEpisodeMeta {
 pub struct MikanBangumiPosterMeta {
     pub origin_poster_src: Url,
     pub poster_src: Option<String>,
+    /// Read from the poster header, `None` if a stored poster can not be read
+    pub poster_format: Option<ImageFormat>,
+    pub poster_width: Option<u32>,
+    pub poster_height: Option<u32>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -773,8 +780,16 @@ pub async fn scrape_mikan_bangumi_index_meta_from_bangumi_homepage_url(
 pub async fn scrape_mikan_poster_data_from_image_url(
     mikan_client: &MikanClient,
     origin_poster_src_url: Url,
-) -> RecorderResult<Bytes> {
-    let poster_data = fetch_image(mikan_client, origin_poster_src_url.clone()).await?;
+) -> RecorderResult<FetchedImage> {
+    let poster_data = fetch_image_with_options(
+        mikan_client,
+        origin_poster_src_url.clone(),
+        FetchImageOptions {
+            max_bytes: Some(MIKAN_POSTER_MAX_SIZE),
+            ..Default::default()
+        },
+    )
+    .await?;
     Ok(poster_data)
 }
 
@@ -796,22 +811,49 @@ pub async fn scrape_mikan_poster_meta_from_image_url(
             .replace(&format!("{MIKAN_BANGUMI_POSTER_PATH}/"), ""),
     );
     let meta = if let Some(poster_src) = storage_service.exists(&storage_path).await? {
+        let poster_header = match storage_service.read(storage_path.as_str()).await {
+            Ok(poster_data) => {
+                ImageHeader::from_bytes(&poster_data.to_bytes(), &Default::default()).ok()
+            }
+            Err(err) => {
+                tracing::warn!(error = %err, path = %storage_path, "Failed to read stored poster");
+                None
+            }
+        };
+
         MikanBangumiPosterMeta {
             origin_poster_src: origin_poster_src_url,
             poster_src: Some(poster_src.to_string()),
+            poster_format: poster_header.map(|header| header.format),
+            poster_width: poster_header.map(|header| header.width),
+            poster_height: poster_header.map(|header| header.height),
         }
     } else {
-        let poster_data =
-            scrape_mikan_poster_data_from_image_url(mikan_client, origin_poster_src_url.clone())
-                .await?;
+        let (poster_header, poster_stream) = fetch_image_stream(
+            mikan_client,
+            origin_poster_src_url.clone(),
+            FetchImageOptions {
+                max_bytes: Some(MIKAN_POSTER_MAX_SIZE),
+                ..Default::default()
+            },
+            FetchStreamOptions {
+                content_types: vec![String::from("image/*")],
+                max_resume_attempts: 3,
+                ..Default::default()
+            },
+        )
+        .await?;
 
         let poster_str = storage_service
-            .write(storage_path.clone(), poster_data)
+            .write_stream(storage_path.clone(), poster_stream)
             .await?;
 
         MikanBangumiPosterMeta {
             origin_poster_src: origin_poster_src_url,
             poster_src: Some(poster_str.to_string()),
+            poster_format: Some(poster_header.format),
+            poster_width: Some(poster_header.width),
+            poster_height: Some(poster_header.height),
         }
     };
 
@@ -1138,8 +1180,11 @@ mod test {
 
         resources_mock.shared_resource_mock.expect(1);
 
+        assert_eq!(bgm_poster_data.format, ImageFormat::Jpeg);
+        assert!(bgm_poster_data.width > 0 && bgm_poster_data.height > 0);
+
         let image = {
-            let c = Cursor::new(bgm_poster_data);
+            let c = Cursor::new(bgm_poster_data.bytes);
             ImageReader::new(c)
         };
         let image_format = image.with_guessed_format().ok().and_then(|i| i.format());
@@ -1169,6 +1214,10 @@ mod test {
 
         resources_mock.shared_resource_mock.expect(1);
 
+        assert_eq!(bgm_poster.poster_format, Some(ImageFormat::Jpeg));
+        assert!(bgm_poster.poster_width.is_some_and(|width| width > 0));
+        assert!(bgm_poster.poster_height.is_some_and(|height| height > 0));
+
         let storage_service = app_ctx.storage();
 
         let storage_fullname = storage_service.build_public_object_path(
@@ -1196,6 +1245,13 @@ mod test {
             "should start with valid jpeg data magic number"
         );
 
+        let stored_poster = scrape_mikan_poster_meta_from_image_url(
+            app_ctx.as_ref(),
+            bgm_poster.origin_poster_src.clone(),
+        )
+        .await?;
+        assert_eq!(stored_poster, bgm_poster);
+
         Ok(())
     }
 
@@ -15,6 +15,7 @@ pub fn register_downloads_to_schema_context(context: &mut BuilderContext) {
 pub fn register_downloads_to_schema_builder(mut builder: SeaographyBuilder) -> SeaographyBuilder {
     builder.register_enumeration::<downloads::DownloadStatus>();
     builder.register_enumeration::<downloads::DownloadMime>();
+    builder.register_enumeration::<downloads::DownloadSeedingAction>();
     builder = register_entity_default_writable!(builder, downloads, false);
 
     builder
@@ -17,12 +17,14 @@ pub mod auth;
 pub mod cache;
 pub mod crypto;
 pub mod database;
+pub mod download;
 pub mod errors;
 pub mod extract;
 pub mod graphql;
 pub mod logger;
 pub mod media;
 pub mod message;
+pub mod metrics;
 pub mod migrations;
 pub mod models;
 pub mod storage;
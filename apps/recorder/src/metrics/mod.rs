@@ -0,0 +1,223 @@
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use lazy_static::lazy_static;
+use prometheus::{
+    Gauge, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+
+use crate::{app::AppContextTrait, errors::RecorderResult, models::cron};
+
+/// Recorder metrics, registered in the prometheus default registry next to
+/// the outbound http metrics of [`fetch`].
+#[derive(Clone)]
+pub struct RecorderMetrics {
+    /// Labels: `method`, `route`, `status`
+    pub http_requests_total: IntCounterVec,
+    /// Labels: `method`, `route`
+    pub http_request_duration_seconds: HistogramVec,
+    /// Labels: `task_type`, `result` (`success` or `failure`)
+    pub subscription_syncs_total: IntCounterVec,
+    /// Labels: `state` (`idle`, `in_use` or `max`), collected on scrape
+    pub db_pool_connections: IntGaugeVec,
+    /// Labels: `queue`, `task_type`, `status`, collected on scrape
+    pub task_jobs: IntGaugeVec,
+    /// Collected on scrape
+    pub cron_overdue: IntGauge,
+    /// Collected on scrape
+    pub cron_lag_seconds: Gauge,
+}
+
+impl RecorderMetrics {
+    pub fn new() -> prometheus::Result<Self> {
+        Ok(Self {
+            http_requests_total: IntCounterVec::new(
+                Opts::new(
+                    "recorder_http_requests_total",
+                    "Http requests served by method, route and status",
+                ),
+                &["method", "route", "status"],
+            )?,
+            http_request_duration_seconds: HistogramVec::new(
+                HistogramOpts::new(
+                    "recorder_http_request_duration_seconds",
+                    "Http request latency by method and route",
+                ),
+                &["method", "route"],
+            )?,
+            subscription_syncs_total: IntCounterVec::new(
+                Opts::new(
+                    "recorder_subscription_syncs_total",
+                    "Subscription sync tasks by task type and result",
+                ),
+                &["task_type", "result"],
+            )?,
+            db_pool_connections: IntGaugeVec::new(
+                Opts::new(
+                    "recorder_db_pool_connections",
+                    "Database pool connections by state",
+                ),
+                &["state"],
+            )?,
+            task_jobs: IntGaugeVec::new(
+                Opts::new(
+                    "recorder_task_jobs",
+                    "Task queue jobs by queue, task type and status",
+                ),
+                &["queue", "task_type", "status"],
+            )?,
+            cron_overdue: IntGauge::new(
+                "recorder_cron_overdue",
+                "Enabled crons whose next run has passed without being triggered",
+            )?,
+            cron_lag_seconds: Gauge::new(
+                "recorder_cron_lag_seconds",
+                "How long the most overdue cron has been waiting to be triggered",
+            )?,
+        })
+    }
+
+    pub fn register(&self, registry: &Registry) -> prometheus::Result<()> {
+        registry.register(Box::new(self.http_requests_total.clone()))?;
+        registry.register(Box::new(self.http_request_duration_seconds.clone()))?;
+        registry.register(Box::new(self.subscription_syncs_total.clone()))?;
+        registry.register(Box::new(self.db_pool_connections.clone()))?;
+        registry.register(Box::new(self.task_jobs.clone()))?;
+        registry.register(Box::new(self.cron_overdue.clone()))?;
+        registry.register(Box::new(self.cron_lag_seconds.clone()))?;
+        Ok(())
+    }
+
+    pub fn observe_http_request(
+        &self,
+        method: &str,
+        route: &str,
+        status: StatusCode,
+        elapsed: Duration,
+    ) {
+        self.http_requests_total
+            .with_label_values(&[method, route, status.as_str()])
+            .inc();
+        self.http_request_duration_seconds
+            .with_label_values(&[method, route])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn observe_subscription_sync(&self, task_type: &str, success: bool) {
+        self.subscription_syncs_total
+            .with_label_values(&[task_type, if success { "success" } else { "failure" }])
+            .inc();
+    }
+
+    /// Refreshes the gauges that are read from the database and the
+    /// connection pool, a failing source is logged and left stale so the
+    /// rest of the scrape still succeeds.
+    pub async fn collect(&self, ctx: &dyn AppContextTrait) {
+        let pool_stats = ctx.db().pool_stats();
+        self.db_pool_connections
+            .with_label_values(&["idle"])
+            .set(pool_stats.idle as i64);
+        self.db_pool_connections
+            .with_label_values(&["in_use"])
+            .set(pool_stats.size.saturating_sub(pool_stats.idle as u32) as i64);
+        self.db_pool_connections
+            .with_label_values(&["max"])
+            .set(pool_stats.max_connections as i64);
+
+        match ctx.task().queue_stats().await {
+            Ok(queue_stats) => {
+                self.task_jobs.reset();
+                for stats in queue_stats {
+                    self.task_jobs
+                        .with_label_values(&[stats.queue, &stats.task_type, &stats.status])
+                        .set(stats.count);
+                }
+            }
+            Err(err) => tracing::error!(
+                err.msg = %err,
+                err.detail = ?err,
+                "Failed to collect task queue metrics"
+            ),
+        }
+
+        match cron::Model::due_lag(ctx).await {
+            Ok((overdue, lag)) => {
+                self.cron_overdue.set(overdue);
+                self.cron_lag_seconds
+                    .set(lag.num_milliseconds() as f64 / 1000.0);
+            }
+            Err(err) => tracing::error!(
+                err.msg = %err,
+                err.detail = ?err,
+                "Failed to collect cron metrics"
+            ),
+        }
+    }
+}
+
+lazy_static! {
+    static ref RECORDER_METRICS: RecorderMetrics = {
+        let metrics = RecorderMetrics::new().expect("Failed to create recorder metrics");
+        metrics
+            .register(prometheus::default_registry())
+            .expect("Failed to register recorder metrics");
+        metrics
+    };
+}
+
+pub fn recorder_metrics() -> &'static RecorderMetrics {
+    &RECORDER_METRICS
+}
+
+/// Collects the scrape time gauges and renders every metric in the default
+/// registry in the prometheus text format.
+pub async fn gather_metrics(ctx: &dyn AppContextTrait) -> RecorderResult<String> {
+    // Registers the outbound http metrics before the first request is sent
+    fetch::client::http_client_metrics();
+    recorder_metrics().collect(ctx).await;
+
+    let body = TextEncoder::new().encode_to_string(&prometheus::gather())?;
+
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recorder_metrics_register() {
+        let registry = Registry::new();
+        let metrics = RecorderMetrics::new().unwrap();
+        metrics.register(&registry).unwrap();
+
+        metrics.observe_http_request(
+            "GET",
+            "/api/metadata/ping",
+            StatusCode::OK,
+            Duration::from_millis(3),
+        );
+        metrics.observe_subscription_sync("sync_one_subscription_feeds_incremental", false);
+
+        assert_eq!(
+            metrics
+                .http_requests_total
+                .with_label_values(&["GET", "/api/metadata/ping", "200"])
+                .get(),
+            1
+        );
+        assert_eq!(
+            metrics
+                .subscription_syncs_total
+                .with_label_values(&["sync_one_subscription_feeds_incremental", "failure"])
+                .get(),
+            1
+        );
+
+        let body = TextEncoder::new()
+            .encode_to_string(&registry.gather())
+            .unwrap();
+        assert!(body.contains("recorder_http_request_duration_seconds_bucket"));
+    }
+}
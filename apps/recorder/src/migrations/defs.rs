@@ -52,6 +52,9 @@ pub enum Bangumi {
     RssLink,
     PosterLink,
     OriginPosterLink,
+    PosterFormat,
+    PosterWidth,
+    PosterHeight,
     /**
      * @deprecated
      */
@@ -125,6 +128,10 @@ pub enum Downloads {
     Url,
     Homepage,
     SavePath,
+    Hash,
+    SeedingAction,
+    SeedingLimitReachedAt,
+    MissingSince,
 }
 
 #[derive(DeriveIden)]
@@ -137,6 +144,7 @@ pub enum Downloaders {
     Username,
     SubscriberId,
     SavePath,
+    SeedingPolicy,
 }
 
 #[derive(DeriveIden)]
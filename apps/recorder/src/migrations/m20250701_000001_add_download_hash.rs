@@ -0,0 +1,60 @@
+use async_trait::async_trait;
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::migrations::defs::Downloads;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Downloads::Table)
+                    .add_column_if_not_exists(text_null(Downloads::Hash))
+                    .modify_column(big_integer_null(Downloads::AllSize))
+                    .modify_column(big_integer_null(Downloads::CurrSize))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_downloads_downloader_id_hash")
+                    .table(Downloads::Table)
+                    .col(Downloads::DownloaderId)
+                    .col(Downloads::Hash)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .if_exists()
+                    .name("idx_downloads_downloader_id_hash")
+                    .table(Downloads::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Downloads::Table)
+                    .drop_column(Downloads::Hash)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
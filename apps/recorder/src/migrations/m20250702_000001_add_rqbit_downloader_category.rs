@@ -0,0 +1,27 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+
+use super::defs::*;
+use crate::models::downloaders::{DownloaderCategory, DownloaderCategoryEnum};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        create_postgres_enum_for_active_enum!(
+            manager,
+            DownloaderCategoryEnum,
+            DownloaderCategory::Rqbit
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // postgres does not support removing values from an enum type
+        Ok(())
+    }
+}
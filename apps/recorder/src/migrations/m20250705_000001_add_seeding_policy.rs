@@ -0,0 +1,78 @@
+use async_trait::async_trait;
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::{
+    migrations::defs::{Downloaders, Downloads},
+    models::downloads::{DownloadSeedingAction, DownloadSeedingActionEnum},
+};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        create_postgres_enum_for_active_enum!(
+            manager,
+            DownloadSeedingActionEnum,
+            DownloadSeedingAction::Pause,
+            DownloadSeedingAction::Remove,
+            DownloadSeedingAction::RemoveWithFiles
+        )
+        .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Downloaders::Table)
+                    .add_column_if_not_exists(json_binary_null(Downloaders::SeedingPolicy))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Downloads::Table)
+                    .add_column_if_not_exists(enumeration_null(
+                        Downloads::SeedingAction,
+                        DownloadSeedingActionEnum,
+                        DownloadSeedingAction::iden_values(),
+                    ))
+                    .add_column_if_not_exists(timestamp_with_time_zone_null(
+                        Downloads::SeedingLimitReachedAt,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Downloads::Table)
+                    .drop_column(Downloads::SeedingAction)
+                    .drop_column(Downloads::SeedingLimitReachedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Downloaders::Table)
+                    .drop_column(Downloaders::SeedingPolicy)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_postgres_enum_for_active_enum(DownloadSeedingActionEnum)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+use async_trait::async_trait;
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::migrations::defs::Bangumi;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Bangumi::Table)
+                    .add_column_if_not_exists(text_null(Bangumi::PosterFormat))
+                    .add_column_if_not_exists(integer_null(Bangumi::PosterWidth))
+                    .add_column_if_not_exists(integer_null(Bangumi::PosterHeight))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Bangumi::Table)
+                    .drop_column(Bangumi::PosterFormat)
+                    .drop_column(Bangumi::PosterWidth)
+                    .drop_column(Bangumi::PosterHeight)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+use async_trait::async_trait;
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::migrations::defs::Downloads;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Downloads::Table)
+                    .add_column_if_not_exists(timestamp_with_time_zone_null(
+                        Downloads::MissingSince,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Downloads::Table)
+                    .drop_column(Downloads::MissingSince)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
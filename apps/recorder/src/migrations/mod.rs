@@ -11,6 +11,13 @@ pub mod m20250520_021135_add_tasks;
 pub mod m20250622_015618_feeds;
 pub mod m20250622_020819_bangumi_and_episode_type;
 pub mod m20250629_065628_add_cron;
+pub mod m20250701_000001_add_download_hash;
+pub mod m20250702_000001_add_rqbit_downloader_category;
+pub mod m20250703_000001_add_aria2_downloader_category;
+pub mod m20250704_000001_add_transmission_downloader_category;
+pub mod m20250705_000001_add_seeding_policy;
+pub mod m20250706_000001_add_bangumi_poster_meta;
+pub mod m20250707_000001_add_download_missing_since;
 
 pub struct Migrator;
 
@@ -26,6 +33,13 @@ impl MigratorTrait for Migrator {
             Box::new(m20250622_015618_feeds::Migration),
             Box::new(m20250622_020819_bangumi_and_episode_type::Migration),
             Box::new(m20250629_065628_add_cron::Migration),
+            Box::new(m20250701_000001_add_download_hash::Migration),
+            Box::new(m20250702_000001_add_rqbit_downloader_category::Migration),
+            Box::new(m20250703_000001_add_aria2_downloader_category::Migration),
+            Box::new(m20250704_000001_add_transmission_downloader_category::Migration),
+            Box::new(m20250705_000001_add_seeding_policy::Migration),
+            Box::new(m20250706_000001_add_bangumi_poster_meta::Migration),
+            Box::new(m20250707_000001_add_download_missing_since::Migration),
         ]
     }
 }
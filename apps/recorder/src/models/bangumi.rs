@@ -58,6 +58,10 @@ pub struct Model {
     pub rss_link: Option<String>,
     pub poster_link: Option<String>,
     pub origin_poster_link: Option<String>,
+    /// Extension of the stored poster format, e.g. `jpg`
+    pub poster_format: Option<String>,
+    pub poster_width: Option<i32>,
+    pub poster_height: Option<i32>,
     pub homepage: Option<String>,
 }
 
@@ -142,13 +146,12 @@ impl ActiveModel {
             Some(&meta.mikan_fansub_id),
         );
 
-        let poster_link = if let Some(origin_poster_src) = meta.origin_poster_src.clone() {
-            let poster_meta =
-                scrape_mikan_poster_meta_from_image_url(ctx, origin_poster_src).await?;
-            poster_meta.poster_src
+        let poster_meta = if let Some(origin_poster_src) = meta.origin_poster_src.clone() {
+            Some(scrape_mikan_poster_meta_from_image_url(ctx, origin_poster_src).await?)
         } else {
             None
         };
+        let poster_meta = poster_meta.as_ref();
 
         Ok(Self {
             mikan_bangumi_id: ActiveValue::Set(Some(meta.mikan_bangumi_id)),
@@ -159,9 +162,30 @@ impl ActiveModel {
             season: ActiveValue::Set(season_index),
             season_raw: ActiveValue::Set(season_raw),
             fansub: ActiveValue::Set(Some(meta.fansub)),
-            poster_link: ActiveValue::Set(poster_link),
-            origin_poster_link: ActiveValue::Set(meta.origin_poster_src.map(|src| src.to_string())),
-            homepage: ActiveValue::Set(Some(meta.homepage.to_string())),
+            poster_link: ActiveValue::Set(poster_meta.and_then(|m| m.poster_src.clone())),
+            poster_format: ActiveValue::Set(
+                poster_meta
+                    .and_then(|m| m.poster_format)
+                    .and_then(|format| format.extensions_str().first())
+                    .map(|ext| ext.to_string()),
+            ),
+            poster_width: ActiveValue::Set(
+                poster_meta
+                    .and_then(|m| m.poster_width)
+                    .map(|width| width as i32),
+            ),
+            poster_height: ActiveValue::Set(
+                poster_meta
+                    .and_then(|m| m.poster_height)
+                    .map(|height| height as i32),
+            ),
+            origin_poster_link: ActiveValue::Set(
+                meta.origin_poster_src
+                    .map(|src| mikan_client.normalize_url(&src).to_string()),
+            ),
+            homepage: ActiveValue::Set(Some(
+                mikan_client.normalize_url(&meta.homepage).to_string(),
+            )),
             rss_link: ActiveValue::Set(Some(rss_url.to_string())),
             bangumi_type: ActiveValue::Set(BangumiType::Mikan),
             ..Default::default()
@@ -241,6 +265,9 @@ impl Model {
                         Column::Fansub,
                         Column::PosterLink,
                         Column::OriginPosterLink,
+                        Column::PosterFormat,
+                        Column::PosterWidth,
+                        Column::PosterHeight,
                         Column::Season,
                         Column::SeasonRaw,
                         Column::RssLink,
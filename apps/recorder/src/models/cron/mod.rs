@@ -379,6 +379,35 @@ impl Model {
         Ok(())
     }
 
+    /// Number of enabled crons whose `next_run` has passed without being
+    /// picked up, and how long the oldest of them is overdue
+    pub async fn due_lag(ctx: &dyn AppContextTrait) -> RecorderResult<(i64, chrono::Duration)> {
+        let db = ctx.db();
+
+        let (overdue, oldest_next_run) = Entity::find()
+            .select_only()
+            .column_as(Expr::col(Column::Id).count(), "overdue")
+            .column_as(Expr::col(Column::NextRun).min(), "oldest_next_run")
+            .filter(Column::Enabled.eq(true))
+            .filter(Column::Status.is_in([
+                CronStatus::Pending,
+                CronStatus::Completed,
+                CronStatus::Failed,
+            ]))
+            .filter(Expr::col(Column::NextRun).lte(Expr::current_timestamp()))
+            .into_tuple::<(i64, Option<DateTime<Utc>>)>()
+            .one(db)
+            .await?
+            .unwrap_or_default();
+
+        let lag = oldest_next_run
+            .map(|next_run| Utc::now() - next_run)
+            .unwrap_or_default()
+            .max(chrono::Duration::zero());
+
+        Ok((overdue, lag))
+    }
+
     pub async fn check_and_cleanup_expired_cron_locks(
         ctx: &dyn AppContextTrait,
         retry_duration: chrono::Duration,
@@ -1,8 +1,11 @@
 use async_trait::async_trait;
-use sea_orm::entity::prelude::*;
+use downloader::bittorrent::seeding::SeedingPolicy;
+use sea_orm::{FromJsonQueryResult, QueryOrder, entity::prelude::*};
 use serde::{Deserialize, Serialize};
 use url::Url;
 
+use crate::{app::AppContextTrait, errors::RecorderResult};
+
 #[derive(
     Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, DeriveDisplay, Serialize, Deserialize,
 )]
@@ -17,8 +20,22 @@ pub enum DownloaderCategory {
     QBittorrent,
     #[sea_orm(string_value = "dandanplay")]
     Dandanplay,
+    #[sea_orm(string_value = "rqbit")]
+    Rqbit,
+    #[sea_orm(string_value = "aria2")]
+    Aria2,
+    #[sea_orm(string_value = "transmission")]
+    Transmission,
 }
 
+/// Seeding limits applied to every completed download of the downloader.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, FromJsonQueryResult)]
+#[serde(transparent)]
+pub struct DownloaderSeedingPolicy(pub SeedingPolicy);
+
+// the max ratio comes from user config and is never NaN
+impl Eq for DownloaderSeedingPolicy {}
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
 #[sea_orm(table_name = "downloaders")]
 pub struct Model {
@@ -34,6 +51,7 @@ pub struct Model {
     pub username: String,
     pub subscriber_id: i32,
     pub save_path: String,
+    pub seeding_policy: Option<DownloaderSeedingPolicy>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -82,4 +100,19 @@ impl Model {
         let url = Url::parse(&self.endpoint)?;
         Ok(url)
     }
+
+    pub async fn find_subscriber_downloader(
+        ctx: &dyn AppContextTrait,
+        subscriber_id: i32,
+    ) -> RecorderResult<Option<Self>> {
+        let db = ctx.db();
+
+        let downloader = Entity::find()
+            .filter(Column::SubscriberId.eq(subscriber_id))
+            .order_by_asc(Column::Id)
+            .one(db)
+            .await?;
+
+        Ok(downloader)
+    }
 }
@@ -1,7 +1,34 @@
+use std::collections::HashMap;
+
 use async_trait::async_trait;
-use sea_orm::entity::prelude::*;
+use chrono::Utc;
+use downloader::{
+    bittorrent::{
+        handle::TorrentDownloadInfo,
+        seeding::{SeedingLimitAction, SeedingLimitReached},
+        source::HashTorrentSourceTrait,
+    },
+    core::DownloadSimpleState,
+};
+use itertools::Itertools;
+use quirks_path::Path;
+use sea_orm::{ActiveValue, IntoActiveModel, entity::prelude::*};
 use serde::{Deserialize, Serialize};
 
+use super::{
+    downloaders::{self, DownloaderSeedingPolicy},
+    episodes,
+};
+use crate::{
+    app::AppContextTrait,
+    errors::{RecorderError, RecorderResult},
+};
+
+/// How long a torrent has to stay missing from its downloader before its
+/// download is marked deleted, downloaders can briefly miss torrents they just
+/// accepted or replaced, such as aria2 following a magnet to a new gid.
+const DOWNLOAD_MISSING_GRACE_SECONDS: i64 = 10 * 60;
+
 #[derive(
     Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, DeriveDisplay, Serialize, Deserialize,
 )]
@@ -22,6 +49,46 @@ pub enum DownloadStatus {
     Deleted,
 }
 
+impl DownloadStatus {
+    pub fn from_download_simple_state(state: &DownloadSimpleState) -> Option<Self> {
+        match state {
+            DownloadSimpleState::Paused => Some(Self::Paused),
+            DownloadSimpleState::Active => Some(Self::Downloading),
+            DownloadSimpleState::Completed => Some(Self::Completed),
+            DownloadSimpleState::Error => Some(Self::Failed),
+            DownloadSimpleState::Unknown => None,
+        }
+    }
+}
+
+#[derive(
+    Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, DeriveDisplay, Serialize, Deserialize,
+)]
+#[sea_orm(
+    rs_type = "String",
+    db_type = "Enum",
+    enum_name = "download_seeding_action"
+)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadSeedingAction {
+    #[sea_orm(string_value = "pause")]
+    Pause,
+    #[sea_orm(string_value = "remove")]
+    Remove,
+    #[sea_orm(string_value = "remove_with_files")]
+    RemoveWithFiles,
+}
+
+impl From<SeedingLimitAction> for DownloadSeedingAction {
+    fn from(action: SeedingLimitAction) -> Self {
+        match action {
+            SeedingLimitAction::Pause => Self::Pause,
+            SeedingLimitAction::Remove => Self::Remove,
+            SeedingLimitAction::RemoveWithFiles => Self::RemoveWithFiles,
+        }
+    }
+}
+
 #[derive(
     Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, DeriveDisplay, Serialize, Deserialize,
 )]
@@ -56,6 +123,12 @@ pub struct Model {
     pub curr_size: Option<i64>,
     pub homepage: Option<String>,
     pub save_path: Option<String>,
+    pub hash: Option<String>,
+    pub seeding_action: Option<DownloadSeedingAction>,
+    pub seeding_limit_reached_at: Option<DateTimeUtc>,
+    /// First sync the torrent was missing from the downloader at, cleared once
+    /// it is seen again
+    pub missing_since: Option<DateTimeUtc>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -118,3 +191,513 @@ pub enum RelatedEntity {
 impl ActiveModelBehavior for ActiveModel {}
 
 impl ActiveModel {}
+
+impl Model {
+    #[tracing::instrument(err, skip(ctx))]
+    pub async fn add_downloads_for_subscription(
+        ctx: &dyn AppContextTrait,
+        subscriber_id: i32,
+        subscription_id: i32,
+    ) -> RecorderResult<()> {
+        let db = ctx.db();
+
+        let downloader = downloaders::Model::find_subscriber_downloader(ctx, subscriber_id)
+            .await?
+            .ok_or_else(|| {
+                RecorderError::from_entity_not_found_detail::<downloaders::Entity, _>(format!(
+                    "subscriber {subscriber_id} has no downloader configured"
+                ))
+            })?;
+
+        let undownloaded_episode_list =
+            episodes::Model::get_undownloaded_episode_list_from_subscription(ctx, subscription_id)
+                .await?;
+
+        let mut episode_list_group_by_save_path = HashMap::new();
+        for (episode, bangumi) in undownloaded_episode_list {
+            let save_path = match bangumi {
+                Some(bangumi) => Path::new(&downloader.save_path)
+                    .join(&bangumi.display_name)
+                    .to_string(),
+                None => downloader.save_path.clone(),
+            };
+            episode_list_group_by_save_path
+                .entry(save_path)
+                .or_insert_with(Vec::new)
+                .push(episode);
+        }
+
+        for (save_path, episode_list) in episode_list_group_by_save_path {
+            let mut sources = vec![];
+            let mut download_active_models = vec![];
+            let mut episode_ids = vec![];
+
+            for episode in episode_list {
+                let Some(source) = episode
+                    .get_torrent_source(ctx)
+                    .await
+                    .inspect_err(|err| {
+                        tracing::error!(
+                            err = ?err,
+                            episode_id = episode.id,
+                            "Failed to resolve torrent source of episode, skip"
+                        );
+                    })
+                    .ok()
+                    .flatten()
+                else {
+                    continue;
+                };
+
+                download_active_models.push(ActiveModel {
+                    origin_name: ActiveValue::Set(episode.origin_name.clone()),
+                    display_name: ActiveValue::Set(episode.display_name.clone()),
+                    downloader_id: ActiveValue::Set(downloader.id),
+                    episode_id: ActiveValue::Set(episode.id),
+                    subscriber_id: ActiveValue::Set(subscriber_id),
+                    status: ActiveValue::Set(DownloadStatus::Pending),
+                    mime: ActiveValue::Set(DownloadMime::BitTorrent),
+                    url: ActiveValue::Set(episode.enclosure_url().unwrap_or_default().to_string()),
+                    all_size: ActiveValue::Set(episode.enclosure_content_length),
+                    curr_size: ActiveValue::Set(None),
+                    homepage: ActiveValue::Set(episode.homepage.clone()),
+                    save_path: ActiveValue::Set(Some(save_path.clone())),
+                    hash: ActiveValue::Set(Some(source.hash_info().to_string())),
+                    ..Default::default()
+                });
+                episode_ids.push(episode.id);
+                sources.push(source);
+            }
+
+            if sources.is_empty() {
+                continue;
+            }
+
+            let hashes = sources
+                .iter()
+                .map(|source| source.hash_info().to_string())
+                .collect_vec();
+            let downloader_handle = ctx.downloader().get_or_build(&downloader).await?;
+
+            // Rows are saved before the torrents are handed to the downloader, so no
+            // connection is held while talking to it. A failed add removes both the
+            // torrents it may have accepted and the rows, so the episodes are
+            // retried and neither side is left with downloads the other does not
+            // know.
+            Entity::insert_many(download_active_models)
+                .exec_without_returning(db)
+                .await?;
+
+            if let Err(err) = downloader_handle
+                .add_sources(save_path.into(), sources)
+                .await
+            {
+                // some downloaders, such as aria2, can only forget torrents
+                let removed = match downloader_handle.remove_by_hashes(hashes.clone()).await {
+                    Ok(()) => Ok(()),
+                    Err(_) => downloader_handle.remove_by_hashes_keep_files(hashes).await,
+                };
+                if let Err(remove_err) = removed {
+                    tracing::error!(
+                        err = ?remove_err,
+                        downloader_id = downloader.id,
+                        "Failed to remove torrents of downloads failed to be added"
+                    );
+                }
+                Entity::delete_many()
+                    .filter(Column::DownloaderId.eq(downloader.id))
+                    .filter(Column::EpisodeId.is_in(episode_ids))
+                    .filter(Column::Status.eq(DownloadStatus::Pending))
+                    .exec(db)
+                    .await?;
+                return Err(err.into());
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(err, skip(ctx))]
+    pub async fn sync_downloads_status(
+        ctx: &dyn AppContextTrait,
+        subscriber_id: Option<i32>,
+    ) -> RecorderResult<()> {
+        let db = ctx.db();
+
+        let mut select = Entity::find()
+            .filter(Column::Status.ne(DownloadStatus::Deleted))
+            .filter(Column::Hash.is_not_null());
+        if let Some(subscriber_id) = subscriber_id {
+            select = select.filter(Column::SubscriberId.eq(subscriber_id));
+        }
+
+        let download_list_group_by_downloader = select
+            .all(db)
+            .await?
+            .into_iter()
+            .into_group_map_by(|d| d.downloader_id);
+
+        if download_list_group_by_downloader.is_empty() {
+            return Ok(());
+        }
+
+        let downloader_list = downloaders::Entity::find()
+            .filter(
+                downloaders::Column::Id.is_in(
+                    download_list_group_by_downloader
+                        .keys()
+                        .copied()
+                        .collect_vec(),
+                ),
+            )
+            .all(db)
+            .await?;
+
+        for downloader in downloader_list {
+            let Some(download_list) = download_list_group_by_downloader.get(&downloader.id) else {
+                continue;
+            };
+
+            let seeding_limit_reached_list = match &downloader.seeding_policy {
+                Some(seeding_policy) => {
+                    Self::enforce_seeding_policy(ctx, &downloader, download_list, seeding_policy)
+                        .await
+                        .unwrap_or_else(|err| {
+                            tracing::error!(
+                                err = ?err,
+                                downloader_id = downloader.id,
+                                "Failed to enforce seeding policy of downloader, skip"
+                            );
+                            HashMap::new()
+                        })
+                }
+                None => HashMap::new(),
+            };
+
+            let torrent_info_list =
+                match Self::query_torrent_info_list(ctx, &downloader, download_list).await {
+                    Ok(torrent_info_list) => torrent_info_list,
+                    Err(err) => {
+                        tracing::error!(
+                            err = ?err,
+                            downloader_id = downloader.id,
+                            "Failed to query downloads from downloader, skip"
+                        );
+                        continue;
+                    }
+                };
+
+            for download in download_list {
+                let torrent_info = download
+                    .hash
+                    .as_ref()
+                    .and_then(|hash| torrent_info_list.get(hash));
+                let seeding_limit_reached = download
+                    .hash
+                    .as_ref()
+                    .and_then(|hash| seeding_limit_reached_list.get(hash));
+
+                if let Some(am) =
+                    download.reconcile_with_torrent_info(torrent_info, seeding_limit_reached)
+                {
+                    am.update(db).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn query_torrent_info_list(
+        ctx: &dyn AppContextTrait,
+        downloader: &downloaders::Model,
+        download_list: &[Self],
+    ) -> RecorderResult<HashMap<String, TorrentDownloadInfo>> {
+        let hashes = download_list
+            .iter()
+            .filter_map(|d| d.hash.clone())
+            .unique()
+            .collect_vec();
+
+        let torrent_info_list = ctx
+            .downloader()
+            .get_or_build(downloader)
+            .await?
+            .query_by_hashes(hashes)
+            .await?;
+
+        Ok(torrent_info_list
+            .into_iter()
+            .map(|info| (info.hash_info.clone(), info))
+            .collect())
+    }
+
+    async fn enforce_seeding_policy(
+        ctx: &dyn AppContextTrait,
+        downloader: &downloaders::Model,
+        download_list: &[Self],
+        seeding_policy: &DownloaderSeedingPolicy,
+    ) -> RecorderResult<HashMap<String, SeedingLimitReached>> {
+        // paused torrents are still reported as completed, those that already
+        // reached a limit are skipped so the action is only taken once
+        let hashes = download_list
+            .iter()
+            .filter(|d| {
+                d.status == DownloadStatus::Completed && d.seeding_limit_reached_at.is_none()
+            })
+            .filter_map(|d| d.hash.clone())
+            .unique()
+            .collect_vec();
+
+        let seeding_limit_reached_list = ctx
+            .downloader()
+            .get_or_build(downloader)
+            .await?
+            .enforce_seeding_policy(hashes, &seeding_policy.0)
+            .await?;
+
+        Ok(seeding_limit_reached_list
+            .into_iter()
+            .map(|reached| (reached.hash_info.clone(), reached))
+            .collect())
+    }
+
+    fn reconcile_with_torrent_info(
+        &self,
+        torrent_info: Option<&TorrentDownloadInfo>,
+        seeding_limit_reached: Option<&SeedingLimitReached>,
+    ) -> Option<ActiveModel> {
+        let now = Utc::now();
+
+        // the first time a limit is reached is kept
+        let (seeding_action, seeding_limit_reached_at) = match self.seeding_limit_reached_at {
+            Some(reached_at) => (self.seeding_action.clone(), Some(reached_at)),
+            None => (
+                seeding_limit_reached
+                    .map(|reached| DownloadSeedingAction::from(reached.action))
+                    .or_else(|| self.seeding_action.clone()),
+                seeding_limit_reached.map(|_| now),
+            ),
+        };
+
+        // a missing torrent is only taken as deleted once it stayed missing on
+        // every sync for the grace period
+        let Some(torrent_info) = torrent_info else {
+            let missing_since = self.missing_since.unwrap_or(now);
+            let status = if now - missing_since
+                >= chrono::Duration::seconds(DOWNLOAD_MISSING_GRACE_SECONDS)
+            {
+                DownloadStatus::Deleted
+            } else {
+                self.status.clone()
+            };
+
+            if status == self.status
+                && self.missing_since.is_some()
+                && seeding_action == self.seeding_action
+                && seeding_limit_reached_at == self.seeding_limit_reached_at
+            {
+                return None;
+            }
+
+            let mut am = self.clone().into_active_model();
+            am.status = ActiveValue::Set(status);
+            am.missing_since = ActiveValue::Set(Some(missing_since));
+            am.seeding_action = ActiveValue::Set(seeding_action);
+            am.seeding_limit_reached_at = ActiveValue::Set(seeding_limit_reached_at);
+            return Some(am);
+        };
+
+        let status = DownloadStatus::from_download_simple_state(&torrent_info.state)
+            .unwrap_or_else(|| self.status.clone());
+        let all_size = torrent_info.total_bytes.map(|s| s as i64).or(self.all_size);
+        let curr_size = torrent_info.dl_bytes.map(|s| s as i64).or(self.curr_size);
+        let save_path = torrent_info
+            .save_path
+            .clone()
+            .or_else(|| self.save_path.clone());
+
+        if status == self.status
+            && all_size == self.all_size
+            && curr_size == self.curr_size
+            && save_path == self.save_path
+            && seeding_action == self.seeding_action
+            && seeding_limit_reached_at == self.seeding_limit_reached_at
+            && self.missing_since.is_none()
+        {
+            return None;
+        }
+
+        let mut am = self.clone().into_active_model();
+        am.status = ActiveValue::Set(status);
+        am.missing_since = ActiveValue::Set(None);
+        am.all_size = ActiveValue::Set(all_size);
+        am.curr_size = ActiveValue::Set(curr_size);
+        am.save_path = ActiveValue::Set(save_path);
+        am.seeding_action = ActiveValue::Set(seeding_action);
+        am.seeding_limit_reached_at = ActiveValue::Set(seeding_limit_reached_at);
+
+        Some(am)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn download(status: DownloadStatus) -> Model {
+        Model {
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            id: 1,
+            origin_name: "origin".to_string(),
+            display_name: "display".to_string(),
+            downloader_id: 1,
+            episode_id: 1,
+            subscriber_id: 1,
+            status,
+            mime: DownloadMime::BitTorrent,
+            url: "https://mikanani.me/Download/1.torrent".to_string(),
+            all_size: Some(100),
+            curr_size: Some(10),
+            homepage: None,
+            save_path: Some("/downloads/bangumi".to_string()),
+            hash: Some("47ee2d69e7f19af783ad896541a07b012676f858".to_string()),
+            seeding_action: None,
+            seeding_limit_reached_at: None,
+            missing_since: None,
+        }
+    }
+
+    fn torrent_info(state: DownloadSimpleState, dl_bytes: u64) -> TorrentDownloadInfo {
+        TorrentDownloadInfo {
+            hash_info: "47ee2d69e7f19af783ad896541a07b012676f858".to_string(),
+            name: "display".to_string(),
+            state,
+            dl_bytes: Some(dl_bytes),
+            total_bytes: Some(100),
+            save_path: Some("/downloads/bangumi".to_string()),
+            ratio: None,
+            seeding_time: None,
+        }
+    }
+
+    #[test]
+    fn test_reconcile_with_torrent_info() {
+        let pending = download(DownloadStatus::Pending);
+
+        let unchanged = download(DownloadStatus::Downloading).reconcile_with_torrent_info(
+            Some(&torrent_info(DownloadSimpleState::Active, 10)),
+            None,
+        );
+        assert!(unchanged.is_none());
+
+        let progressed = pending
+            .reconcile_with_torrent_info(Some(&torrent_info(DownloadSimpleState::Active, 50)), None)
+            .expect("progress should be saved");
+        assert_eq!(
+            progressed.status,
+            ActiveValue::Set(DownloadStatus::Downloading)
+        );
+        assert_eq!(progressed.curr_size, ActiveValue::Set(Some(50)));
+
+        let mut moved_info = torrent_info(DownloadSimpleState::Completed, 100);
+        moved_info.save_path = Some("/downloads/archive".to_string());
+        let moved = pending
+            .reconcile_with_torrent_info(Some(&moved_info), None)
+            .expect("completion should be saved");
+        assert_eq!(moved.status, ActiveValue::Set(DownloadStatus::Completed));
+        assert_eq!(
+            moved.save_path,
+            ActiveValue::Set(Some("/downloads/archive".to_string()))
+        );
+
+        let unknown = download(DownloadStatus::Paused).reconcile_with_torrent_info(
+            Some(&torrent_info(DownloadSimpleState::Unknown, 10)),
+            None,
+        );
+        assert!(unknown.is_none());
+    }
+
+    #[test]
+    fn test_reconcile_marks_deleted_after_missing_grace_period() {
+        let downloading = download(DownloadStatus::Downloading);
+
+        let missing = downloading
+            .reconcile_with_torrent_info(None, None)
+            .expect("first missing sync should be saved");
+        assert_eq!(
+            missing.status,
+            ActiveValue::Set(DownloadStatus::Downloading)
+        );
+        let ActiveValue::Set(Some(missing_since)) = missing.missing_since else {
+            panic!("missing since should be set");
+        };
+
+        // a downloader lagging behind for a few syncs is not a deletion
+        let mut still_missing = downloading.clone();
+        still_missing.missing_since = Some(missing_since);
+        assert!(
+            still_missing
+                .reconcile_with_torrent_info(None, None)
+                .is_none()
+        );
+
+        let reappeared = still_missing
+            .reconcile_with_torrent_info(Some(&torrent_info(DownloadSimpleState::Active, 10)), None)
+            .expect("reappeared torrent should clear missing since");
+        assert_eq!(reappeared.missing_since, ActiveValue::Set(None));
+        assert_eq!(
+            reappeared.status,
+            ActiveValue::Set(DownloadStatus::Downloading)
+        );
+
+        let mut long_missing = downloading;
+        long_missing.missing_since =
+            Some(Utc::now() - chrono::Duration::seconds(DOWNLOAD_MISSING_GRACE_SECONDS + 1));
+        let deleted = long_missing
+            .reconcile_with_torrent_info(None, None)
+            .expect("torrent missing past the grace period should be saved as deleted");
+        assert_eq!(deleted.status, ActiveValue::Set(DownloadStatus::Deleted));
+    }
+
+    #[test]
+    fn test_reconcile_keeps_first_seeding_limit_reached_at() {
+        let reached = SeedingLimitReached {
+            hash_info: "47ee2d69e7f19af783ad896541a07b012676f858".to_string(),
+            action: SeedingLimitAction::Pause,
+            ratio: Some(2.0),
+            seeding_time: None,
+        };
+        let completed_info = torrent_info(DownloadSimpleState::Completed, 100);
+        let mut completed = download(DownloadStatus::Completed);
+        completed.curr_size = Some(100);
+
+        let first = completed
+            .reconcile_with_torrent_info(Some(&completed_info), Some(&reached))
+            .expect("reached limit should be saved");
+        assert_eq!(
+            first.seeding_action,
+            ActiveValue::Set(Some(DownloadSeedingAction::Pause))
+        );
+        let ActiveValue::Set(Some(first_reached_at)) = first.seeding_limit_reached_at else {
+            panic!("reached time should be set");
+        };
+
+        let mut paused = completed.clone();
+        paused.seeding_action = Some(DownloadSeedingAction::Pause);
+        paused.seeding_limit_reached_at = Some(first_reached_at);
+
+        // paused torrents are still reported as completed
+        assert!(
+            paused
+                .reconcile_with_torrent_info(Some(&completed_info), Some(&reached))
+                .is_none()
+        );
+        assert!(
+            paused
+                .reconcile_with_torrent_info(Some(&completed_info), None)
+                .is_none()
+        );
+    }
+}
@@ -1,10 +1,11 @@
 use async_trait::async_trait;
+use downloader::bittorrent::source::HashTorrentSource;
 use sea_orm::{
-    ActiveValue, IntoSimpleExpr, QuerySelect, entity::prelude::*, sea_query::OnConflict,
+    ActiveValue, IntoSimpleExpr, JoinType, QuerySelect, entity::prelude::*, sea_query::OnConflict,
 };
 use serde::{Deserialize, Serialize};
 
-use super::{bangumi, query::InsertManyReturningExt, subscription_episode};
+use super::{bangumi, downloads, query::InsertManyReturningExt, subscription_episode};
 use crate::{
     app::AppContextTrait,
     errors::RecorderResult,
@@ -162,7 +163,11 @@ impl ActiveModel {
             poster_link: ActiveValue::Set(bangumi.poster_link.clone()),
             origin_poster_link: ActiveValue::Set(bangumi.origin_poster_link.clone()),
             episode_index: ActiveValue::Set(0),
-            enclosure_torrent_link: ActiveValue::Set(enclosure_meta.torrent_link),
+            enclosure_torrent_link: ActiveValue::Set(
+                enclosure_meta
+                    .torrent_link
+                    .map(|link| ctx.mikan().normalize_link(&link)),
+            ),
             enclosure_magnet_link: ActiveValue::Set(enclosure_meta.magnet_link),
             enclosure_pub_date: ActiveValue::Set(enclosure_meta.pub_date),
             enclosure_content_length: ActiveValue::Set(enclosure_meta.content_length),
@@ -291,4 +296,49 @@ impl Model {
 
         Ok(())
     }
+    pub async fn get_undownloaded_episode_list_from_subscription(
+        ctx: &dyn AppContextTrait,
+        subscription_id: i32,
+    ) -> RecorderResult<Vec<(Self, Option<bangumi::Model>)>> {
+        let db = ctx.db();
+
+        let episode_list = Entity::find()
+            .filter(subscription_episode::Column::SubscriptionId.eq(subscription_id))
+            .filter(downloads::Column::Id.is_null())
+            .join_rev(
+                JoinType::InnerJoin,
+                subscription_episode::Relation::Episode.def(),
+            )
+            .join(JoinType::LeftJoin, Relation::Download.def())
+            .find_also_related(bangumi::Entity)
+            .all(db)
+            .await?;
+
+        Ok(episode_list)
+    }
+
+    pub fn enclosure_url(&self) -> Option<&str> {
+        self.enclosure_torrent_link
+            .as_deref()
+            .or(self.enclosure_magnet_link.as_deref())
+    }
+
+    #[tracing::instrument(err, skip(self, ctx), fields(episode_id = self.id))]
+    pub async fn get_torrent_source(
+        &self,
+        ctx: &dyn AppContextTrait,
+    ) -> RecorderResult<Option<HashTorrentSource>> {
+        let source = if let Some(torrent_link) = self.enclosure_torrent_link.clone() {
+            Some(
+                HashTorrentSource::from_torrent_url_and_http_client(ctx.mikan(), torrent_link)
+                    .await?,
+            )
+        } else if let Some(magnet_link) = self.enclosure_magnet_link.clone() {
+            Some(HashTorrentSource::from_magnet_url(magnet_link)?)
+        } else {
+            None
+        };
+
+        Ok(source)
+    }
 }
@@ -151,6 +151,52 @@ impl StorageService {
         })
     }
 
+    /// Writes chunks as they arrive, nothing is left behind if the stream or
+    /// the write fails.
+    pub async fn write_stream<P, S, E>(
+        &self,
+        path: P,
+        stream: S,
+    ) -> RecorderResult<StorageStoredUrl>
+    where
+        P: Into<PathBuf> + Send,
+        S: Stream<Item = Result<Bytes, E>> + Send,
+        E: Into<RecorderError>,
+    {
+        let operator = &self.operator;
+
+        let path = path.into();
+
+        if let Some(dirname) = path.parent() {
+            let dirname = dirname.join("/");
+            operator.create_dir(dirname.as_str()).await?;
+        }
+
+        let mut writer = operator.writer(path.as_str()).await?;
+
+        let result: RecorderResult<()> = async {
+            futures::pin_mut!(stream);
+            while let Some(chunk) = stream.next().await {
+                writer.write(chunk.map_err(Into::into)?).await?;
+            }
+            Ok(())
+        }
+        .await;
+
+        if let Err(err) = result {
+            if let Err(abort_err) = writer.abort().await {
+                tracing::warn!(error = %abort_err, path = %path, "Failed to abort storage writer");
+            }
+            return Err(err);
+        }
+
+        writer.close().await?;
+
+        Ok(StorageStoredUrl::RelativePath {
+            path: path.to_string(),
+        })
+    }
+
     pub async fn exists<P: ToString + Send>(
         &self,
         path: P,
@@ -16,6 +16,8 @@ pub struct TaskConfig {
     pub cron_retry_duration: Duration,
     #[serde(default = "default_cron_interval_duration")]
     pub cron_interval_duration: Duration,
+    #[serde(default = "default_sync_downloads_status_interval_duration")]
+    pub sync_downloads_status_interval_duration: Duration,
 }
 
 impl Default for TaskConfig {
@@ -28,6 +30,8 @@ impl Default for TaskConfig {
             system_task_reenqueue_orphaned_after: default_system_task_reenqueue_orphaned_after(),
             cron_retry_duration: default_cron_retry_duration(),
             cron_interval_duration: default_cron_interval_duration(),
+            sync_downloads_status_interval_duration:
+                default_sync_downloads_status_interval_duration(),
         }
     }
 }
@@ -52,6 +56,10 @@ pub fn default_cron_interval_duration() -> Duration {
     Duration::from_secs(30)
 }
 
+pub fn default_sync_downloads_status_interval_duration() -> Duration {
+    Duration::from_secs(10)
+}
+
 pub fn default_subscriber_task_reenqueue_orphaned_after() -> Duration {
     Duration::from_secs(3600)
 }
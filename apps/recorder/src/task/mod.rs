@@ -13,10 +13,11 @@ pub use config::TaskConfig;
 pub use registry::{
     EchoTask, OptimizeImageTask, SubscriberTask, SubscriberTaskInput, SubscriberTaskType,
     SubscriberTaskTypeEnum, SubscriberTaskTypeVariant, SubscriberTaskTypeVariantIter,
-    SyncOneSubscriptionFeedsFullTask, SyncOneSubscriptionFeedsIncrementalTask,
-    SyncOneSubscriptionSourcesTask, SystemTask, SystemTaskInput, SystemTaskType,
-    SystemTaskTypeEnum, SystemTaskTypeVariant, SystemTaskTypeVariantIter,
+    SyncDownloadsStatusTask, SyncOneSubscriptionFeedsFullTask,
+    SyncOneSubscriptionFeedsIncrementalTask, SyncOneSubscriptionSourcesTask, SystemTask,
+    SystemTaskInput, SystemTaskType, SystemTaskTypeEnum, SystemTaskTypeVariant,
+    SystemTaskTypeVariantIter,
 };
 #[allow(unused_imports)]
 pub(crate) use registry::{register_subscriber_task_type, register_system_task_type};
-pub use service::TaskService;
+pub use service::{TaskQueueStats, TaskService};
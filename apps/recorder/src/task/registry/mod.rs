@@ -9,6 +9,6 @@ pub use subscriber::{
 };
 pub(crate) use system::register_system_task_type;
 pub use system::{
-    EchoTask, OptimizeImageTask, SystemTask, SystemTaskInput, SystemTaskType, SystemTaskTypeEnum,
-    SystemTaskTypeVariant, SystemTaskTypeVariantIter,
+    EchoTask, OptimizeImageTask, SyncDownloadsStatusTask, SystemTask, SystemTaskInput,
+    SystemTaskType, SystemTaskTypeEnum, SystemTaskTypeVariant, SystemTaskTypeVariantIter,
 };
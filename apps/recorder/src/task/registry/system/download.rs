@@ -0,0 +1,23 @@
+use std::sync::Arc;
+
+use tracing::instrument;
+
+use crate::{
+    app::AppContextTrait,
+    errors::RecorderResult,
+    models::downloads,
+    task::{AsyncTaskTrait, register_system_task_type},
+};
+
+register_system_task_type! {
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct SyncDownloadsStatusTask {}
+}
+
+#[async_trait::async_trait]
+impl AsyncTaskTrait for SyncDownloadsStatusTask {
+    #[instrument(err, skip(ctx))]
+    async fn run_async(self, ctx: Arc<dyn AppContextTrait>) -> RecorderResult<()> {
+        downloads::Model::sync_downloads_status(ctx.as_ref(), self.subscriber_id).await
+    }
+}
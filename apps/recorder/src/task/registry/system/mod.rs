@@ -1,8 +1,10 @@
 mod base;
+mod download;
 mod media;
 mod misc;
 
 pub(crate) use base::register_system_task_type;
+pub use download::SyncDownloadsStatusTask;
 pub use media::OptimizeImageTask;
 pub use misc::EchoTask;
 use sea_orm::{DeriveActiveEnum, DeriveDisplay, EnumIter, FromJsonQueryResult};
@@ -146,6 +148,7 @@ register_system_task_types! {
         pub enum SystemTaskType {
             OptimizeImage => "optimize_image",
             Test => "test",
+            SyncDownloadsStatus => "sync_downloads_status",
         }
     },
     task_enum: {
@@ -153,6 +156,7 @@ register_system_task_types! {
         pub enum SystemTask {
             OptimizeImage(OptimizeImageTask),
             Echo(EchoTask),
+            SyncDownloadsStatus(SyncDownloadsStatusTask),
         }
     }
 }
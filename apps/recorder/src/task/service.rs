@@ -6,22 +6,39 @@ use apalis_sql::{
     context::SqlContext,
     postgres::{PgListen as ApalisPgListen, PostgresStorage as ApalisPostgresStorage},
 };
-use sea_orm::{ActiveModelTrait, sqlx::postgres::PgListener};
+use sea_orm::{
+    ActiveEnum, ActiveModelTrait, EntityTrait, QuerySelect, sea_query::Expr,
+    sqlx::postgres::PgListener,
+};
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
 use crate::{
     app::AppContextTrait,
     errors::{RecorderError, RecorderResult},
-    models::cron::{self, CRON_DUE_DEBUG_EVENT, CRON_DUE_EVENT},
+    metrics::recorder_metrics,
+    models::{
+        cron::{self, CRON_DUE_DEBUG_EVENT, CRON_DUE_EVENT},
+        subscriber_tasks, system_tasks,
+    },
     task::{
         AsyncTaskTrait, SUBSCRIBER_TASK_APALIS_NAME, SYSTEM_TASK_APALIS_NAME, SubscriberTask,
-        TaskConfig,
+        SyncDownloadsStatusTask, TaskConfig,
         config::{default_subscriber_task_workers, default_system_task_workers},
         registry::SystemTask,
     },
 };
 
+/// Number of jobs of one task type in one status
+#[derive(Debug, Clone)]
+pub struct TaskQueueStats {
+    /// `subscriber` or `system`
+    pub queue: &'static str,
+    pub task_type: String,
+    pub status: String,
+    pub count: i64,
+}
+
 pub struct TaskService {
     pub config: TaskConfig,
     ctx: Arc<dyn AppContextTrait>,
@@ -66,8 +83,13 @@ impl TaskService {
         data: Data<Arc<dyn AppContextTrait>>,
     ) -> RecorderResult<()> {
         let ctx = data.deref().clone();
+        let task_type = job.task_type();
 
-        job.run_async(ctx).await
+        let result = job.run_async(ctx).await;
+
+        recorder_metrics().observe_subscription_sync(&task_type.to_value(), result.is_ok());
+
+        result
     }
 
     async fn run_system_task(
@@ -137,6 +159,56 @@ impl TaskService {
         Ok(task_id)
     }
 
+    pub async fn queue_stats(&self) -> RecorderResult<Vec<TaskQueueStats>> {
+        let db = self.ctx.db();
+
+        let subscriber_task_stats = subscriber_tasks::Entity::find()
+            .select_only()
+            .column(subscriber_tasks::Column::TaskType)
+            .column(subscriber_tasks::Column::Status)
+            .column_as(Expr::col(subscriber_tasks::Column::Id).count(), "count")
+            .group_by(subscriber_tasks::Column::TaskType)
+            .group_by(subscriber_tasks::Column::Status)
+            .into_tuple::<(
+                subscriber_tasks::SubscriberTaskType,
+                subscriber_tasks::SubscriberTaskStatus,
+                i64,
+            )>()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|(task_type, status, count)| TaskQueueStats {
+                queue: "subscriber",
+                task_type: task_type.to_value(),
+                status: status.to_value(),
+                count,
+            });
+
+        let system_task_stats = system_tasks::Entity::find()
+            .select_only()
+            .column(system_tasks::Column::TaskType)
+            .column(system_tasks::Column::Status)
+            .column_as(Expr::col(system_tasks::Column::Id).count(), "count")
+            .group_by(system_tasks::Column::TaskType)
+            .group_by(system_tasks::Column::Status)
+            .into_tuple::<(
+                system_tasks::SystemTaskType,
+                system_tasks::SystemTaskStatus,
+                i64,
+            )>()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|(task_type, status, count)| TaskQueueStats {
+                queue: "system",
+                task_type: task_type.to_value(),
+                status: status.to_value(),
+                count,
+            });
+
+        Ok(subscriber_task_stats.chain(system_task_stats).collect())
+    }
+
     pub async fn add_subscriber_task_cron(
         &self,
         cm: cron::ActiveModel,
@@ -239,6 +311,20 @@ impl TaskService {
                     Ok::<_, RecorderError>(())
                 }
             } => {}
+            _ = {
+                let ctx = self.ctx.clone();
+                let mut interval =
+                    tokio::time::interval(self.config.sync_downloads_status_interval_duration);
+                async move {
+                    loop {
+                        interval.tick().await;
+                        let task = SystemTask::from(SyncDownloadsStatusTask::builder().build());
+                        if let Err(e) = task.run_async(ctx.clone()).await {
+                            tracing::error!("Error syncing downloads status: {e}");
+                        }
+                    }
+                }
+            } => {}
         };
 
         Ok(())
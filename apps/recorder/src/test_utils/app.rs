@@ -4,12 +4,13 @@ use once_cell::sync::OnceCell;
 use typed_builder::TypedBuilder;
 
 use crate::{
-    app::AppContextTrait,
+    app::{AppConfig, AppContextTrait},
     errors::RecorderResult,
     task::TaskConfig,
     test_utils::{
         crypto::build_testing_crypto_service,
         database::{TestingDatabaseServiceConfig, build_testing_database_service},
+        downloader::build_testing_downloader_service,
         media::build_testing_media_service,
         mikan::{MikanMockServer, build_testing_mikan_client},
         storage::build_testing_storage_service,
@@ -22,7 +23,8 @@ use crate::{
 pub struct TestingAppContext {
     logger: Option<crate::logger::LoggerService>,
     db: Option<crate::database::DatabaseService>,
-    config: Option<crate::app::AppConfig>,
+    #[builder(default, setter(!strip_option))]
+    config: Option<AppConfig>,
     cache: Option<crate::cache::CacheService>,
     mikan: Option<crate::extract::mikan::MikanClient>,
     auth: Option<crate::auth::AuthService>,
@@ -30,6 +32,7 @@ pub struct TestingAppContext {
     storage: Option<crate::storage::StorageService>,
     crypto: Option<crate::crypto::CryptoService>,
     media: Option<crate::media::MediaService>,
+    downloader: Option<crate::download::DownloaderService>,
     #[builder(default = Arc::new(OnceCell::new()), setter(!strip_option))]
     task: Arc<OnceCell<crate::task::TaskService>>,
     message: Option<crate::message::MessageService>,
@@ -52,6 +55,7 @@ impl TestingAppContext {
         let crypto_service = build_testing_crypto_service().await?;
         let storage_service = build_testing_storage_service().await?;
         let media_service = build_testing_media_service().await?;
+        let downloader_service = build_testing_downloader_service().await?;
         let app_ctx = Arc::new(
             TestingAppContext::builder()
                 .mikan(mikan_client)
@@ -59,6 +63,8 @@ impl TestingAppContext {
                 .crypto(crypto_service)
                 .storage(storage_service)
                 .media(media_service)
+                .downloader(downloader_service)
+                .config(config.app_config)
                 .build(),
         );
 
@@ -132,6 +138,10 @@ impl AppContextTrait for TestingAppContext {
     fn media(&self) -> &crate::media::MediaService {
         self.media.as_ref().expect("should set media")
     }
+
+    fn downloader(&self) -> &crate::download::DownloaderService {
+        self.downloader.as_ref().expect("should set downloader")
+    }
 }
 
 #[derive(TypedBuilder, Debug)]
@@ -140,6 +150,7 @@ pub struct TestingAppContextConfig {
     pub mikan_base_url: Option<String>,
     pub database_config: Option<TestingDatabaseServiceConfig>,
     pub task_config: Option<TaskConfig>,
+    pub app_config: Option<AppConfig>,
 }
 
 #[derive(TypedBuilder)]
@@ -171,6 +182,7 @@ impl TestingPreset {
             mikan_base_url: None,
             database_config: None,
             task_config: None,
+            app_config: None,
         })
         .await
     }
@@ -0,0 +1,27 @@
+use std::sync::Arc;
+
+use downloader::fake::{FakeDownloader, FakeDownloaderCreation};
+
+use crate::{
+    download::{DownloaderConfig, DownloaderService},
+    errors::RecorderResult,
+    models::downloaders,
+};
+
+pub async fn build_testing_downloader_service() -> RecorderResult<DownloaderService> {
+    DownloaderService::from_config(DownloaderConfig::default(), "tests/data").await
+}
+
+pub async fn register_testing_fake_downloader(
+    downloader_service: &DownloaderService,
+    model: &downloaders::Model,
+) -> Arc<FakeDownloader> {
+    let downloader = FakeDownloader::from_creation(FakeDownloaderCreation {
+        save_path: model.save_path.clone(),
+        ..Default::default()
+    });
+    downloader_service
+        .register(model.clone(), downloader.clone())
+        .await;
+    downloader
+}
@@ -6,9 +6,8 @@ use std::{
 };
 
 use chrono::{Duration, Utc};
-use fetch::{FetchError, HttpClientConfig, IntoUrl, get_random_ua};
+use fetch::{FetchError, HttpClientConfig, IntoUrl, get_random_ua, replay::HttpReplayPath};
 use lazy_static::lazy_static;
-use percent_encoding::{AsciiSet, CONTROLS, percent_decode, utf8_percent_encode};
 use serde::{Deserialize, Serialize};
 use url::Url;
 
@@ -29,26 +28,13 @@ const TESTING_MIKAN_PASSWORD: &str = "test_password";
 const TESTING_MIKAN_ANTIFORGERY: &str = "test_antiforgery";
 const TESTING_MIKAN_IDENTITY: &str = "test_identity";
 
-const FILE_UNSAFE: &AsciiSet = &CONTROLS
-    .add(b'<')
-    .add(b'>')
-    .add(b':')
-    .add(b'"')
-    .add(b'|')
-    .add(b'?')
-    .add(b'*')
-    .add(b'\\')
-    .add(b'/')
-    .add(b'&')
-    .add(b'=')
-    .add(b'#');
-
 pub async fn build_testing_mikan_client(
     base_mikan_url: impl IntoUrl,
 ) -> RecorderResult<MikanClient> {
     let mikan_client = MikanClient::from_config(MikanConfig {
         http_client: HttpClientConfig::default(),
         base_url: base_mikan_url.into_url().map_err(FetchError::from)?,
+        mirrors: Default::default(),
     })
     .await?;
     Ok(mikan_client)
@@ -120,13 +106,11 @@ impl MikanDoppelPath {
     }
 
     pub fn encode_path_component(component: &str) -> String {
-        utf8_percent_encode(component, FILE_UNSAFE).to_string()
+        HttpReplayPath::encode_path_component(component)
     }
 
     pub fn decode_path_component(component: &str) -> Result<String, std::str::Utf8Error> {
-        Ok(percent_decode(component.as_bytes())
-            .decode_utf8()?
-            .to_string())
+        HttpReplayPath::decode_path_component(component)
     }
 
     pub fn meta_path(&self) -> path::PathBuf {
@@ -163,23 +147,8 @@ lazy_static! {
 impl From<Url> for MikanDoppelPath {
     fn from(value: Url) -> Self {
         let doppel_path = PathBuf::from(format!("{}/mikan/doppel", TEST_RESOURCES_DIR.as_str()));
-        let base_path = doppel_path.join(value.path().trim_matches('/'));
-        let dirname = base_path.parent();
-        let stem = base_path.file_stem();
-        debug_assert!(dirname.is_some() && stem.is_some());
-        let extension = if let Some(ext) = base_path.extension() {
-            ext.to_string_lossy().to_string()
-        } else {
-            String::from("html")
-        };
-        let mut filename = stem.unwrap().to_string_lossy().to_string();
-        if let Some(query) = value.query() {
-            filename.push_str(&format!("-{}", Self::encode_path_component(query)));
-        }
-        filename.push_str(&format!(".{extension}"));
-
         Self {
-            path: dirname.unwrap().join(filename),
+            path: HttpReplayPath::from_url(doppel_path, &value).into_path(),
         }
     }
 }
@@ -1,6 +1,7 @@
 pub mod app;
 pub mod crypto;
 pub mod database;
+pub mod downloader;
 pub mod media;
 pub mod mikan;
 pub mod storage;
@@ -1,9 +1,20 @@
 use std::sync::Arc;
 
-use axum::{Json, Router, extract::State, routing::get};
+use axum::{
+    Json, Router,
+    extract::State,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use prometheus::TEXT_FORMAT;
 use serde::Serialize;
+use subtle::ConstantTimeEq;
 
-use crate::{app::AppContextTrait, errors::RecorderResult, web::controller::Controller};
+use crate::{
+    app::AppContextTrait, errors::RecorderResult, metrics::gather_metrics,
+    web::controller::Controller,
+};
 
 pub const CONTROLLER_PREFIX: &str = "/api/metadata";
 
@@ -33,10 +44,117 @@ async fn ping() -> Json<StandardResponse> {
     })
 }
 
+async fn metrics(
+    State(ctx): State<Arc<dyn AppContextTrait>>,
+    headers: HeaderMap,
+) -> RecorderResult<Response> {
+    let token = ctx
+        .config()
+        .server
+        .middlewares
+        .metrics
+        .as_ref()
+        .and_then(|metrics| metrics.token.as_deref())
+        .filter(|token| !token.is_empty());
+
+    if let Some(token) = token {
+        let bearer = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        if !bearer.is_some_and(|bearer| bool::from(bearer.as_bytes().ct_eq(token.as_bytes()))) {
+            return Ok(StatusCode::UNAUTHORIZED.into_response());
+        }
+    }
+
+    let body = gather_metrics(ctx.as_ref()).await?;
+
+    Ok(([(header::CONTENT_TYPE, TEXT_FORMAT)], body).into_response())
+}
+
 pub async fn create(_context: Arc<dyn AppContextTrait>) -> RecorderResult<Controller> {
     let router = Router::<Arc<dyn AppContextTrait>>::new()
         .route("/health", get(health))
-        .route("/ping", get(ping));
+        .route("/ping", get(ping))
+        .route("/metrics", get(metrics));
 
     Ok(Controller::from_nest_router(CONTROLLER_PREFIX, router))
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::{Body, to_bytes},
+        http::Request,
+    };
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        app::{AppConfig, Environment},
+        test_utils::app::{TestingAppContextConfig, TestingPreset},
+        web::{controller::core::ControllerTrait, middleware::metrics::Metrics},
+    };
+
+    const METRICS_TOKEN: &str = "metrics-token";
+
+    #[tokio::test]
+    async fn test_metrics_requires_bearer_token() -> RecorderResult<()> {
+        let mut app_config = AppConfig::load_config(
+            &Environment::Testing,
+            Some(concat!(env!("CARGO_MANIFEST_DIR"), "/recorder.config.toml")),
+        )
+        .await?;
+        app_config.server.middlewares.metrics = Some(Metrics {
+            enable: true,
+            token: Some(String::from(METRICS_TOKEN)),
+        });
+
+        let preset = TestingPreset::default_with_config(
+            TestingAppContextConfig::builder()
+                .app_config(app_config)
+                .build(),
+        )
+        .await?;
+
+        let router = create(preset.app_ctx.clone())
+            .await?
+            .apply_to(Router::new())
+            .with_state(preset.app_ctx.clone());
+
+        let metrics_request = |authorization: Option<String>| {
+            let mut request = Request::get(format!("{CONTROLLER_PREFIX}/metrics"));
+            if let Some(authorization) = authorization {
+                request = request.header(header::AUTHORIZATION, authorization);
+            }
+            request.body(Body::empty()).unwrap()
+        };
+
+        let response = router.clone().oneshot(metrics_request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = router
+            .clone()
+            .oneshot(metrics_request(Some(String::from("Bearer wrong-token"))))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = router
+            .oneshot(metrics_request(Some(format!("Bearer {METRICS_TOKEN}"))))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            TEXT_FORMAT
+        );
+
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("# TYPE recorder_db_pool_connections gauge"));
+        assert!(body.contains("recorder_cron_overdue"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+//! Metrics Middleware
+//!
+//! This middleware counts requests and records their latency by method,
+//! matched route and status. The metrics are exported with the rest of the
+//! recorder metrics by `/api/metadata/metrics`.
+
+use std::{sync::Arc, time::Instant};
+
+use axum::{
+    Router,
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::AppContextTrait, errors::RecorderResult, metrics::recorder_metrics,
+    web::middleware::MiddlewareLayer,
+};
+
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Metrics {
+    #[serde(default)]
+    pub enable: bool,
+    /// Bearer token required to read `/api/metadata/metrics`, which is public
+    /// if not set
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+impl MiddlewareLayer for Metrics {
+    /// Returns the name of the middleware
+    fn name(&self) -> &'static str {
+        "metrics"
+    }
+
+    /// Returns whether the middleware is enabled or not
+    fn is_enabled(&self) -> bool {
+        self.enable
+    }
+
+    fn config(&self) -> serde_json::Result<serde_json::Value> {
+        serde_json::to_value(self)
+    }
+
+    /// Applies the metrics middleware to the application router.
+    fn apply(
+        &self,
+        app: Router<Arc<dyn AppContextTrait>>,
+    ) -> RecorderResult<Router<Arc<dyn AppContextTrait>>> {
+        Ok(app.layer(axum::middleware::from_fn(metrics_middleware)))
+    }
+}
+
+/// Records the request with its matched route rather than its path, so
+/// requests to the webui fallback and unknown paths share one label.
+pub async fn metrics_middleware(request: Request, next: Next) -> Response {
+    let method = request.method().clone();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|matched_path| matched_path.as_str().to_string());
+    let start = Instant::now();
+
+    let response = next.run(request).await;
+
+    recorder_metrics().observe_http_request(
+        method.as_str(),
+        route.as_deref().unwrap_or(UNMATCHED_ROUTE),
+        response.status(),
+        start.elapsed(),
+    );
+
+    response
+}
@@ -4,6 +4,7 @@ pub mod cors;
 pub mod etag;
 pub mod format;
 pub mod logger;
+pub mod metrics;
 pub mod remote_ip;
 pub mod request_id;
 pub mod secure_headers;
@@ -133,6 +134,16 @@ pub fn default_middleware_stack(ctx: Arc<dyn AppContextTrait>) -> Vec<Box<dyn Mi
                 .clone()
                 .unwrap_or_else(|| request_id::RequestId { enable: true }),
         ),
+        // Metrics middleware with a default if none
+        Box::new(
+            middlewares
+                .metrics
+                .clone()
+                .unwrap_or_else(|| metrics::Metrics {
+                    enable: true,
+                    token: None,
+                }),
+        ),
     ]
 }
 
@@ -165,4 +176,7 @@ pub struct MiddlewareConfig {
 
     /// Request ID
     pub request_id: Option<request_id::RequestId>,
+
+    /// Http server metrics and the token protecting their endpoint
+    pub metrics: Option<metrics::Metrics>,
 }
@@ -21,7 +21,7 @@ export type Scalars = {
   JsonbFilterInput: { input: any; output: any; }
   /** type SubscriberTaskType = { "taskType": "sync_one_subscription_feeds_incremental" } & SyncOneSubscriptionFeedsIncrementalTask | { "taskType": "sync_one_subscription_feeds_full" } & SyncOneSubscriptionFeedsFullTask | { "taskType": "sync_one_subscription_sources" } & SyncOneSubscriptionSourcesTask; */
   SubscriberTaskType: { input: SubscriberTaskInput; output: SubscriberTaskType; }
-  /** type SystemTaskType = { "taskType": "optimize_image" } & OptimizeImageTask | { "taskType": "test" } & EchoTask; */
+  /** type SystemTaskType = { "taskType": "optimize_image" } & OptimizeImageTask | { "taskType": "test" } & EchoTask | { "taskType": "sync_downloads_status" } & SyncDownloadsStatusTask; */
   SystemTaskType: { input: any; output: any; }
 };
 
@@ -39,7 +39,10 @@ export type Bangumi = {
   mikanFansubId?: Maybe<Scalars['String']['output']>;
   originName: Scalars['String']['output'];
   originPosterLink?: Maybe<Scalars['String']['output']>;
+  posterFormat?: Maybe<Scalars['String']['output']>;
+  posterHeight?: Maybe<Scalars['Int']['output']>;
   posterLink?: Maybe<Scalars['String']['output']>;
+  posterWidth?: Maybe<Scalars['Int']['output']>;
   rssLink?: Maybe<Scalars['String']['output']>;
   season: Scalars['Int']['output'];
   seasonRaw?: Maybe<Scalars['String']['output']>;
@@ -84,7 +87,10 @@ export type BangumiBasic = {
   mikanFansubId?: Maybe<Scalars['String']['output']>;
   originName: Scalars['String']['output'];
   originPosterLink?: Maybe<Scalars['String']['output']>;
+  posterFormat?: Maybe<Scalars['String']['output']>;
+  posterHeight?: Maybe<Scalars['Int']['output']>;
   posterLink?: Maybe<Scalars['String']['output']>;
+  posterWidth?: Maybe<Scalars['Int']['output']>;
   rssLink?: Maybe<Scalars['String']['output']>;
   season: Scalars['Int']['output'];
   seasonRaw?: Maybe<Scalars['String']['output']>;
@@ -119,7 +125,10 @@ export type BangumiFilterInput = {
   or?: InputMaybe<Array<BangumiFilterInput>>;
   originName?: InputMaybe<StringFilterInput>;
   originPosterLink?: InputMaybe<StringFilterInput>;
+  posterFormat?: InputMaybe<StringFilterInput>;
+  posterHeight?: InputMaybe<IntegerFilterInput>;
   posterLink?: InputMaybe<StringFilterInput>;
+  posterWidth?: InputMaybe<IntegerFilterInput>;
   rssLink?: InputMaybe<StringFilterInput>;
   season?: InputMaybe<IntegerFilterInput>;
   seasonRaw?: InputMaybe<StringFilterInput>;
@@ -139,7 +148,10 @@ export type BangumiInsertInput = {
   mikanFansubId?: InputMaybe<Scalars['String']['input']>;
   originName: Scalars['String']['input'];
   originPosterLink?: InputMaybe<Scalars['String']['input']>;
+  posterFormat?: InputMaybe<Scalars['String']['input']>;
+  posterHeight?: InputMaybe<Scalars['Int']['input']>;
   posterLink?: InputMaybe<Scalars['String']['input']>;
+  posterWidth?: InputMaybe<Scalars['Int']['input']>;
   rssLink?: InputMaybe<Scalars['String']['input']>;
   season: Scalars['Int']['input'];
   seasonRaw?: InputMaybe<Scalars['String']['input']>;
@@ -159,7 +171,10 @@ export type BangumiOrderInput = {
   mikanFansubId?: InputMaybe<OrderByEnum>;
   originName?: InputMaybe<OrderByEnum>;
   originPosterLink?: InputMaybe<OrderByEnum>;
+  posterFormat?: InputMaybe<OrderByEnum>;
+  posterHeight?: InputMaybe<OrderByEnum>;
   posterLink?: InputMaybe<OrderByEnum>;
+  posterWidth?: InputMaybe<OrderByEnum>;
   rssLink?: InputMaybe<OrderByEnum>;
   season?: InputMaybe<OrderByEnum>;
   seasonRaw?: InputMaybe<OrderByEnum>;
@@ -197,7 +212,10 @@ export type BangumiUpdateInput = {
   mikanFansubId?: InputMaybe<Scalars['String']['input']>;
   originName?: InputMaybe<Scalars['String']['input']>;
   originPosterLink?: InputMaybe<Scalars['String']['input']>;
+  posterFormat?: InputMaybe<Scalars['String']['input']>;
+  posterHeight?: InputMaybe<Scalars['Int']['input']>;
   posterLink?: InputMaybe<Scalars['String']['input']>;
+  posterWidth?: InputMaybe<Scalars['Int']['input']>;
   rssLink?: InputMaybe<Scalars['String']['input']>;
   season?: InputMaybe<Scalars['Int']['input']>;
   seasonRaw?: InputMaybe<Scalars['String']['input']>;
@@ -555,8 +573,11 @@ export type DownloadStatusEnumFilterInput = {
 };
 
 export const DownloaderCategoryEnum = {
+  Aria2: 'aria2',
   Dandanplay: 'dandanplay',
-  Qbittorrent: 'qbittorrent'
+  Qbittorrent: 'qbittorrent',
+  Rqbit: 'rqbit',
+  Transmission: 'transmission'
 } as const;
 
 export type DownloaderCategoryEnum = typeof DownloaderCategoryEnum[keyof typeof DownloaderCategoryEnum];
@@ -2144,6 +2165,7 @@ export const SystemTaskStatusEnum = {
 export type SystemTaskStatusEnum = typeof SystemTaskStatusEnum[keyof typeof SystemTaskStatusEnum];
 export const SystemTaskTypeEnum = {
   OptimizeImage: 'optimize_image',
+  SyncDownloadsStatus: 'sync_downloads_status',
   Test: 'test'
 } as const;
 
@@ -0,0 +1,652 @@
+use std::{
+    collections::HashSet,
+    fmt::Debug,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use async_trait::async_trait;
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64_STANDARD};
+use fetch::{HttpClient, reqwest::header::CONTENT_TYPE};
+use itertools::Itertools;
+use quirks_path::{Path, PathBuf};
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use snafu::whatever;
+use tracing::instrument;
+use url::Url;
+
+use super::task::{
+    Aria2Creation, Aria2DownloadState, Aria2Gid, Aria2Hash, Aria2HashSelector, Aria2Selector,
+    Aria2State, Aria2Status, Aria2Task,
+};
+use crate::{
+    DownloaderError,
+    bittorrent::{
+        downloader::TorrentDownloaderTrait,
+        handle::{TorrentDownloadInfo, TorrentDownloaderHandleTrait},
+        labels::{TORRENT_STATE_FOLDER_NAME, TorrentLabelStore},
+        source::{HashTorrentSource, HashTorrentSourceTrait, MagnetUrlSource, TorrentFileSource},
+        task::{TORRENT_TAG_NAME, TorrentTaskTrait},
+    },
+    core::{DownloadStateTrait, DownloadTaskTrait, DownloaderTrait},
+    errors::UnsupportedOperationSnafu,
+};
+
+const ARIA2_QUERY_PAGE_SIZE: usize = 1000;
+
+pub const ARIA2_LABELS_FILE_NAME: &str = "aria2-labels.json";
+
+#[derive(Debug)]
+pub struct Aria2DownloaderCreation {
+    pub endpoint: String,
+    pub secret: Option<String>,
+    pub save_path: String,
+    /// Where the tags and categories of downloads are kept, defaults to a
+    /// hidden folder inside the save path.
+    pub data_dir: Option<String>,
+    pub subscriber_id: i32,
+    pub downloader_id: i32,
+}
+
+impl Aria2DownloaderCreation {
+    pub fn state_dir(&self) -> std::path::PathBuf {
+        self.data_dir
+            .as_ref()
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(|| {
+                std::path::Path::new(&self.save_path).join(TORRENT_STATE_FOLDER_NAME)
+            })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Aria2File {
+    index: String,
+    path: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Aria2RpcErrorBody {
+    code: i64,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Aria2RpcResponse<T> {
+    result: Option<T>,
+    error: Option<Aria2RpcErrorBody>,
+}
+
+pub struct Aria2Downloader {
+    pub subscriber_id: i32,
+    pub downloader_id: i32,
+    pub endpoint_url: Url,
+    pub secret: Option<String>,
+    pub save_path: PathBuf,
+    pub client: HttpClient,
+    /// aria2 has no tags or categories, they are kept in this sidecar store
+    /// and downloads added by konobangu are tagged with [`TORRENT_TAG_NAME`].
+    pub labels: TorrentLabelStore<Aria2Hash>,
+    request_id: AtomicU64,
+}
+
+impl Aria2Downloader {
+    #[instrument(level = "debug")]
+    pub async fn from_creation(
+        creation: Aria2DownloaderCreation,
+    ) -> Result<Arc<Self>, DownloaderError> {
+        let endpoint_url = Url::parse(&creation.endpoint)?;
+        let labels =
+            TorrentLabelStore::load(creation.state_dir().join(ARIA2_LABELS_FILE_NAME)).await?;
+
+        let downloader = Arc::new(Self {
+            subscriber_id: creation.subscriber_id,
+            downloader_id: creation.downloader_id,
+            endpoint_url,
+            secret: creation.secret.filter(|s| !s.is_empty()),
+            save_path: creation.save_path.into(),
+            client: HttpClient::default(),
+            labels,
+            request_id: AtomicU64::new(0),
+        });
+
+        downloader.check_connection().await?;
+
+        Ok(downloader)
+    }
+
+    #[instrument(level = "debug", skip(self, params))]
+    async fn call<T: DeserializeOwned>(
+        &self,
+        method: &'static str,
+        params: Vec<Value>,
+    ) -> Result<T, DownloaderError> {
+        let params = self
+            .secret
+            .as_ref()
+            .map(|secret| Value::String(format!("token:{secret}")))
+            .into_iter()
+            .chain(params)
+            .collect::<Vec<_>>();
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": format!(
+                "konobangu-{}",
+                self.request_id.fetch_add(1, Ordering::Relaxed)
+            ),
+            "method": method,
+            "params": params,
+        });
+
+        let response: Aria2RpcResponse<T> = self
+            .client
+            .post(self.endpoint_url.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .send()
+            .await?
+            .json()
+            .await
+            .map_err(fetch::reqwest_middleware::Error::from)?;
+
+        match response {
+            Aria2RpcResponse {
+                error: Some(error), ..
+            } => Err(DownloaderError::Aria2RpcError {
+                method: method.into(),
+                code: error.code,
+                message: error.message,
+            }),
+            Aria2RpcResponse {
+                result: Some(result),
+                ..
+            } => Ok(result),
+            _ => Err(DownloaderError::Aria2RpcError {
+                method: method.into(),
+                code: -1,
+                message: "missing result in aria2 rpc response".to_string(),
+            }),
+        }
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn api_version(&self) -> Result<String, DownloaderError> {
+        #[derive(Deserialize)]
+        struct Aria2Version {
+            version: String,
+        }
+
+        let result: Aria2Version = self.call("aria2.getVersion", vec![]).await?;
+        Ok(result.version)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn check_connection(&self) -> Result<(), DownloaderError> {
+        self.api_version().await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn add_torrent(
+        &self,
+        source: HashTorrentSource,
+        save_path: &Path,
+    ) -> Result<Aria2Gid, DownloaderError> {
+        let options = json!({ "dir": save_path.as_str() });
+        let gid = match source {
+            HashTorrentSource::MagnetUrl(MagnetUrlSource { url, .. }) => {
+                self.call("aria2.addUri", vec![json!([url]), options])
+                    .await?
+            }
+            HashTorrentSource::TorrentFile(TorrentFileSource { payload, .. }) => {
+                self.call(
+                    "aria2.addTorrent",
+                    vec![json!(BASE64_STANDARD.encode(payload)), json!([]), options],
+                )
+                .await?
+            }
+        };
+        Ok(gid)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn list_statuses(&self) -> Result<Vec<Aria2Status>, DownloaderError> {
+        let keys = json!(Aria2Status::KEYS);
+        let (active, waiting, stopped) = futures::future::try_join3(
+            self.call::<Vec<Aria2Status>>("aria2.tellActive", vec![keys.clone()]),
+            self.list_statuses_paged("aria2.tellWaiting", &keys),
+            self.list_statuses_paged("aria2.tellStopped", &keys),
+        )
+        .await?;
+
+        // downloads moving between lists while paging may be listed twice
+        Ok(active
+            .into_iter()
+            .chain(waiting)
+            .chain(stopped)
+            .unique_by(|s| s.gid.clone())
+            .collect())
+    }
+
+    async fn list_statuses_paged(
+        &self,
+        method: &'static str,
+        keys: &Value,
+    ) -> Result<Vec<Aria2Status>, DownloaderError> {
+        let mut statuses = vec![];
+        loop {
+            let page = self
+                .call::<Vec<Aria2Status>>(
+                    method,
+                    vec![
+                        json!(statuses.len()),
+                        json!(ARIA2_QUERY_PAGE_SIZE),
+                        keys.clone(),
+                    ],
+                )
+                .await?;
+            let is_last_page = page.len() < ARIA2_QUERY_PAGE_SIZE;
+            statuses.extend(page);
+            if is_last_page {
+                return Ok(statuses);
+            }
+        }
+    }
+
+    /// Forgets the downloads, files already written to disk are kept.
+    #[instrument(level = "debug", skip(self))]
+    async fn forget_torrents(&self, hashes: &Aria2HashSelector) -> Result<(), DownloaderError> {
+        let statuses = self.list_statuses_by_hashes(hashes).await?;
+
+        let running_gids = statuses
+            .iter()
+            .filter(|s| {
+                matches!(
+                    s.status,
+                    Aria2State::Active | Aria2State::Waiting | Aria2State::Paused
+                )
+            })
+            .map(|s| s.gid.clone());
+        self.call_for_each_gid("aria2.forceRemove", running_gids)
+            .await?;
+
+        let all_gids = statuses.into_iter().map(|s| s.gid);
+        self.call_for_each_gid("aria2.removeDownloadResult", all_gids)
+            .await?;
+
+        self.labels.remove(hashes).await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn list_statuses_by_hashes(
+        &self,
+        hashes: &[Aria2Hash],
+    ) -> Result<Vec<Aria2Status>, DownloaderError> {
+        let hashes = hashes
+            .iter()
+            .map(|h| h.to_lowercase())
+            .collect::<HashSet<_>>();
+
+        Ok(self
+            .list_statuses()
+            .await?
+            .into_iter()
+            .filter(|s| {
+                s.info_hash
+                    .as_deref()
+                    .is_some_and(|h| hashes.contains(&h.to_lowercase()))
+            })
+            .collect())
+    }
+
+    async fn change_option(&self, gid: Aria2Gid, options: Value) -> Result<(), DownloaderError> {
+        self.call::<Value>("aria2.changeOption", vec![json!(gid), options])
+            .await?;
+        Ok(())
+    }
+
+    /// Gids of downloads whose input file options can still be changed, that
+    /// is waiting or paused ones, fails if any other download is selected.
+    fn changeable_gids(statuses: Vec<Aria2Status>) -> Result<Vec<Aria2Gid>, DownloaderError> {
+        if let Some(status) = statuses
+            .iter()
+            .find(|s| !matches!(s.status, Aria2State::Waiting | Aria2State::Paused))
+        {
+            whatever!(
+                "aria2 can only change waiting or paused downloads, but {} is {:?}",
+                status.gid,
+                status.status
+            );
+        }
+        Ok(statuses.into_iter().map(|s| s.gid).collect())
+    }
+
+    async fn call_for_each_gid(
+        &self,
+        method: &'static str,
+        gids: impl IntoIterator<Item = Aria2Gid>,
+    ) -> Result<(), DownloaderError> {
+        futures::future::try_join_all(
+            gids.into_iter()
+                .map(|gid| self.call::<Value>(method, vec![json!(gid)])),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DownloaderTrait for Aria2Downloader {
+    type State = Aria2DownloadState;
+    type Id = Aria2Hash;
+    type Task = Aria2Task;
+    type Creation = Aria2Creation;
+    type Selector = Aria2Selector;
+
+    #[instrument(level = "debug", skip(self))]
+    async fn add_downloads(
+        &self,
+        creation: <Self as DownloaderTrait>::Creation,
+    ) -> Result<Vec<<Self as DownloaderTrait>::Id>, DownloaderError> {
+        let save_path = creation.save_path;
+        let tags = {
+            let mut tags = vec![TORRENT_TAG_NAME.to_string()];
+            tags.extend(creation.tags);
+            tags.into_iter()
+                .filter(|s| !s.is_empty())
+                .unique()
+                .collect_vec()
+        };
+        let category = creation.category.filter(|s| !s.is_empty());
+        let hashes = creation
+            .sources
+            .iter()
+            .map(|s| s.hash_info().to_lowercase())
+            .collect::<Vec<_>>();
+
+        futures::future::try_join_all(
+            creation
+                .sources
+                .into_iter()
+                .map(|s| self.add_torrent(s, &save_path)),
+        )
+        .await?;
+
+        self.labels
+            .update(&hashes, |labels| {
+                labels.tags = tags.clone();
+                labels.category = category.clone();
+            })
+            .await?;
+
+        Ok(hashes)
+    }
+
+    async fn pause_downloads(
+        &self,
+        selector: <Self as DownloaderTrait>::Selector,
+    ) -> Result<impl IntoIterator<Item = Self::Id>, DownloaderError> {
+        <Self as TorrentDownloaderTrait>::pause_downloads(self, selector).await
+    }
+
+    async fn resume_downloads(
+        &self,
+        selector: <Self as DownloaderTrait>::Selector,
+    ) -> Result<impl IntoIterator<Item = Self::Id>, DownloaderError> {
+        <Self as TorrentDownloaderTrait>::resume_downloads(self, selector).await
+    }
+
+    async fn remove_downloads(
+        &self,
+        selector: <Self as DownloaderTrait>::Selector,
+    ) -> Result<impl IntoIterator<Item = Self::Id>, DownloaderError> {
+        <Self as TorrentDownloaderTrait>::remove_downloads(self, selector).await
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn query_downloads(
+        &self,
+        selector: Aria2Selector,
+    ) -> Result<Vec<<Self as DownloaderTrait>::Task>, DownloaderError> {
+        let statuses = self.list_statuses_by_hashes(&selector).await?;
+
+        Ok(statuses
+            .into_iter()
+            .filter(|s| !s.is_metadata_only() && s.status != Aria2State::Removed)
+            .filter_map(|s| {
+                let labels = s
+                    .info_hash
+                    .as_ref()
+                    .map(|h| self.labels.get(h))
+                    .unwrap_or_default();
+                Aria2Task::from_status(s, labels)
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl TorrentDownloaderTrait for Aria2Downloader {
+    type IdSelector = Aria2HashSelector;
+
+    #[instrument(level = "debug", skip(self))]
+    async fn pause_torrents(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        let gids = self
+            .list_statuses_by_hashes(&hashes)
+            .await?
+            .into_iter()
+            .filter(|s| matches!(s.status, Aria2State::Active | Aria2State::Waiting))
+            .map(|s| s.gid);
+        self.call_for_each_gid("aria2.pause", gids).await?;
+        Ok(hashes)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn resume_torrents(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        let gids = self
+            .list_statuses_by_hashes(&hashes)
+            .await?
+            .into_iter()
+            .filter(|s| s.status == Aria2State::Paused)
+            .map(|s| s.gid);
+        self.call_for_each_gid("aria2.unpause", gids).await?;
+        Ok(hashes)
+    }
+
+    /// aria2 has no rpc to delete files, so torrents are only removed through
+    /// [`TorrentDownloaderHandleTrait::remove_by_hashes_keep_files`].
+    async fn remove_torrents(
+        &self,
+        _hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        UnsupportedOperationSnafu {
+            downloader: "aria2",
+            operation: "removing torrents with their files",
+        }
+        .fail()
+    }
+
+    /// aria2 only takes a new `dir` for waiting or paused downloads, files
+    /// already written to disk are not moved.
+    #[instrument(level = "debug", skip(self))]
+    async fn move_torrents(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+        save_path: &str,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        let statuses = self
+            .list_statuses_by_hashes(&hashes)
+            .await?
+            .into_iter()
+            .filter(|s| !s.is_metadata_only())
+            .collect_vec();
+        let gids = Self::changeable_gids(statuses)?;
+
+        futures::future::try_join_all(
+            gids.into_iter()
+                .map(|gid| self.change_option(gid, json!({ "dir": save_path }))),
+        )
+        .await?;
+
+        Ok(hashes)
+    }
+
+    /// Renames the output file with `index-out`, which aria2 only takes for
+    /// waiting or paused downloads.
+    #[instrument(level = "debug", skip(self))]
+    async fn rename_torrent_file(
+        &self,
+        hash: <Self as DownloaderTrait>::Id,
+        old_path: &str,
+        new_path: &str,
+    ) -> Result<(), DownloaderError> {
+        let Some(status) = self
+            .list_statuses_by_hashes(&[hash.clone()])
+            .await?
+            .into_iter()
+            .find(|s| !s.is_metadata_only())
+        else {
+            whatever!("torrent {hash} not found in aria2");
+        };
+        let dir = status.dir.clone().unwrap_or_default();
+        let gid = Self::changeable_gids(vec![status])?.remove(0);
+
+        let files: Vec<Aria2File> = self.call("aria2.getFiles", vec![json!(gid)]).await?;
+        let Some(file) = files.into_iter().find(|f| {
+            std::path::Path::new(&f.path)
+                .strip_prefix(&dir)
+                .is_ok_and(|p| p == std::path::Path::new(old_path))
+        }) else {
+            whatever!("file {old_path} not found in torrent {hash}");
+        };
+
+        self.change_option(
+            gid,
+            json!({ "index-out": format!("{}={new_path}", file.index) }),
+        )
+        .await
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn add_torrent_tags(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+        tags: Vec<String>,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        if tags.is_empty() {
+            whatever!("add bittorrent tags can not be empty");
+        }
+        self.labels
+            .update(&hashes, |labels| {
+                labels.tags = labels
+                    .tags
+                    .iter()
+                    .chain(tags.iter())
+                    .unique()
+                    .cloned()
+                    .collect();
+            })
+            .await?;
+        Ok(hashes)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn set_torrents_category(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+        category: &str,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        self.labels
+            .update(&hashes, |labels| {
+                labels.category = Some(category.to_string());
+            })
+            .await?;
+        Ok(hashes)
+    }
+}
+
+#[async_trait]
+impl TorrentDownloaderHandleTrait for Aria2Downloader {
+    async fn add_sources(
+        &self,
+        save_path: PathBuf,
+        sources: Vec<HashTorrentSource>,
+    ) -> Result<Vec<String>, DownloaderError> {
+        DownloaderTrait::add_downloads(
+            self,
+            Aria2Creation {
+                save_path,
+                sources,
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    async fn query_by_hashes(
+        &self,
+        hashes: Vec<String>,
+    ) -> Result<Vec<TorrentDownloadInfo>, DownloaderError> {
+        if hashes.is_empty() {
+            return Ok(vec![]);
+        }
+        let tasks = DownloaderTrait::query_downloads(self, hashes.into()).await?;
+
+        Ok(tasks
+            .into_iter()
+            .map(|task| TorrentDownloadInfo {
+                name: DownloadTaskTrait::name(&task).to_string(),
+                state: task.state().to_download_state(),
+                dl_bytes: task.dl_bytes(),
+                total_bytes: task.total_bytes(),
+                save_path: task.status.dir.clone(),
+                hash_info: task.hash_info().to_string(),
+                ratio: TorrentTaskTrait::ratio(&task),
+                seeding_time: TorrentTaskTrait::seeding_time(&task),
+            })
+            .collect())
+    }
+
+    async fn pause_by_hashes(&self, hashes: Vec<String>) -> Result<(), DownloaderError> {
+        TorrentDownloaderTrait::pause_torrents(self, hashes.into()).await?;
+        Ok(())
+    }
+
+    async fn resume_by_hashes(&self, hashes: Vec<String>) -> Result<(), DownloaderError> {
+        TorrentDownloaderTrait::resume_torrents(self, hashes.into()).await?;
+        Ok(())
+    }
+
+    async fn remove_by_hashes(&self, hashes: Vec<String>) -> Result<(), DownloaderError> {
+        TorrentDownloaderTrait::remove_torrents(self, hashes.into()).await?;
+        Ok(())
+    }
+
+    async fn remove_by_hashes_keep_files(
+        &self,
+        hashes: Vec<String>,
+    ) -> Result<(), DownloaderError> {
+        self.forget_torrents(&hashes.into()).await
+    }
+}
+
+impl Debug for Aria2Downloader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Aria2Downloader")
+            .field("subscriber_id", &self.subscriber_id)
+            .field("client", &self.endpoint_url.as_str())
+            .finish()
+    }
+}
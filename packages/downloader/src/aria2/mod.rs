@@ -0,0 +1,11 @@
+pub mod downloader;
+pub mod task;
+
+#[cfg(test)]
+mod test;
+
+pub use downloader::{Aria2Downloader, Aria2DownloaderCreation};
+pub use task::{
+    Aria2Creation, Aria2DownloadState, Aria2Gid, Aria2Hash, Aria2HashSelector, Aria2Selector,
+    Aria2State, Aria2Status, Aria2Task,
+};
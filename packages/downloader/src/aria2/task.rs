@@ -0,0 +1,239 @@
+use std::{borrow::Cow, time::Duration};
+
+use quirks_path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use serde_with::{DisplayFromStr, serde_as};
+
+use crate::{
+    bittorrent::{
+        labels::TorrentLabels,
+        source::HashTorrentSource,
+        task::{SimpleTorrentHash, TorrentCreationTrait, TorrentStateTrait, TorrentTaskTrait},
+    },
+    core::{
+        DownloadCreationTrait, DownloadIdSelector, DownloadSimpleState, DownloadStateTrait,
+        DownloadTaskTrait,
+    },
+};
+
+pub type Aria2Hash = SimpleTorrentHash;
+
+pub type Aria2Gid = String;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Aria2State {
+    Active,
+    Waiting,
+    Paused,
+    Error,
+    Complete,
+    Removed,
+}
+
+impl DownloadStateTrait for Aria2State {
+    fn to_download_state(&self) -> DownloadSimpleState {
+        match self {
+            Self::Active | Self::Waiting => DownloadSimpleState::Active,
+            Self::Paused => DownloadSimpleState::Paused,
+            Self::Error => DownloadSimpleState::Error,
+            Self::Complete => DownloadSimpleState::Completed,
+            Self::Removed => DownloadSimpleState::Unknown,
+        }
+    }
+}
+
+impl TorrentStateTrait for Aria2State {}
+
+/// aria2 keeps seeding torrents `active`, so completion is told from the
+/// lengths rather than the status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Aria2DownloadState {
+    pub status: Aria2State,
+    pub total_length: u64,
+    pub completed_length: u64,
+}
+
+impl Aria2DownloadState {
+    pub fn from_status(status: &Aria2Status) -> Self {
+        Self {
+            status: status.status,
+            total_length: status.total_length,
+            completed_length: status.completed_length,
+        }
+    }
+}
+
+impl DownloadStateTrait for Aria2DownloadState {
+    fn to_download_state(&self) -> DownloadSimpleState {
+        match self.status {
+            Aria2State::Active | Aria2State::Waiting
+                if self.total_length > 0 && self.completed_length == self.total_length =>
+            {
+                DownloadSimpleState::Completed
+            }
+            status => status.to_download_state(),
+        }
+    }
+}
+
+impl TorrentStateTrait for Aria2DownloadState {}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Aria2BittorrentInfo {
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Aria2BittorrentStatus {
+    pub info: Option<Aria2BittorrentInfo>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Aria2Status {
+    pub gid: Aria2Gid,
+    pub status: Aria2State,
+    #[serde_as(as = "DisplayFromStr")]
+    pub total_length: u64,
+    #[serde_as(as = "DisplayFromStr")]
+    pub completed_length: u64,
+    #[serde_as(as = "DisplayFromStr")]
+    pub download_speed: u64,
+    pub info_hash: Option<String>,
+    pub dir: Option<String>,
+    pub bittorrent: Option<Aria2BittorrentStatus>,
+    #[serde(default)]
+    pub followed_by: Vec<Aria2Gid>,
+    pub error_message: Option<String>,
+}
+
+impl Aria2Status {
+    pub const KEYS: &[&str] = &[
+        "gid",
+        "status",
+        "totalLength",
+        "completedLength",
+        "downloadSpeed",
+        "infoHash",
+        "dir",
+        "bittorrent",
+        "followedBy",
+        "errorMessage",
+    ];
+
+    /// Magnet links are resolved by a metadata-only download that is followed
+    /// by the real one, the metadata download should not be reported as a
+    /// torrent task.
+    pub fn is_metadata_only(&self) -> bool {
+        !self.followed_by.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub struct Aria2Task {
+    pub hash_info: Aria2Hash,
+    pub status: Aria2Status,
+    pub state: Aria2DownloadState,
+    pub labels: TorrentLabels,
+}
+
+impl Aria2Task {
+    pub fn from_status(status: Aria2Status, labels: TorrentLabels) -> Option<Self> {
+        let hash_info = status.info_hash.as_deref()?.to_lowercase();
+        Some(Self {
+            hash_info,
+            state: Aria2DownloadState::from_status(&status),
+            status,
+            labels,
+        })
+    }
+}
+
+impl DownloadTaskTrait for Aria2Task {
+    type State = Aria2DownloadState;
+    type Id = Aria2Hash;
+
+    fn id(&self) -> &Self::Id {
+        &self.hash_info
+    }
+
+    fn into_id(self) -> Self::Id {
+        self.hash_info
+    }
+
+    fn name(&self) -> Cow<'_, str> {
+        self.status
+            .bittorrent
+            .as_ref()
+            .and_then(|b| b.info.as_ref())
+            .and_then(|i| i.name.as_deref())
+            .map(Cow::Borrowed)
+            .unwrap_or_else(|| TorrentTaskTrait::name(self))
+    }
+
+    fn speed(&self) -> Option<u64> {
+        Some(self.status.download_speed)
+    }
+
+    fn state(&self) -> &Self::State {
+        &self.state
+    }
+
+    fn dl_bytes(&self) -> Option<u64> {
+        Some(self.status.completed_length)
+    }
+
+    fn total_bytes(&self) -> Option<u64> {
+        Some(self.status.total_length)
+    }
+
+    fn et(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl TorrentTaskTrait for Aria2Task {
+    fn hash_info(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.hash_info)
+    }
+
+    fn tags(&self) -> impl Iterator<Item = Cow<'_, str>> {
+        self.labels.tags.iter().map(|t| Cow::Borrowed(t.as_str()))
+    }
+
+    fn category(&self) -> Option<Cow<'_, str>> {
+        self.labels.category.as_deref().map(Cow::Borrowed)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Aria2Creation {
+    pub save_path: PathBuf,
+    pub tags: Vec<String>,
+    pub category: Option<String>,
+    pub sources: Vec<HashTorrentSource>,
+}
+
+impl DownloadCreationTrait for Aria2Creation {
+    type Task = Aria2Task;
+}
+
+impl TorrentCreationTrait for Aria2Creation {
+    fn save_path(&self) -> &Path {
+        self.save_path.as_ref()
+    }
+
+    fn save_path_mut(&mut self) -> &mut PathBuf {
+        &mut self.save_path
+    }
+
+    fn sources_mut(&mut self) -> &mut Vec<HashTorrentSource> {
+        &mut self.sources
+    }
+}
+
+pub type Aria2HashSelector = DownloadIdSelector<Aria2Task>;
+
+pub type Aria2Selector = Aria2HashSelector;
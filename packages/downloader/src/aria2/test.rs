@@ -0,0 +1,524 @@
+use futures::StreamExt;
+use mockito::{Matcher, Mock, ServerGuard};
+use serde_json::{Value, json};
+
+use crate::{
+    DownloaderError,
+    aria2::{
+        Aria2Creation, Aria2Downloader, Aria2DownloaderCreation, Aria2HashSelector, Aria2Status,
+    },
+    bittorrent::{
+        downloader::TorrentDownloaderTrait,
+        handle::TorrentDownloaderHandleTrait,
+        source::HashTorrentSource,
+        task::{TORRENT_TAG_NAME, TorrentTaskTrait},
+    },
+    core::{
+        DownloadEvent, DownloadIdSelectorTrait, DownloadSimpleState, DownloadStateTrait,
+        DownloadTaskTrait, DownloaderTrait,
+    },
+};
+
+const TEST_SECRET: &str = "konobangu";
+const TEST_SAVE_PATH: &str = "/downloads/konobangu";
+const TEST_HASH: &str = "47ee2d69e7f19af783ad896541a07b012676f858";
+const TEST_GID: &str = "2089b05ecca3d829";
+const TEST_METADATA_GID: &str = "d270c8a2c8a8e0a1";
+
+fn test_magnet_url() -> String {
+    format!("magnet:?xt=urn:btih:{TEST_HASH}&dn=test")
+}
+
+fn mock_rpc(
+    server: &mut ServerGuard,
+    method: &str,
+    params: Option<Value>,
+    response: Value,
+) -> Mock {
+    let body = match params {
+        Some(params) => json!({ "method": method, "params": params }),
+        None => json!({ "method": method }),
+    };
+
+    server
+        .mock("POST", "/jsonrpc")
+        .match_body(Matcher::PartialJson(body))
+        .with_header("content-type", "application/json")
+        .with_body(json!({ "id": "konobangu", "jsonrpc": "2.0", "result": response }).to_string())
+}
+
+async fn create_test_downloader(
+    server: &mut ServerGuard,
+) -> Result<std::sync::Arc<Aria2Downloader>, DownloaderError> {
+    mock_rpc(
+        server,
+        "aria2.getVersion",
+        None,
+        json!({ "version": "1.37.0", "enabledFeatures": ["BitTorrent"] }),
+    )
+    .create_async()
+    .await;
+
+    Aria2Downloader::from_creation(Aria2DownloaderCreation {
+        endpoint: format!("{}/jsonrpc", server.url()),
+        secret: Some(TEST_SECRET.to_string()),
+        save_path: TEST_SAVE_PATH.to_string(),
+        data_dir: Some(
+            std::env::temp_dir()
+                .join(format!(
+                    "konobangu-aria2-{}-{}",
+                    std::process::id(),
+                    server.socket_address().port()
+                ))
+                .to_string_lossy()
+                .into_owned(),
+        ),
+        subscriber_id: 0,
+        downloader_id: 0,
+    })
+    .await
+}
+
+async fn mock_statuses(server: &mut ServerGuard, active_state: &str) {
+    mock_rpc(
+        server,
+        "aria2.tellActive",
+        None,
+        json!([{
+            "gid": TEST_GID,
+            "status": active_state,
+            "totalLength": "1024",
+            "completedLength": "512",
+            "downloadSpeed": "128",
+            "infoHash": TEST_HASH,
+            "dir": format!("{TEST_SAVE_PATH}/bangumi"),
+            "bittorrent": { "info": { "name": "test torrent" } }
+        }]),
+    )
+    .create_async()
+    .await;
+    mock_rpc(server, "aria2.tellWaiting", None, json!([]))
+        .create_async()
+        .await;
+    mock_rpc(
+        server,
+        "aria2.tellStopped",
+        None,
+        json!([{
+            "gid": TEST_METADATA_GID,
+            "status": "complete",
+            "totalLength": "0",
+            "completedLength": "0",
+            "downloadSpeed": "0",
+            "infoHash": TEST_HASH,
+            "dir": TEST_SAVE_PATH,
+            "followedBy": [TEST_GID]
+        }]),
+    )
+    .create_async()
+    .await;
+}
+
+#[tokio::test]
+async fn test_aria2_downloader_add_and_query() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let downloader = create_test_downloader(&mut server).await?;
+
+    let add_uri = mock_rpc(
+        &mut server,
+        "aria2.addUri",
+        Some(json!([
+            format!("token:{TEST_SECRET}"),
+            [test_magnet_url()],
+            { "dir": format!("{TEST_SAVE_PATH}/bangumi") }
+        ])),
+        json!(TEST_GID),
+    )
+    .create_async()
+    .await;
+
+    let hashes = downloader
+        .add_downloads(Aria2Creation {
+            save_path: format!("{TEST_SAVE_PATH}/bangumi").into(),
+            sources: vec![HashTorrentSource::from_magnet_url(test_magnet_url())?],
+            ..Default::default()
+        })
+        .await?;
+
+    add_uri.assert_async().await;
+    assert_eq!(hashes, vec![TEST_HASH.to_string()]);
+
+    mock_statuses(&mut server, "active").await;
+
+    let tasks = downloader
+        .query_downloads(Aria2HashSelector::from_id(TEST_HASH.to_string()))
+        .await?;
+
+    assert_eq!(tasks.len(), 1);
+    let task = tasks.first().expect("should have task");
+    assert_eq!(task.hash_info(), TEST_HASH);
+    assert_eq!(DownloadTaskTrait::name(task), "test torrent");
+    assert_eq!(
+        task.state().to_download_state(),
+        DownloadSimpleState::Active
+    );
+    assert_eq!(task.dl_bytes(), Some(512));
+    assert_eq!(task.total_bytes(), Some(1024));
+    assert!(task.tags().any(|t| t == TORRENT_TAG_NAME));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_aria2_downloader_pages_waiting_and_stopped() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let downloader = create_test_downloader(&mut server).await?;
+
+    let page_size = 1000;
+    let full_page = (0..page_size)
+        .map(|i| {
+            json!({
+                "gid": format!("{i:016x}"),
+                "status": "waiting",
+                "totalLength": "0",
+                "completedLength": "0",
+                "downloadSpeed": "0",
+                "infoHash": format!("{i:040x}"),
+                "dir": TEST_SAVE_PATH,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    mock_rpc(&mut server, "aria2.tellActive", None, json!([]))
+        .create_async()
+        .await;
+    let first_page = mock_rpc(
+        &mut server,
+        "aria2.tellWaiting",
+        Some(json!([
+            format!("token:{TEST_SECRET}"),
+            0,
+            page_size,
+            Aria2Status::KEYS
+        ])),
+        json!(full_page),
+    )
+    .create_async()
+    .await;
+    let second_page = mock_rpc(
+        &mut server,
+        "aria2.tellWaiting",
+        Some(json!([
+            format!("token:{TEST_SECRET}"),
+            page_size,
+            page_size,
+            Aria2Status::KEYS
+        ])),
+        json!([{
+            "gid": TEST_GID,
+            "status": "paused",
+            "totalLength": "1024",
+            "completedLength": "0",
+            "downloadSpeed": "0",
+            "infoHash": TEST_HASH,
+            "dir": TEST_SAVE_PATH,
+        }]),
+    )
+    .create_async()
+    .await;
+    mock_rpc(&mut server, "aria2.tellStopped", None, json!([]))
+        .create_async()
+        .await;
+
+    let tasks = downloader
+        .query_downloads(Aria2HashSelector::from_id(TEST_HASH.to_string()))
+        .await?;
+
+    first_page.assert_async().await;
+    second_page.assert_async().await;
+    assert_eq!(tasks.len(), 1);
+    let task = tasks.first().expect("should have task");
+    assert_eq!(
+        task.state().to_download_state(),
+        DownloadSimpleState::Paused
+    );
+    // not added by konobangu, even though it is under the save path
+    assert!(!task.tags().any(|t| t == TORRENT_TAG_NAME));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_aria2_downloader_reports_seeding_as_completed() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let downloader = create_test_downloader(&mut server).await?;
+
+    // aria2 keeps a fully downloaded torrent active while it is seeding
+    mock_rpc(
+        &mut server,
+        "aria2.tellActive",
+        None,
+        json!([{
+            "gid": TEST_GID,
+            "status": "active",
+            "totalLength": "1024",
+            "completedLength": "1024",
+            "downloadSpeed": "0",
+            "infoHash": TEST_HASH,
+            "dir": format!("{TEST_SAVE_PATH}/bangumi"),
+            "bittorrent": { "info": { "name": "test torrent" } }
+        }]),
+    )
+    .create_async()
+    .await;
+    mock_rpc(&mut server, "aria2.tellWaiting", None, json!([]))
+        .create_async()
+        .await;
+    mock_rpc(&mut server, "aria2.tellStopped", None, json!([]))
+        .create_async()
+        .await;
+
+    let infos = downloader
+        .query_by_hashes(vec![TEST_HASH.to_string()])
+        .await?;
+
+    assert_eq!(infos.len(), 1);
+    assert_eq!(infos[0].state, DownloadSimpleState::Completed);
+    assert_eq!(infos[0].dl_bytes, Some(1024));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_aria2_downloader_pause_resume_and_remove() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let downloader = create_test_downloader(&mut server).await?;
+
+    mock_statuses(&mut server, "active").await;
+
+    let pause = mock_rpc(
+        &mut server,
+        "aria2.pause",
+        Some(json!([format!("token:{TEST_SECRET}"), TEST_GID])),
+        json!(TEST_GID),
+    )
+    .create_async()
+    .await;
+    let unpause = mock_rpc(&mut server, "aria2.unpause", None, json!(TEST_GID))
+        .expect(0)
+        .create_async()
+        .await;
+
+    downloader
+        .pause_torrents(vec![TEST_HASH.to_string()].into())
+        .await?;
+    downloader
+        .resume_torrents(vec![TEST_HASH.to_string()].into())
+        .await?;
+
+    pause.assert_async().await;
+    unpause.assert_async().await;
+
+    let force_remove = mock_rpc(
+        &mut server,
+        "aria2.forceRemove",
+        Some(json!([format!("token:{TEST_SECRET}"), TEST_GID])),
+        json!(TEST_GID),
+    )
+    .create_async()
+    .await;
+    let remove_result = mock_rpc(&mut server, "aria2.removeDownloadResult", None, json!("OK"))
+        .expect(2)
+        .create_async()
+        .await;
+
+    // aria2 can not delete files, so removing with files must not forget the
+    // download and leave its files behind
+    assert!(matches!(
+        downloader
+            .remove_by_hashes(vec![TEST_HASH.to_string()])
+            .await,
+        Err(DownloaderError::UnsupportedOperationError { .. })
+    ));
+
+    downloader
+        .remove_by_hashes_keep_files(vec![TEST_HASH.to_string()])
+        .await?;
+
+    force_remove.assert_async().await;
+    remove_result.assert_async().await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_aria2_downloader_move_rename_and_labels() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let downloader = create_test_downloader(&mut server).await?;
+
+    mock_statuses(&mut server, "paused").await;
+
+    let change_dir = mock_rpc(
+        &mut server,
+        "aria2.changeOption",
+        Some(json!([
+            format!("token:{TEST_SECRET}"),
+            TEST_GID,
+            { "dir": "/downloads/moved" }
+        ])),
+        json!("OK"),
+    )
+    .create_async()
+    .await;
+    mock_rpc(
+        &mut server,
+        "aria2.getFiles",
+        Some(json!([format!("token:{TEST_SECRET}"), TEST_GID])),
+        json!([{
+            "index": "1",
+            "path": format!("{TEST_SAVE_PATH}/bangumi/test/a.mkv"),
+            "length": "1024",
+            "selected": "true"
+        }]),
+    )
+    .create_async()
+    .await;
+    let change_index_out = mock_rpc(
+        &mut server,
+        "aria2.changeOption",
+        Some(json!([
+            format!("token:{TEST_SECRET}"),
+            TEST_GID,
+            { "index-out": "1=test/b.mkv" }
+        ])),
+        json!("OK"),
+    )
+    .create_async()
+    .await;
+
+    downloader
+        .move_torrents(vec![TEST_HASH.to_string()].into(), "/downloads/moved")
+        .await?;
+    downloader
+        .rename_torrent_file(TEST_HASH.to_string(), "test/a.mkv", "test/b.mkv")
+        .await?;
+    downloader
+        .add_torrent_tags(vec![TEST_HASH.to_string()].into(), vec!["test_tag".into()])
+        .await?;
+    downloader
+        .set_torrents_category(vec![TEST_HASH.to_string()].into(), "bangumi")
+        .await?;
+
+    change_dir.assert_async().await;
+    change_index_out.assert_async().await;
+
+    let task = downloader
+        .query_downloads(Aria2HashSelector::from_id(TEST_HASH.to_string()))
+        .await?
+        .into_iter()
+        .next()
+        .expect("should have task");
+    assert!(task.tags().any(|t| t == "test_tag"));
+    assert_eq!(task.category().as_deref(), Some("bangumi"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_aria2_downloader_move_active_fails() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let downloader = create_test_downloader(&mut server).await?;
+
+    mock_statuses(&mut server, "active").await;
+    let change_option = mock_rpc(&mut server, "aria2.changeOption", None, json!("OK"))
+        .expect(0)
+        .create_async()
+        .await;
+
+    let result = downloader
+        .move_torrents(vec![TEST_HASH.to_string()].into(), "/downloads/moved")
+        .await;
+
+    assert!(result.is_err());
+    change_option.assert_async().await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_aria2_downloader_watch_downloads() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let downloader = create_test_downloader(&mut server).await?;
+
+    mock_statuses(&mut server, "complete").await;
+
+    let events = downloader
+        .watch_downloads(vec![TEST_HASH.to_string()])
+        .take(4)
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+
+    assert_eq!(
+        events,
+        vec![
+            DownloadEvent::Added {
+                id: TEST_HASH.to_string(),
+                name: "test torrent".to_string(),
+            },
+            DownloadEvent::MetadataResolved {
+                id: TEST_HASH.to_string(),
+                name: "test torrent".to_string(),
+                total_bytes: Some(1024),
+            },
+            DownloadEvent::Progress {
+                id: TEST_HASH.to_string(),
+                dl_bytes: Some(512),
+                total_bytes: Some(1024),
+                speed: Some(128),
+            },
+            DownloadEvent::Completed {
+                id: TEST_HASH.to_string(),
+            },
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_aria2_downloader_rpc_error() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let downloader = create_test_downloader(&mut server).await?;
+
+    server
+        .mock("POST", "/jsonrpc")
+        .match_body(Matcher::PartialJson(json!({ "method": "aria2.addUri" })))
+        .with_header("content-type", "application/json")
+        .with_body(
+            json!({
+                "id": "konobangu",
+                "jsonrpc": "2.0",
+                "error": { "code": 1, "message": "Unauthorized" }
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+
+    let result = downloader
+        .add_downloads(Aria2Creation {
+            save_path: TEST_SAVE_PATH.into(),
+            sources: vec![HashTorrentSource::from_magnet_url(test_magnet_url())?],
+            ..Default::default()
+        })
+        .await;
+
+    assert!(matches!(
+        result,
+        Err(DownloaderError::Aria2RpcError { code: 1, .. })
+    ));
+
+    Ok(())
+}
@@ -1,2 +1,4 @@
 pub const BITTORRENT_MIME_TYPE: &str = "application/x-bittorrent";
+/// Torrent files are metadata only, anything larger is not a torrent
+pub const BITTORRENT_FILE_MAX_SIZE: u64 = 16 * 1024 * 1024;
 pub const MAGNET_SCHEMA: &str = "magnet";
@@ -71,4 +71,33 @@ where
         &self,
         hashes: Self::IdSelector,
     ) -> Result<Self::IdSelector, DownloaderError>;
+
+    /// Relocates the torrents to `save_path`, files already downloaded are
+    /// moved along.
+    async fn move_torrents(
+        &self,
+        hashes: Self::IdSelector,
+        save_path: &str,
+    ) -> Result<Self::IdSelector, DownloaderError>;
+
+    /// Renames a file inside a torrent, both paths are relative to the
+    /// torrent root.
+    async fn rename_torrent_file(
+        &self,
+        hash: Self::Id,
+        old_path: &str,
+        new_path: &str,
+    ) -> Result<(), DownloaderError>;
+
+    async fn add_torrent_tags(
+        &self,
+        hashes: Self::IdSelector,
+        tags: Vec<String>,
+    ) -> Result<Self::IdSelector, DownloaderError>;
+
+    async fn set_torrents_category(
+        &self,
+        hashes: Self::IdSelector,
+        category: &str,
+    ) -> Result<Self::IdSelector, DownloaderError>;
 }
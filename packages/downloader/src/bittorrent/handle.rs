@@ -0,0 +1,106 @@
+use std::{fmt::Debug, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use itertools::Itertools;
+use quirks_path::PathBuf;
+use snafu::whatever;
+
+use crate::{
+    DownloaderError,
+    bittorrent::{
+        seeding::{SeedingLimitAction, SeedingLimitReached, SeedingPolicy},
+        source::HashTorrentSource,
+    },
+    core::DownloadSimpleState,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TorrentDownloadInfo {
+    pub hash_info: String,
+    pub name: String,
+    pub state: DownloadSimpleState,
+    pub dl_bytes: Option<u64>,
+    pub total_bytes: Option<u64>,
+    pub save_path: Option<String>,
+    pub ratio: Option<f64>,
+    pub seeding_time: Option<Duration>,
+}
+
+#[async_trait]
+pub trait TorrentDownloaderHandleTrait: Send + Sync + Debug {
+    async fn add_sources(
+        &self,
+        save_path: PathBuf,
+        sources: Vec<HashTorrentSource>,
+    ) -> Result<Vec<String>, DownloaderError>;
+
+    async fn query_by_hashes(
+        &self,
+        hashes: Vec<String>,
+    ) -> Result<Vec<TorrentDownloadInfo>, DownloaderError>;
+
+    async fn pause_by_hashes(&self, hashes: Vec<String>) -> Result<(), DownloaderError>;
+
+    async fn resume_by_hashes(&self, hashes: Vec<String>) -> Result<(), DownloaderError>;
+
+    async fn remove_by_hashes(&self, hashes: Vec<String>) -> Result<(), DownloaderError>;
+
+    /// Removes torrents but keeps their downloaded files on disk.
+    async fn remove_by_hashes_keep_files(
+        &self,
+        hashes: Vec<String>,
+    ) -> Result<(), DownloaderError> {
+        whatever!(
+            "{self:?} can not remove torrents {} without their files",
+            hashes.join(", ")
+        )
+    }
+
+    /// Releases what the downloader holds open, such as an embedded session,
+    /// the handle must not be used afterwards.
+    async fn shutdown(&self) -> Result<(), DownloaderError> {
+        Ok(())
+    }
+
+    /// Takes the policy action on every completed torrent that reached one of
+    /// its limits and returns them.
+    async fn enforce_seeding_policy(
+        &self,
+        hashes: Vec<String>,
+        policy: &SeedingPolicy,
+    ) -> Result<Vec<SeedingLimitReached>, DownloaderError> {
+        if hashes.is_empty() || policy.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let reached = self
+            .query_by_hashes(hashes)
+            .await?
+            .into_iter()
+            .filter(|info| policy.is_reached(info))
+            .map(|info| SeedingLimitReached {
+                hash_info: info.hash_info,
+                action: policy.action,
+                ratio: info.ratio,
+                seeding_time: info.seeding_time,
+            })
+            .collect_vec();
+
+        let reached_hashes = reached.iter().map(|r| r.hash_info.clone()).collect_vec();
+        if !reached_hashes.is_empty() {
+            match policy.action {
+                SeedingLimitAction::Pause => self.pause_by_hashes(reached_hashes).await?,
+                SeedingLimitAction::Remove => {
+                    self.remove_by_hashes_keep_files(reached_hashes).await?
+                }
+                SeedingLimitAction::RemoveWithFiles => {
+                    self.remove_by_hashes(reached_hashes).await?
+                }
+            }
+        }
+
+        Ok(reached)
+    }
+}
+
+pub type TorrentDownloaderHandle = Arc<dyn TorrentDownloaderHandleTrait>;
@@ -0,0 +1,145 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+use tokio::sync::Mutex;
+
+use crate::{DownloaderError, bittorrent::task::SimpleTorrentHash};
+
+/// Folder that keeps the state konobangu needs on top of a downloader, inside
+/// the downloader save path unless configured otherwise.
+pub const TORRENT_STATE_FOLDER_NAME: &str = ".konobangu";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TorrentLabels {
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub category: Option<String>,
+    /// When the torrent was first seen finished, for downloaders that do not
+    /// keep track of seeding time themselves.
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// Torrent hash the labels are kept by in the sidecar file.
+pub trait TorrentLabelKey: Sized {
+    fn to_label_key(&self) -> String;
+
+    fn from_label_key(key: &str) -> Option<Self>;
+}
+
+impl TorrentLabelKey for SimpleTorrentHash {
+    fn to_label_key(&self) -> String {
+        self.to_lowercase()
+    }
+
+    fn from_label_key(key: &str) -> Option<Self> {
+        Some(key.to_string())
+    }
+}
+
+/// Tags and categories of downloaders without them are kept per info hash in
+/// a json sidecar file and written through on every change.
+#[derive(Debug)]
+pub struct TorrentLabelStore<H> {
+    path: PathBuf,
+    labels: RwLock<HashMap<String, TorrentLabels>>,
+    persist_lock: Mutex<()>,
+    marker: std::marker::PhantomData<fn() -> H>,
+}
+
+impl<H> TorrentLabelStore<H>
+where
+    H: TorrentLabelKey,
+{
+    pub async fn load(path: PathBuf) -> Result<Self, DownloaderError> {
+        let labels = match tokio::fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .with_whatever_context::<_, _, DownloaderError>(|_| {
+                    format!("failed to parse torrent labels from {}", path.display())
+                })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            path,
+            labels: RwLock::new(labels),
+            persist_lock: Mutex::new(()),
+            marker: std::marker::PhantomData,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self, hash: &H) -> TorrentLabels {
+        self.labels
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&hash.to_label_key())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn find_hashes<F>(&self, predicate: F) -> Vec<H>
+    where
+        F: Fn(&TorrentLabels) -> bool,
+    {
+        self.labels
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter(|(_, labels)| predicate(labels))
+            .filter_map(|(hash, _)| H::from_label_key(hash))
+            .collect()
+    }
+
+    pub async fn update<F>(&self, hashes: &[H], f: F) -> Result<(), DownloaderError>
+    where
+        F: Fn(&mut TorrentLabels),
+    {
+        {
+            let mut labels = self.labels.write().unwrap_or_else(|e| e.into_inner());
+            for hash in hashes {
+                f(labels.entry(hash.to_label_key()).or_default());
+            }
+        }
+        self.persist().await
+    }
+
+    pub async fn remove(&self, hashes: &[H]) -> Result<(), DownloaderError> {
+        {
+            let mut labels = self.labels.write().unwrap_or_else(|e| e.into_inner());
+            for hash in hashes {
+                labels.remove(&hash.to_label_key());
+            }
+        }
+        self.persist().await
+    }
+
+    async fn persist(&self) -> Result<(), DownloaderError> {
+        let _guard = self.persist_lock.lock().await;
+
+        let bytes = {
+            let labels = self.labels.read().unwrap_or_else(|e| e.into_inner());
+            serde_json::to_vec_pretty(&*labels)
+                .whatever_context::<_, DownloaderError>("failed to serialize torrent labels")?
+        };
+
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let tmp_path = self.path.with_extension("json.tmp");
+        tokio::fs::write(&tmp_path, bytes).await?;
+        tokio::fs::rename(&tmp_path, &self.path).await?;
+
+        Ok(())
+    }
+}
@@ -1,5 +1,9 @@
 pub mod defs;
 pub mod downloader;
+pub mod handle;
+pub mod labels;
+pub mod seeding;
+pub mod selection;
 pub mod source;
 pub mod task;
 
@@ -0,0 +1,117 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_with::{DurationSeconds, serde_as};
+
+use crate::{bittorrent::handle::TorrentDownloadInfo, core::DownloadSimpleState};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeedingLimitAction {
+    #[default]
+    Pause,
+    /// Removes the torrent but keeps the downloaded files.
+    Remove,
+    RemoveWithFiles,
+}
+
+/// Limits a completed torrent keeps seeding under, the action is taken as
+/// soon as any of them is reached.
+#[serde_as]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SeedingPolicy {
+    /// Uploaded bytes over the torrent size.
+    pub max_ratio: Option<f64>,
+    #[serde_as(as = "Option<DurationSeconds<u64>>")]
+    #[serde(default)]
+    pub max_seeding_time: Option<Duration>,
+    #[serde(default)]
+    pub action: SeedingLimitAction,
+}
+
+impl SeedingPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.max_ratio.is_none() && self.max_seeding_time.is_none()
+    }
+
+    /// Only completed torrents are checked, downloaders that do not report
+    /// ratio or seeding time never reach the matching limit.
+    pub fn is_reached(&self, info: &TorrentDownloadInfo) -> bool {
+        if info.state != DownloadSimpleState::Completed {
+            return false;
+        }
+        let ratio_reached = self
+            .max_ratio
+            .zip(info.ratio)
+            .is_some_and(|(max, ratio)| ratio >= max);
+        let seeding_time_reached = self
+            .max_seeding_time
+            .zip(info.seeding_time)
+            .is_some_and(|(max, seeding_time)| seeding_time >= max);
+
+        ratio_reached || seeding_time_reached
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeedingLimitReached {
+    pub hash_info: String,
+    pub action: SeedingLimitAction,
+    pub ratio: Option<f64>,
+    pub seeding_time: Option<Duration>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(state: DownloadSimpleState, ratio: f64, seeding_secs: u64) -> TorrentDownloadInfo {
+        TorrentDownloadInfo {
+            hash_info: "47ee2d69e7f19af783ad896541a07b012676f858".to_string(),
+            name: "test torrent".to_string(),
+            state,
+            dl_bytes: Some(1024),
+            total_bytes: Some(1024),
+            save_path: None,
+            ratio: Some(ratio),
+            seeding_time: Some(Duration::from_secs(seeding_secs)),
+        }
+    }
+
+    #[test]
+    fn test_seeding_policy_is_reached() {
+        let policy = SeedingPolicy {
+            max_ratio: Some(2.0),
+            max_seeding_time: Some(Duration::from_secs(3600)),
+            action: SeedingLimitAction::Remove,
+        };
+
+        assert!(!policy.is_reached(&info(DownloadSimpleState::Completed, 1.5, 60)));
+        assert!(policy.is_reached(&info(DownloadSimpleState::Completed, 2.0, 60)));
+        assert!(policy.is_reached(&info(DownloadSimpleState::Completed, 0.1, 3600)));
+        assert!(!policy.is_reached(&info(DownloadSimpleState::Active, 3.0, 7200)));
+        assert!(!SeedingPolicy::default().is_reached(&info(
+            DownloadSimpleState::Completed,
+            3.0,
+            7200
+        )));
+    }
+
+    #[test]
+    fn test_seeding_policy_serde() -> anyhow::Result<()> {
+        let policy: SeedingPolicy = serde_json::from_str(
+            r#"{ "max_ratio": 1.5, "max_seeding_time": 86400, "action": "remove_with_files" }"#,
+        )?;
+
+        assert_eq!(
+            policy,
+            SeedingPolicy {
+                max_ratio: Some(1.5),
+                max_seeding_time: Some(Duration::from_secs(86400)),
+                action: SeedingLimitAction::RemoveWithFiles,
+            }
+        );
+
+        Ok(())
+    }
+}
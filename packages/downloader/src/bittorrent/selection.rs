@@ -0,0 +1,193 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+
+use crate::errors::{DownloaderError, FileSelectionGlobSnafu};
+
+/// Which files of a torrent should be downloaded, files not selected are
+/// skipped once the torrent metadata is resolved.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TorrentFileSelection {
+    /// Globs matched against the path of the file inside the torrent, any file
+    /// is included when empty.
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Allowed file extensions without the leading dot, compared case
+    /// insensitively, any extension is allowed when empty.
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    pub max_file_size: Option<u64>,
+}
+
+impl TorrentFileSelection {
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty()
+            && self.exclude.is_empty()
+            && self.extensions.is_empty()
+            && self.max_file_size.is_none()
+    }
+
+    pub fn matcher(&self) -> Result<TorrentFileSelectionMatcher, DownloaderError> {
+        fn build_glob_set(patterns: &[String]) -> Result<Option<GlobSet>, DownloaderError> {
+            if patterns.is_empty() {
+                return Ok(None);
+            }
+            let mut builder = GlobSetBuilder::new();
+            for pattern in patterns {
+                builder.add(Glob::new(pattern).context(FileSelectionGlobSnafu {
+                    pattern: pattern.clone(),
+                })?);
+            }
+            let set = builder.build().context(FileSelectionGlobSnafu {
+                pattern: patterns.join(", "),
+            })?;
+            Ok(Some(set))
+        }
+
+        Ok(TorrentFileSelectionMatcher {
+            include: build_glob_set(&self.include)?,
+            exclude: build_glob_set(&self.exclude)?,
+            extensions: self
+                .extensions
+                .iter()
+                .map(|e| e.trim_start_matches('.').to_lowercase())
+                .collect(),
+            max_file_size: self.max_file_size,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TorrentFileSelectionMatcher {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+    extensions: Vec<String>,
+    max_file_size: Option<u64>,
+}
+
+impl TorrentFileSelectionMatcher {
+    pub fn is_selected(&self, file_path: &str, file_size: u64) -> bool {
+        let file_path = file_path.replace('\\', "/");
+
+        if self.max_file_size.is_some_and(|max| file_size > max) {
+            return false;
+        }
+        if !self.extensions.is_empty() {
+            let extension = file_path
+                .rsplit('/')
+                .next()
+                .and_then(|name| name.rsplit_once('.'))
+                .map(|(_, e)| e.to_lowercase())
+                .unwrap_or_default();
+            if !self.extensions.contains(&extension) {
+                return false;
+            }
+        }
+        if self
+            .include
+            .as_ref()
+            .is_some_and(|include| !include.is_match(&file_path))
+        {
+            return false;
+        }
+        if self
+            .exclude
+            .as_ref()
+            .is_some_and(|exclude| exclude.is_match(&file_path))
+        {
+            return false;
+        }
+        true
+    }
+
+    /// Returns the indexes of the selected files, files are given as
+    /// `(path, size)` in torrent order.
+    pub fn select_indexes<'a>(
+        &self,
+        files: impl IntoIterator<Item = (&'a str, u64)>,
+    ) -> Vec<usize> {
+        files
+            .into_iter()
+            .enumerate()
+            .filter(|(_, (path, size))| self.is_selected(path, *size))
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILES: &[(&str, u64)] = &[
+        (
+            "[LoliHouse] Bocchi/[LoliHouse] Bocchi - 01 [1080p].mkv",
+            1 << 30,
+        ),
+        (
+            "[LoliHouse] Bocchi/[LoliHouse] Bocchi - 01 [1080p].ass",
+            1 << 16,
+        ),
+        (
+            "[LoliHouse] Bocchi/NCOP/[LoliHouse] Bocchi - NCOP [1080p].mkv",
+            1 << 28,
+        ),
+        ("[LoliHouse] Bocchi/Fonts/SourceHanSans.otf", 1 << 24),
+    ];
+
+    #[test]
+    fn test_empty_selection_selects_all() -> Result<(), DownloaderError> {
+        let matcher = TorrentFileSelection::default().matcher()?;
+
+        assert_eq!(
+            matcher.select_indexes(FILES.iter().copied()),
+            vec![0, 1, 2, 3]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_selection_excludes_extras() -> Result<(), DownloaderError> {
+        let matcher = TorrentFileSelection {
+            exclude: vec!["**/NCOP/**".to_string(), "**/NCED/**".to_string()],
+            extensions: vec![".MKV".to_string(), "ass".to_string()],
+            ..Default::default()
+        }
+        .matcher()?;
+
+        assert_eq!(matcher.select_indexes(FILES.iter().copied()), vec![0, 1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_selection_include_and_max_file_size() -> Result<(), DownloaderError> {
+        let matcher = TorrentFileSelection {
+            include: vec!["**/*.mkv".to_string()],
+            max_file_size: Some(1 << 29),
+            ..Default::default()
+        }
+        .matcher()?;
+
+        assert_eq!(matcher.select_indexes(FILES.iter().copied()), vec![2]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_selection_invalid_glob() {
+        let result = TorrentFileSelection {
+            include: vec!["[".to_string()],
+            ..Default::default()
+        }
+        .matcher();
+
+        assert!(matches!(
+            result,
+            Err(DownloaderError::FileSelectionGlobError { .. })
+        ));
+    }
+}
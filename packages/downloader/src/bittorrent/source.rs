@@ -3,14 +3,16 @@ use std::{
     fmt::{Debug, Formatter},
 };
 
-use bytes::Bytes;
-use fetch::{bytes::fetch_bytes, client::HttpClientTrait};
+use bytes::{Bytes, BytesMut};
+use fetch::{FetchStreamOptions, client::HttpClientTrait, fetch_stream};
+use futures::TryStreamExt;
+use itertools::Itertools;
 use librqbit_core::{magnet::Magnet, torrent_metainfo, torrent_metainfo::TorrentMetaV1Owned};
 use snafu::ResultExt;
 use url::Url;
 use util::errors::AnyhowResultExt;
 
-use super::defs::MAGNET_SCHEMA;
+use super::defs::{BITTORRENT_FILE_MAX_SIZE, BITTORRENT_MIME_TYPE, MAGNET_SCHEMA};
 use crate::errors::{DownloadFetchSnafu, DownloaderError, MagnetFormatSnafu, TorrentMetaSnafu};
 
 pub trait HashTorrentSourceTrait: Sized {
@@ -114,11 +116,54 @@ impl TorrentFileSource {
             filename,
         })
     }
+    /// Tracker urls from `announce` and `announce-list`, in tier order and
+    /// without duplicates.
+    pub fn trackers(&self) -> Vec<String> {
+        self.meta
+            .announce
+            .iter()
+            .chain(self.meta.announce_list.iter().flatten())
+            .map(|tracker| String::from_utf8_lossy(AsRef::<[u8]>::as_ref(tracker)).into_owned())
+            .filter(|tracker| !tracker.is_empty())
+            .unique()
+            .collect()
+    }
+
+    /// Streams the torrent file, failing early on bodies that are too large
+    /// or are not served as a torrent.
+    async fn fetch_payload(
+        client: &impl HttpClientTrait,
+        url: &str,
+    ) -> Result<Bytes, fetch::FetchError> {
+        let stream = fetch_stream(
+            client,
+            url,
+            FetchStreamOptions {
+                max_size: Some(BITTORRENT_FILE_MAX_SIZE),
+                content_types: vec![
+                    BITTORRENT_MIME_TYPE.to_string(),
+                    String::from("application/octet-stream"),
+                ],
+                max_resume_attempts: 1,
+            },
+        )
+        .await?;
+
+        let payload = stream
+            .try_fold(BytesMut::new(), |mut acc, chunk| async move {
+                acc.extend_from_slice(&chunk);
+                Ok(acc)
+            })
+            .await?;
+
+        Ok(payload.freeze())
+    }
+
     pub async fn from_url_and_http_client(
         client: &impl HttpClientTrait,
         url: String,
     ) -> Result<TorrentFileSource, DownloaderError> {
-        let payload = fetch_bytes(client, &url)
+        let payload = Self::fetch_payload(client, &url)
             .await
             .boxed()
             .with_context(|_| DownloadFetchSnafu { url: url.clone() })?;
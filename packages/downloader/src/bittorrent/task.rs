@@ -1,9 +1,9 @@
-use std::{borrow::Cow, hash::Hash};
+use std::{borrow::Cow, hash::Hash, time::Duration};
 
 use quirks_path::{Path, PathBuf};
 
 use crate::{
-    bittorrent::source::HashTorrentSource,
+    bittorrent::{selection::TorrentFileSelection, source::HashTorrentSource},
     core::{DownloadCreationTrait, DownloadIdTrait, DownloadStateTrait, DownloadTaskTrait},
 };
 
@@ -33,6 +33,17 @@ where
     fn tags(&self) -> impl Iterator<Item = Cow<'_, str>>;
 
     fn category(&self) -> Option<Cow<'_, str>>;
+
+    /// Uploaded bytes over the torrent size, `None` when the downloader does
+    /// not report uploads.
+    fn ratio(&self) -> Option<f64> {
+        None
+    }
+
+    /// How long the torrent has been seeding since it completed.
+    fn seeding_time(&self) -> Option<Duration> {
+        None
+    }
 }
 
 pub trait TorrentCreationTrait: DownloadCreationTrait {
@@ -41,4 +52,10 @@ pub trait TorrentCreationTrait: DownloadCreationTrait {
     fn save_path_mut(&mut self) -> &mut PathBuf;
 
     fn sources_mut(&mut self) -> &mut Vec<HashTorrentSource>;
+
+    /// Downloaders that can not skip files ignore the selection and download
+    /// every file.
+    fn file_selection(&self) -> Option<&TorrentFileSelection> {
+        None
+    }
 }
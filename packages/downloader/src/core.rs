@@ -1,9 +1,17 @@
 use std::{
-    any::Any, borrow::Cow, fmt::Debug, hash::Hash, marker::PhantomData, ops::Deref, time::Duration,
+    any::Any,
+    borrow::Cow,
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::Debug,
+    hash::Hash,
+    marker::PhantomData,
+    ops::Deref,
+    time::Duration,
     vec::IntoIter,
 };
 
 use async_trait::async_trait;
+use futures::{StreamExt, stream::BoxStream};
 
 use super::DownloaderError;
 
@@ -20,7 +28,7 @@ pub trait DownloadStateTrait: Sized + Debug {
     fn to_download_state(&self) -> DownloadSimpleState;
 }
 
-pub trait DownloadIdTrait: Hash + Sized + Clone + Send + Debug {}
+pub trait DownloadIdTrait: Hash + Eq + Sized + Clone + Send + Sync + Debug {}
 
 pub trait DownloadTaskTrait: Sized + Send + Debug {
     type State: DownloadStateTrait;
@@ -226,4 +234,415 @@ pub trait DownloaderTrait {
         &self,
         selector: Self::Selector,
     ) -> Result<impl IntoIterator<Item = Self::Task>, DownloaderError>;
+
+    /// Streams state transitions of the downloads with the given ids.
+    ///
+    /// Downloads already present when the stream starts are reported as added
+    /// first. The default implementation polls [`Self::query_downloads`] every
+    /// [`DOWNLOAD_EVENT_POLL_INTERVAL`], downloaders that are notified of
+    /// changes by other means should override it.
+    fn watch_downloads(
+        &self,
+        ids: Vec<Self::Id>,
+    ) -> BoxStream<'_, Result<DownloadEvent<Self::Id>, DownloaderError>>
+    where
+        Self: Sync,
+        Self::Selector: From<Vec<Self::Id>>,
+    {
+        DownloadEventDiffer::<Self::Id>::default()
+            .stream(
+                download_event_ticks(DOWNLOAD_EVENT_POLL_INTERVAL).then(move |_| {
+                    let selector = Self::Selector::from(ids.clone());
+                    async move {
+                        self.query_downloads(selector)
+                            .await
+                            .map(|tasks| tasks.into_iter().collect::<Vec<_>>())
+                    }
+                }),
+            )
+            .boxed()
+    }
+}
+
+pub const DOWNLOAD_EVENT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Ticks once immediately and then every `interval`, for polling
+/// implementations of [`DownloaderTrait::watch_downloads`].
+pub fn download_event_ticks(interval: Duration) -> impl futures::Stream<Item = ()> + Send {
+    futures::stream::unfold(true, move |first| async move {
+        if !first {
+            tokio::time::sleep(interval).await;
+        }
+        Some(((), false))
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DownloadEvent<Id> {
+    Added {
+        id: Id,
+        name: String,
+    },
+    MetadataResolved {
+        id: Id,
+        name: String,
+        total_bytes: Option<u64>,
+    },
+    Progress {
+        id: Id,
+        dl_bytes: Option<u64>,
+        total_bytes: Option<u64>,
+        speed: Option<u64>,
+    },
+    Completed {
+        id: Id,
+    },
+    Errored {
+        id: Id,
+    },
+    Removed {
+        id: Id,
+    },
+}
+
+impl<Id> DownloadEvent<Id> {
+    pub fn id(&self) -> &Id {
+        match self {
+            Self::Added { id, .. }
+            | Self::MetadataResolved { id, .. }
+            | Self::Progress { id, .. }
+            | Self::Completed { id }
+            | Self::Errored { id }
+            | Self::Removed { id } => id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct DownloadTaskSnapshot {
+    state: DownloadSimpleState,
+    dl_bytes: Option<u64>,
+    has_metadata: bool,
+}
+
+/// Turns successive snapshots of downloads into [`DownloadEvent`]s by
+/// comparing each snapshot with the previous one.
+#[derive(Debug)]
+pub struct DownloadEventDiffer<Id: DownloadIdTrait> {
+    snapshots: HashMap<Id, DownloadTaskSnapshot>,
+}
+
+impl<Id: DownloadIdTrait> Default for DownloadEventDiffer<Id> {
+    fn default() -> Self {
+        Self {
+            snapshots: HashMap::new(),
+        }
+    }
+}
+
+impl<Id: DownloadIdTrait> DownloadEventDiffer<Id> {
+    pub fn diff<Task>(&mut self, tasks: impl IntoIterator<Item = Task>) -> Vec<DownloadEvent<Id>>
+    where
+        Task: DownloadTaskTrait<Id = Id>,
+    {
+        let mut events = vec![];
+        let mut seen = HashSet::new();
+
+        for task in tasks {
+            let id = task.id().clone();
+            let total_bytes = task.total_bytes();
+            let current = DownloadTaskSnapshot {
+                state: task.state().to_download_state(),
+                dl_bytes: task.dl_bytes(),
+                has_metadata: total_bytes.is_some_and(|t| t > 0),
+            };
+            let previous = self.snapshots.get(&id);
+
+            if previous.is_none() {
+                events.push(DownloadEvent::Added {
+                    id: id.clone(),
+                    name: task.name().to_string(),
+                });
+            }
+            if current.has_metadata && !previous.is_some_and(|p| p.has_metadata) {
+                events.push(DownloadEvent::MetadataResolved {
+                    id: id.clone(),
+                    name: task.name().to_string(),
+                    total_bytes,
+                });
+            }
+            if current.dl_bytes.is_some() && previous.is_none_or(|p| p.dl_bytes != current.dl_bytes)
+            {
+                events.push(DownloadEvent::Progress {
+                    id: id.clone(),
+                    dl_bytes: current.dl_bytes,
+                    total_bytes,
+                    speed: task.speed(),
+                });
+            }
+            if previous.is_none_or(|p| p.state != current.state) {
+                match current.state {
+                    DownloadSimpleState::Completed => {
+                        events.push(DownloadEvent::Completed { id: id.clone() })
+                    }
+                    DownloadSimpleState::Error => {
+                        events.push(DownloadEvent::Errored { id: id.clone() })
+                    }
+                    _ => {}
+                }
+            }
+
+            seen.insert(id.clone());
+            self.snapshots.insert(id, current);
+        }
+
+        let removed = self
+            .snapshots
+            .keys()
+            .filter(|id| !seen.contains(*id))
+            .cloned()
+            .collect::<Vec<_>>();
+        for id in removed {
+            self.snapshots.remove(&id);
+            events.push(DownloadEvent::Removed { id });
+        }
+
+        events
+    }
+
+    /// Diffs every snapshot yielded by `snapshots`, failed snapshots are
+    /// passed through and do not reset the known state.
+    pub fn stream<'a, Task>(
+        self,
+        snapshots: impl futures::Stream<Item = Result<Vec<Task>, DownloaderError>> + Send + 'a,
+    ) -> impl futures::Stream<Item = Result<DownloadEvent<Id>, DownloaderError>> + Send + 'a
+    where
+        Id: 'a,
+        Task: DownloadTaskTrait<Id = Id> + 'a,
+    {
+        futures::stream::unfold(
+            (self, snapshots.boxed(), VecDeque::new()),
+            |(mut differ, mut snapshots, mut pending)| async move {
+                loop {
+                    if let Some(event) = pending.pop_front() {
+                        return Some((Ok(event), (differ, snapshots, pending)));
+                    }
+                    match snapshots.next().await? {
+                        Ok(tasks) => pending.extend(differ.diff(tasks)),
+                        Err(e) => return Some((Err(e), (differ, snapshots, pending))),
+                    }
+                }
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct TestState(DownloadSimpleState);
+
+    impl DownloadStateTrait for TestState {
+        fn to_download_state(&self) -> DownloadSimpleState {
+            self.0
+        }
+    }
+
+    #[derive(Debug)]
+    struct TestTask {
+        id: String,
+        state: TestState,
+        dl_bytes: Option<u64>,
+        total_bytes: Option<u64>,
+    }
+
+    impl TestTask {
+        fn new(
+            id: &str,
+            state: DownloadSimpleState,
+            dl_bytes: Option<u64>,
+            total_bytes: Option<u64>,
+        ) -> Self {
+            Self {
+                id: id.to_string(),
+                state: TestState(state),
+                dl_bytes,
+                total_bytes,
+            }
+        }
+    }
+
+    impl DownloadTaskTrait for TestTask {
+        type State = TestState;
+        type Id = String;
+
+        fn id(&self) -> &Self::Id {
+            &self.id
+        }
+
+        fn into_id(self) -> Self::Id {
+            self.id
+        }
+
+        fn name(&self) -> Cow<'_, str> {
+            Cow::Owned(format!("task {}", self.id))
+        }
+
+        fn speed(&self) -> Option<u64> {
+            None
+        }
+
+        fn state(&self) -> &Self::State {
+            &self.state
+        }
+
+        fn dl_bytes(&self) -> Option<u64> {
+            self.dl_bytes
+        }
+
+        fn total_bytes(&self) -> Option<u64> {
+            self.total_bytes
+        }
+
+        fn et(&self) -> Option<Duration> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_download_event_differ_transitions() {
+        let mut differ = DownloadEventDiffer::<String>::default();
+
+        // a magnet without metadata yet
+        assert_eq!(
+            differ.diff([TestTask::new("a", DownloadSimpleState::Active, None, None)]),
+            vec![DownloadEvent::Added {
+                id: "a".to_string(),
+                name: "task a".to_string(),
+            }]
+        );
+
+        // nothing changed
+        assert_eq!(
+            differ.diff([TestTask::new("a", DownloadSimpleState::Active, None, None)]),
+            vec![]
+        );
+
+        assert_eq!(
+            differ.diff([TestTask::new(
+                "a",
+                DownloadSimpleState::Active,
+                Some(0),
+                Some(1024)
+            )]),
+            vec![
+                DownloadEvent::MetadataResolved {
+                    id: "a".to_string(),
+                    name: "task a".to_string(),
+                    total_bytes: Some(1024),
+                },
+                DownloadEvent::Progress {
+                    id: "a".to_string(),
+                    dl_bytes: Some(0),
+                    total_bytes: Some(1024),
+                    speed: None,
+                },
+            ]
+        );
+
+        assert_eq!(
+            differ.diff([TestTask::new(
+                "a",
+                DownloadSimpleState::Active,
+                Some(512),
+                Some(1024)
+            )]),
+            vec![DownloadEvent::Progress {
+                id: "a".to_string(),
+                dl_bytes: Some(512),
+                total_bytes: Some(1024),
+                speed: None,
+            }]
+        );
+
+        assert_eq!(
+            differ.diff([
+                TestTask::new("a", DownloadSimpleState::Completed, Some(1024), Some(1024)),
+                TestTask::new("b", DownloadSimpleState::Error, None, None),
+            ]),
+            vec![
+                DownloadEvent::Progress {
+                    id: "a".to_string(),
+                    dl_bytes: Some(1024),
+                    total_bytes: Some(1024),
+                    speed: None,
+                },
+                DownloadEvent::Completed {
+                    id: "a".to_string()
+                },
+                DownloadEvent::Added {
+                    id: "b".to_string(),
+                    name: "task b".to_string(),
+                },
+                DownloadEvent::Errored {
+                    id: "b".to_string()
+                },
+            ]
+        );
+
+        // still completed, reported once
+        assert_eq!(
+            differ.diff([TestTask::new(
+                "a",
+                DownloadSimpleState::Completed,
+                Some(1024),
+                Some(1024)
+            )]),
+            vec![DownloadEvent::Removed {
+                id: "b".to_string()
+            }]
+        );
+
+        assert_eq!(
+            differ.diff(Vec::<TestTask>::new()),
+            vec![DownloadEvent::Removed {
+                id: "a".to_string()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_event_differ_stream_keeps_state_on_error() {
+        let snapshots = futures::stream::iter(vec![
+            Ok(vec![TestTask::new(
+                "a",
+                DownloadSimpleState::Paused,
+                None,
+                None,
+            )]),
+            Err(<DownloaderError as snafu::FromString>::without_source(
+                "connection refused".to_string(),
+            )),
+            Ok(vec![TestTask::new(
+                "a",
+                DownloadSimpleState::Paused,
+                None,
+                None,
+            )]),
+        ]);
+
+        let events = DownloadEventDiffer::<String>::default()
+            .stream(snapshots)
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            &events[0],
+            Ok(DownloadEvent::Added { id, .. }) if id == "a"
+        ));
+        assert!(events[1].is_err());
+    }
 }
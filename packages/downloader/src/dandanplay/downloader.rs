@@ -0,0 +1,506 @@
+use std::{collections::HashSet, fmt::Debug, sync::Arc};
+
+use async_trait::async_trait;
+use fetch::{HttpClient, reqwest::header::CONTENT_TYPE, reqwest_middleware::RequestBuilder};
+use itertools::Itertools;
+use quirks_path::{Path, PathBuf};
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::json;
+use snafu::whatever;
+use tracing::instrument;
+use url::Url;
+
+use super::task::{
+    DandanplayCreation, DandanplayHash, DandanplayHashSelector, DandanplaySelector,
+    DandanplayState, DandanplayTask, DandanplayTaskId, DandanplayTaskStatus,
+};
+use crate::{
+    DownloaderError,
+    bittorrent::{
+        downloader::TorrentDownloaderTrait,
+        handle::{TorrentDownloadInfo, TorrentDownloaderHandleTrait},
+        labels::{TORRENT_STATE_FOLDER_NAME, TorrentLabelStore},
+        source::{HashTorrentSource, HashTorrentSourceTrait, MagnetUrlSource},
+        task::{TORRENT_TAG_NAME, TorrentTaskTrait},
+    },
+    core::{DownloadStateTrait, DownloadTaskTrait, DownloaderTrait},
+    errors::UnsupportedOperationSnafu,
+};
+
+pub const DANDANPLAY_LABELS_FILE_NAME: &str = "dandanplay-labels.json";
+
+#[derive(Debug)]
+pub struct DandanplayDownloaderCreation {
+    pub endpoint: String,
+    /// Remote access token, only required when the remote access of dandanplay
+    /// is protected by a token.
+    pub token: Option<String>,
+    pub save_path: String,
+    /// Where the tags and categories of tasks are kept, defaults to a hidden
+    /// folder inside the save path.
+    pub data_dir: Option<String>,
+    pub subscriber_id: i32,
+    pub downloader_id: i32,
+}
+
+impl DandanplayDownloaderCreation {
+    pub fn state_dir(&self) -> std::path::PathBuf {
+        self.data_dir
+            .as_ref()
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(|| {
+                std::path::Path::new(&self.save_path).join(TORRENT_STATE_FOLDER_NAME)
+            })
+    }
+}
+
+pub struct DandanplayDownloader {
+    pub subscriber_id: i32,
+    pub downloader_id: i32,
+    pub endpoint_url: Url,
+    pub token: Option<String>,
+    pub save_path: PathBuf,
+    pub client: HttpClient,
+    /// dandanplay has no tags or categories, they are kept in this sidecar
+    /// store and tasks added by konobangu are tagged with
+    /// [`TORRENT_TAG_NAME`].
+    pub labels: TorrentLabelStore<DandanplayHash>,
+}
+
+impl DandanplayDownloader {
+    #[instrument(level = "debug")]
+    pub async fn from_creation(
+        creation: DandanplayDownloaderCreation,
+    ) -> Result<Arc<Self>, DownloaderError> {
+        let mut endpoint_url = Url::parse(&creation.endpoint)?;
+        // api paths are joined onto the endpoint, which would otherwise drop
+        // the last segment of an endpoint served under a path
+        if !endpoint_url.path().ends_with('/') {
+            let path = format!("{}/", endpoint_url.path());
+            endpoint_url.set_path(&path);
+        }
+        let labels =
+            TorrentLabelStore::load(creation.state_dir().join(DANDANPLAY_LABELS_FILE_NAME)).await?;
+
+        let downloader = Arc::new(Self {
+            subscriber_id: creation.subscriber_id,
+            downloader_id: creation.downloader_id,
+            endpoint_url,
+            token: creation.token.filter(|s| !s.is_empty()),
+            save_path: creation.save_path.into(),
+            client: HttpClient::default(),
+            labels,
+        });
+
+        downloader.check_connection().await?;
+
+        Ok(downloader)
+    }
+
+    fn api_url(&self, path: &str) -> Result<Url, DownloaderError> {
+        Ok(self.endpoint_url.join(&format!("api/v1/{path}"))?)
+    }
+
+    fn with_auth(&self, request: RequestBuilder) -> RequestBuilder {
+        match self.token.as_deref() {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    async fn send<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<T, DownloaderError> {
+        let result = self
+            .with_auth(request)
+            .send()
+            .await?
+            .error_for_status()
+            .map_err(fetch::reqwest_middleware::Error::from)?
+            .json()
+            .await
+            .map_err(fetch::reqwest_middleware::Error::from)?;
+        Ok(result)
+    }
+
+    async fn send_without_body(&self, request: RequestBuilder) -> Result<(), DownloaderError> {
+        self.with_auth(request)
+            .send()
+            .await?
+            .error_for_status()
+            .map_err(fetch::reqwest_middleware::Error::from)?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn api_version(&self) -> Result<String, DownloaderError> {
+        #[derive(Deserialize)]
+        struct DandanplayWelcome {
+            version: String,
+        }
+
+        let welcome: DandanplayWelcome =
+            self.send(self.client.get(self.api_url("welcome")?)).await?;
+        Ok(welcome.version)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn check_connection(&self) -> Result<(), DownloaderError> {
+        self.api_version().await?;
+        Ok(())
+    }
+
+    /// The remote access api only accepts magnet links, a torrent file is
+    /// handed over as a magnet of its info hash, name and trackers, so peers
+    /// are still found without DHT.
+    fn source_to_magnet_url(source: HashTorrentSource) -> String {
+        match source {
+            HashTorrentSource::MagnetUrl(MagnetUrlSource { url, .. }) => url,
+            HashTorrentSource::TorrentFile(source) => {
+                let mut magnet_url = format!(
+                    "magnet:?xt=urn:btih:{}&dn={}",
+                    source.hash_info(),
+                    url::form_urlencoded::byte_serialize(source.filename.as_bytes())
+                        .collect::<String>()
+                );
+                for tracker in source.trackers() {
+                    magnet_url.push_str("&tr=");
+                    magnet_url.extend(url::form_urlencoded::byte_serialize(tracker.as_bytes()));
+                }
+                magnet_url
+            }
+        }
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn add_torrent(
+        &self,
+        source: HashTorrentSource,
+        save_path: &Path,
+    ) -> Result<(), DownloaderError> {
+        let body = json!({
+            "magnet": Self::source_to_magnet_url(source),
+            "savePath": save_path.as_str(),
+        });
+        self.send_without_body(
+            self.client
+                .post(self.api_url("download/tasks/add")?)
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.to_string()),
+        )
+        .await
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn list_statuses(&self) -> Result<Vec<DandanplayTaskStatus>, DownloaderError> {
+        self.send(self.client.get(self.api_url("download/tasks")?))
+            .await
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn list_statuses_by_hashes(
+        &self,
+        hashes: &[DandanplayHash],
+    ) -> Result<Vec<DandanplayTaskStatus>, DownloaderError> {
+        let hashes = hashes
+            .iter()
+            .map(|h| h.to_lowercase())
+            .collect::<HashSet<_>>();
+
+        Ok(self
+            .list_statuses()
+            .await?
+            .into_iter()
+            .filter(|s| s.hash_info().is_some_and(|h| hashes.contains(&h)))
+            .collect())
+    }
+
+    async fn call_for_each_task(
+        &self,
+        action: &'static str,
+        ids: impl IntoIterator<Item = DandanplayTaskId>,
+    ) -> Result<(), DownloaderError> {
+        futures::future::try_join_all(ids.into_iter().map(|id| async move {
+            let mut url = self.api_url(&format!("download/tasks/{id}/{action}"))?;
+            if action == "delete" {
+                url.query_pairs_mut().append_pair("deleteFile", "true");
+            }
+            self.send_without_body(self.client.get(url)).await
+        }))
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DownloaderTrait for DandanplayDownloader {
+    type State = DandanplayState;
+    type Id = DandanplayHash;
+    type Task = DandanplayTask;
+    type Creation = DandanplayCreation;
+    type Selector = DandanplaySelector;
+
+    #[instrument(level = "debug", skip(self))]
+    async fn add_downloads(
+        &self,
+        creation: <Self as DownloaderTrait>::Creation,
+    ) -> Result<Vec<<Self as DownloaderTrait>::Id>, DownloaderError> {
+        let save_path = creation.save_path;
+        let tags = {
+            let mut tags = vec![TORRENT_TAG_NAME.to_string()];
+            tags.extend(creation.tags);
+            tags.into_iter()
+                .filter(|s| !s.is_empty())
+                .unique()
+                .collect_vec()
+        };
+        let category = creation.category.filter(|s| !s.is_empty());
+        let hashes = creation
+            .sources
+            .iter()
+            .map(|s| s.hash_info().to_lowercase())
+            .collect::<Vec<_>>();
+
+        futures::future::try_join_all(
+            creation
+                .sources
+                .into_iter()
+                .map(|s| self.add_torrent(s, &save_path)),
+        )
+        .await?;
+
+        self.labels
+            .update(&hashes, |labels| {
+                labels.tags = tags.clone();
+                labels.category = category.clone();
+            })
+            .await?;
+
+        Ok(hashes)
+    }
+
+    async fn pause_downloads(
+        &self,
+        selector: <Self as DownloaderTrait>::Selector,
+    ) -> Result<impl IntoIterator<Item = Self::Id>, DownloaderError> {
+        <Self as TorrentDownloaderTrait>::pause_downloads(self, selector).await
+    }
+
+    async fn resume_downloads(
+        &self,
+        selector: <Self as DownloaderTrait>::Selector,
+    ) -> Result<impl IntoIterator<Item = Self::Id>, DownloaderError> {
+        <Self as TorrentDownloaderTrait>::resume_downloads(self, selector).await
+    }
+
+    async fn remove_downloads(
+        &self,
+        selector: <Self as DownloaderTrait>::Selector,
+    ) -> Result<impl IntoIterator<Item = Self::Id>, DownloaderError> {
+        <Self as TorrentDownloaderTrait>::remove_downloads(self, selector).await
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn query_downloads(
+        &self,
+        selector: DandanplaySelector,
+    ) -> Result<Vec<<Self as DownloaderTrait>::Task>, DownloaderError> {
+        let statuses = self.list_statuses_by_hashes(&selector).await?;
+
+        Ok(statuses
+            .into_iter()
+            .filter_map(|s| {
+                let labels = s
+                    .hash_info()
+                    .map(|h| self.labels.get(&h))
+                    .unwrap_or_default();
+                DandanplayTask::from_status(s, labels)
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl TorrentDownloaderTrait for DandanplayDownloader {
+    type IdSelector = DandanplayHashSelector;
+
+    #[instrument(level = "debug", skip(self))]
+    async fn pause_torrents(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        let ids = self
+            .list_statuses_by_hashes(&hashes)
+            .await?
+            .into_iter()
+            .filter(|s| {
+                matches!(
+                    s.state,
+                    DandanplayState::Queued | DandanplayState::Downloading
+                )
+            })
+            .map(|s| s.id);
+        self.call_for_each_task("pause", ids).await?;
+        Ok(hashes)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn resume_torrents(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        let ids = self
+            .list_statuses_by_hashes(&hashes)
+            .await?
+            .into_iter()
+            .filter(|s| matches!(s.state, DandanplayState::Paused | DandanplayState::Error))
+            .map(|s| s.id);
+        self.call_for_each_task("start", ids).await?;
+        Ok(hashes)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn remove_torrents(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        let ids = self
+            .list_statuses_by_hashes(&hashes)
+            .await?
+            .into_iter()
+            .map(|s| s.id);
+        self.call_for_each_task("delete", ids).await?;
+        self.labels.remove(&hashes).await?;
+        Ok(hashes)
+    }
+
+    async fn move_torrents(
+        &self,
+        _hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+        _save_path: &str,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        UnsupportedOperationSnafu {
+            downloader: "dandanplay",
+            operation: "moving torrents",
+        }
+        .fail()
+    }
+
+    async fn rename_torrent_file(
+        &self,
+        _hash: <Self as DownloaderTrait>::Id,
+        _old_path: &str,
+        _new_path: &str,
+    ) -> Result<(), DownloaderError> {
+        UnsupportedOperationSnafu {
+            downloader: "dandanplay",
+            operation: "renaming files inside torrents",
+        }
+        .fail()
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn add_torrent_tags(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+        tags: Vec<String>,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        if tags.is_empty() {
+            whatever!("add bittorrent tags can not be empty");
+        }
+        self.labels
+            .update(&hashes, |labels| {
+                labels.tags = labels
+                    .tags
+                    .iter()
+                    .chain(tags.iter())
+                    .unique()
+                    .cloned()
+                    .collect();
+            })
+            .await?;
+        Ok(hashes)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn set_torrents_category(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+        category: &str,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        self.labels
+            .update(&hashes, |labels| {
+                labels.category = Some(category.to_string());
+            })
+            .await?;
+        Ok(hashes)
+    }
+}
+
+#[async_trait]
+impl TorrentDownloaderHandleTrait for DandanplayDownloader {
+    async fn add_sources(
+        &self,
+        save_path: PathBuf,
+        sources: Vec<HashTorrentSource>,
+    ) -> Result<Vec<String>, DownloaderError> {
+        DownloaderTrait::add_downloads(
+            self,
+            DandanplayCreation {
+                save_path,
+                sources,
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    async fn query_by_hashes(
+        &self,
+        hashes: Vec<String>,
+    ) -> Result<Vec<TorrentDownloadInfo>, DownloaderError> {
+        if hashes.is_empty() {
+            return Ok(vec![]);
+        }
+        let tasks = DownloaderTrait::query_downloads(self, hashes.into()).await?;
+
+        Ok(tasks
+            .into_iter()
+            .map(|task| TorrentDownloadInfo {
+                name: DownloadTaskTrait::name(&task).to_string(),
+                state: task.state().to_download_state(),
+                dl_bytes: task.dl_bytes(),
+                total_bytes: task.total_bytes(),
+                save_path: task.status.save_path.clone(),
+                hash_info: task.hash_info().to_string(),
+                ratio: TorrentTaskTrait::ratio(&task),
+                seeding_time: TorrentTaskTrait::seeding_time(&task),
+            })
+            .collect())
+    }
+
+    async fn pause_by_hashes(&self, hashes: Vec<String>) -> Result<(), DownloaderError> {
+        TorrentDownloaderTrait::pause_torrents(self, hashes.into()).await?;
+        Ok(())
+    }
+
+    async fn resume_by_hashes(&self, hashes: Vec<String>) -> Result<(), DownloaderError> {
+        TorrentDownloaderTrait::resume_torrents(self, hashes.into()).await?;
+        Ok(())
+    }
+
+    async fn remove_by_hashes(&self, hashes: Vec<String>) -> Result<(), DownloaderError> {
+        TorrentDownloaderTrait::remove_torrents(self, hashes.into()).await?;
+        Ok(())
+    }
+}
+
+impl Debug for DandanplayDownloader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DandanplayDownloader")
+            .field("subscriber_id", &self.subscriber_id)
+            .field("client", &self.endpoint_url.as_str())
+            .finish()
+    }
+}
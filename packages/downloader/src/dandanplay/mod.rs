@@ -0,0 +1,11 @@
+pub mod downloader;
+pub mod task;
+
+#[cfg(test)]
+mod test;
+
+pub use downloader::{DandanplayDownloader, DandanplayDownloaderCreation};
+pub use task::{
+    DandanplayCreation, DandanplayHash, DandanplayHashSelector, DandanplaySelector,
+    DandanplayState, DandanplayTask, DandanplayTaskId, DandanplayTaskStatus,
+};
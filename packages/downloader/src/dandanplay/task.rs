@@ -0,0 +1,178 @@
+use std::{borrow::Cow, time::Duration};
+
+use quirks_path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bittorrent::{
+        labels::TorrentLabels,
+        source::{HashTorrentSource, HashTorrentSourceTrait, MagnetUrlSource},
+        task::{SimpleTorrentHash, TorrentCreationTrait, TorrentStateTrait, TorrentTaskTrait},
+    },
+    core::{
+        DownloadCreationTrait, DownloadIdSelector, DownloadSimpleState, DownloadStateTrait,
+        DownloadTaskTrait,
+    },
+};
+
+pub type DandanplayHash = SimpleTorrentHash;
+
+pub type DandanplayTaskId = String;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DandanplayState {
+    Queued,
+    Downloading,
+    Paused,
+    Seeding,
+    Completed,
+    Error,
+    #[serde(other)]
+    Unknown,
+}
+
+impl DownloadStateTrait for DandanplayState {
+    fn to_download_state(&self) -> DownloadSimpleState {
+        match self {
+            Self::Queued | Self::Downloading => DownloadSimpleState::Active,
+            Self::Paused => DownloadSimpleState::Paused,
+            Self::Seeding | Self::Completed => DownloadSimpleState::Completed,
+            Self::Error => DownloadSimpleState::Error,
+            Self::Unknown => DownloadSimpleState::Unknown,
+        }
+    }
+}
+
+impl TorrentStateTrait for DandanplayState {}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DandanplayTaskStatus {
+    pub id: DandanplayTaskId,
+    pub name: Option<String>,
+    pub magnet: String,
+    pub state: DandanplayState,
+    #[serde(default)]
+    pub progress: f64,
+    pub total_bytes: Option<u64>,
+    pub downloaded_bytes: Option<u64>,
+    pub download_speed: Option<u64>,
+    pub save_path: Option<String>,
+}
+
+impl DandanplayTaskStatus {
+    /// Dandanplay identifies tasks by its own id, the info hash is recovered
+    /// from the magnet link the task was created with.
+    pub fn hash_info(&self) -> Option<DandanplayHash> {
+        MagnetUrlSource::from_url(self.magnet.clone())
+            .ok()
+            .map(|m| m.hash_info().to_lowercase())
+    }
+}
+
+#[derive(Debug)]
+pub struct DandanplayTask {
+    pub hash_info: DandanplayHash,
+    pub status: DandanplayTaskStatus,
+    pub labels: TorrentLabels,
+}
+
+impl DandanplayTask {
+    pub fn from_status(status: DandanplayTaskStatus, labels: TorrentLabels) -> Option<Self> {
+        let hash_info = status.hash_info()?;
+        Some(Self {
+            hash_info,
+            status,
+            labels,
+        })
+    }
+}
+
+impl DownloadTaskTrait for DandanplayTask {
+    type State = DandanplayState;
+    type Id = DandanplayHash;
+
+    fn id(&self) -> &Self::Id {
+        &self.hash_info
+    }
+
+    fn into_id(self) -> Self::Id {
+        self.hash_info
+    }
+
+    fn name(&self) -> Cow<'_, str> {
+        self.status
+            .name
+            .as_deref()
+            .map(Cow::Borrowed)
+            .unwrap_or_else(|| TorrentTaskTrait::name(self))
+    }
+
+    fn speed(&self) -> Option<u64> {
+        self.status.download_speed
+    }
+
+    fn state(&self) -> &Self::State {
+        &self.status.state
+    }
+
+    fn dl_bytes(&self) -> Option<u64> {
+        self.status.downloaded_bytes
+    }
+
+    fn total_bytes(&self) -> Option<u64> {
+        self.status.total_bytes
+    }
+
+    fn et(&self) -> Option<Duration> {
+        None
+    }
+
+    fn progress(&self) -> Option<f32> {
+        Some(self.status.progress as f32)
+    }
+}
+
+impl TorrentTaskTrait for DandanplayTask {
+    fn hash_info(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.hash_info)
+    }
+
+    fn tags(&self) -> impl Iterator<Item = Cow<'_, str>> {
+        self.labels.tags.iter().map(|t| Cow::Borrowed(t.as_str()))
+    }
+
+    fn category(&self) -> Option<Cow<'_, str>> {
+        self.labels.category.as_deref().map(Cow::Borrowed)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DandanplayCreation {
+    pub save_path: PathBuf,
+    pub tags: Vec<String>,
+    pub category: Option<String>,
+    pub sources: Vec<HashTorrentSource>,
+}
+
+impl DownloadCreationTrait for DandanplayCreation {
+    type Task = DandanplayTask;
+}
+
+impl TorrentCreationTrait for DandanplayCreation {
+    fn save_path(&self) -> &Path {
+        self.save_path.as_ref()
+    }
+
+    fn save_path_mut(&mut self) -> &mut PathBuf {
+        &mut self.save_path
+    }
+
+    fn sources_mut(&mut self) -> &mut Vec<HashTorrentSource> {
+        &mut self.sources
+    }
+}
+
+pub type DandanplayHashSelector = DownloadIdSelector<DandanplayTask>;
+
+pub type DandanplaySelector = DandanplayHashSelector;
@@ -0,0 +1,340 @@
+use mockito::{Matcher, ServerGuard};
+use serde_json::json;
+use testing_torrents::bencode::BencodeValue;
+
+use crate::{
+    DownloaderError,
+    bittorrent::{
+        downloader::TorrentDownloaderTrait,
+        source::{HashTorrentSource, HashTorrentSourceTrait, TorrentFileSource},
+        task::{TORRENT_TAG_NAME, TorrentTaskTrait},
+    },
+    core::{
+        DownloadIdSelectorTrait, DownloadSimpleState, DownloadStateTrait, DownloadTaskTrait,
+        DownloaderTrait,
+    },
+    dandanplay::{
+        DandanplayCreation, DandanplayDownloader, DandanplayDownloaderCreation,
+        DandanplayHashSelector,
+    },
+};
+
+const TEST_TOKEN: &str = "konobangu";
+const TEST_SAVE_PATH: &str = "/downloads/konobangu";
+const TEST_HASH: &str = "47ee2d69e7f19af783ad896541a07b012676f858";
+const TEST_TASK_ID: &str = "6f1c1a7e-2d0c-4a7b-9f57-0b9a4f8a7c11";
+
+fn test_magnet_url() -> String {
+    format!("magnet:?xt=urn:btih:{TEST_HASH}&dn=test")
+}
+
+fn bearer() -> String {
+    format!("Bearer {TEST_TOKEN}")
+}
+
+fn test_data_dir(server: &ServerGuard) -> String {
+    std::env::temp_dir()
+        .join(format!(
+            "konobangu-dandanplay-{}-{}",
+            std::process::id(),
+            server.socket_address().port()
+        ))
+        .to_string_lossy()
+        .into_owned()
+}
+
+async fn create_test_downloader(
+    server: &mut ServerGuard,
+) -> Result<std::sync::Arc<DandanplayDownloader>, DownloaderError> {
+    server
+        .mock("GET", "/api/v1/welcome")
+        .match_header("authorization", bearer().as_str())
+        .with_header("content-type", "application/json")
+        .with_body(
+            json!({ "message": "hello", "version": "16.3.0", "tokenRequired": true }).to_string(),
+        )
+        .create_async()
+        .await;
+
+    DandanplayDownloader::from_creation(DandanplayDownloaderCreation {
+        endpoint: server.url(),
+        token: Some(TEST_TOKEN.to_string()),
+        save_path: TEST_SAVE_PATH.to_string(),
+        data_dir: Some(test_data_dir(server)),
+        subscriber_id: 0,
+        downloader_id: 0,
+    })
+    .await
+}
+
+async fn mock_tasks(server: &mut ServerGuard, state: &str) {
+    server
+        .mock("GET", "/api/v1/download/tasks")
+        .match_header("authorization", bearer().as_str())
+        .with_header("content-type", "application/json")
+        .with_body(
+            json!([{
+                "Id": TEST_TASK_ID,
+                "Name": "test torrent",
+                "Magnet": test_magnet_url(),
+                "State": state,
+                "Progress": 0.5,
+                "TotalBytes": 1024,
+                "DownloadedBytes": 512,
+                "DownloadSpeed": 128,
+                "SavePath": format!("{TEST_SAVE_PATH}/bangumi")
+            }])
+            .to_string(),
+        )
+        .create_async()
+        .await;
+}
+
+#[tokio::test]
+async fn test_dandanplay_downloader_add_and_query() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let downloader = create_test_downloader(&mut server).await?;
+
+    let add = server
+        .mock("POST", "/api/v1/download/tasks/add")
+        .match_header("authorization", bearer().as_str())
+        .match_body(Matcher::PartialJson(json!({
+            "magnet": test_magnet_url(),
+            "savePath": format!("{TEST_SAVE_PATH}/bangumi")
+        })))
+        .create_async()
+        .await;
+
+    let hashes = downloader
+        .add_downloads(DandanplayCreation {
+            save_path: format!("{TEST_SAVE_PATH}/bangumi").into(),
+            sources: vec![HashTorrentSource::from_magnet_url(test_magnet_url())?],
+            ..Default::default()
+        })
+        .await?;
+
+    add.assert_async().await;
+    assert_eq!(hashes, vec![TEST_HASH.to_string()]);
+
+    mock_tasks(&mut server, "Downloading").await;
+
+    let tasks = downloader
+        .query_downloads(DandanplayHashSelector::from_id(TEST_HASH.to_string()))
+        .await?;
+
+    assert_eq!(tasks.len(), 1);
+    let task = tasks.first().expect("should have task");
+    assert_eq!(task.hash_info(), TEST_HASH);
+    assert_eq!(DownloadTaskTrait::name(task), "test torrent");
+    assert_eq!(
+        task.state().to_download_state(),
+        DownloadSimpleState::Active
+    );
+    assert_eq!(task.dl_bytes(), Some(512));
+    assert_eq!(task.total_bytes(), Some(1024));
+    assert!(task.tags().any(|t| t == TORRENT_TAG_NAME));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dandanplay_downloader_tags_and_category() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let downloader = create_test_downloader(&mut server).await?;
+
+    server
+        .mock("POST", "/api/v1/download/tasks/add")
+        .create_async()
+        .await;
+    mock_tasks(&mut server, "Downloading").await;
+
+    downloader
+        .add_downloads(DandanplayCreation {
+            save_path: format!("{TEST_SAVE_PATH}/bangumi").into(),
+            tags: vec!["test_tag".to_string()],
+            category: Some("bangumi".to_string()),
+            sources: vec![HashTorrentSource::from_magnet_url(test_magnet_url())?],
+        })
+        .await?;
+    downloader
+        .add_torrent_tags(vec![TEST_HASH.to_string()].into(), vec!["other_tag".into()])
+        .await?;
+    downloader
+        .set_torrents_category(vec![TEST_HASH.to_string()].into(), "movie")
+        .await?;
+
+    let task = downloader
+        .query_downloads(DandanplayHashSelector::from_id(TEST_HASH.to_string()))
+        .await?
+        .into_iter()
+        .next()
+        .expect("should have task");
+    assert_eq!(
+        task.tags().collect::<Vec<_>>(),
+        vec![TORRENT_TAG_NAME, "test_tag", "other_tag"]
+    );
+    assert_eq!(task.category().as_deref(), Some("movie"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dandanplay_downloader_add_torrent_file_keeps_trackers() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let downloader = create_test_downloader(&mut server).await?;
+
+    let tracker_a = "http://tracker-a.example/announce";
+    let tracker_b = "udp://tracker-b.example:6969/announce";
+    let torrent = BencodeValue::dict([
+        ("announce", BencodeValue::from(tracker_a)),
+        (
+            "announce-list",
+            BencodeValue::from(vec![
+                BencodeValue::from(vec![BencodeValue::from(tracker_a)]),
+                BencodeValue::from(vec![BencodeValue::from(tracker_b)]),
+            ]),
+        ),
+        (
+            "info",
+            BencodeValue::dict([
+                ("length", BencodeValue::from(1024u64)),
+                ("name", BencodeValue::from("test.mkv")),
+                ("piece length", BencodeValue::from(16384u64)),
+                ("pieces", BencodeValue::from(vec![0u8; 20])),
+            ]),
+        ),
+    ]);
+    let source =
+        TorrentFileSource::from_bytes("test.torrent".to_string(), torrent.encode().into(), None)?;
+    let hash = source.hash_info().to_string();
+
+    let add = server
+        .mock("POST", "/api/v1/download/tasks/add")
+        .match_header("authorization", bearer().as_str())
+        .match_body(Matcher::PartialJson(json!({
+            "magnet": format!(
+                "magnet:?xt=urn:btih:{hash}&dn=test.torrent\
+                 &tr=http%3A%2F%2Ftracker-a.example%2Fannounce\
+                 &tr=udp%3A%2F%2Ftracker-b.example%3A6969%2Fannounce"
+            ),
+        })))
+        .create_async()
+        .await;
+
+    downloader
+        .add_downloads(DandanplayCreation {
+            save_path: TEST_SAVE_PATH.into(),
+            sources: vec![HashTorrentSource::TorrentFile(source)],
+            ..Default::default()
+        })
+        .await?;
+
+    add.assert_async().await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dandanplay_downloader_pause_resume_and_remove() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let downloader = create_test_downloader(&mut server).await?;
+
+    mock_tasks(&mut server, "Downloading").await;
+
+    let pause = server
+        .mock(
+            "GET",
+            format!("/api/v1/download/tasks/{TEST_TASK_ID}/pause").as_str(),
+        )
+        .create_async()
+        .await;
+    let start = server
+        .mock(
+            "GET",
+            format!("/api/v1/download/tasks/{TEST_TASK_ID}/start").as_str(),
+        )
+        .expect(0)
+        .create_async()
+        .await;
+    let delete = server
+        .mock(
+            "GET",
+            format!("/api/v1/download/tasks/{TEST_TASK_ID}/delete").as_str(),
+        )
+        .match_query(Matcher::UrlEncoded("deleteFile".into(), "true".into()))
+        .create_async()
+        .await;
+
+    downloader
+        .pause_torrents(vec![TEST_HASH.to_string()].into())
+        .await?;
+    downloader
+        .resume_torrents(vec![TEST_HASH.to_string()].into())
+        .await?;
+    downloader
+        .remove_torrents(vec![TEST_HASH.to_string()].into())
+        .await?;
+
+    pause.assert_async().await;
+    start.assert_async().await;
+    delete.assert_async().await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dandanplay_downloader_unauthorized() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    server
+        .mock("GET", "/api/v1/welcome")
+        .with_status(401)
+        .create_async()
+        .await;
+
+    let result = DandanplayDownloader::from_creation(DandanplayDownloaderCreation {
+        endpoint: server.url(),
+        token: None,
+        save_path: TEST_SAVE_PATH.to_string(),
+        data_dir: Some(test_data_dir(&server)),
+        subscriber_id: 0,
+        downloader_id: 0,
+    })
+    .await;
+
+    assert!(matches!(
+        result,
+        Err(DownloaderError::DownloadHttpError { .. })
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dandanplay_downloader_endpoint_with_path() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let welcome = server
+        .mock("GET", "/dandan/api/v1/welcome")
+        .match_header("authorization", bearer().as_str())
+        .with_header("content-type", "application/json")
+        .with_body(
+            json!({ "message": "hello", "version": "16.3.0", "tokenRequired": true }).to_string(),
+        )
+        .create_async()
+        .await;
+
+    let downloader = DandanplayDownloader::from_creation(DandanplayDownloaderCreation {
+        endpoint: format!("{}/dandan", server.url()),
+        token: Some(TEST_TOKEN.to_string()),
+        save_path: TEST_SAVE_PATH.to_string(),
+        data_dir: Some(test_data_dir(&server)),
+        subscriber_id: 0,
+        downloader_id: 0,
+    })
+    .await?;
+
+    welcome.assert_async().await;
+    assert_eq!(downloader.endpoint_url.path(), "/dandan/");
+
+    Ok(())
+}
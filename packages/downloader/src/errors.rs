@@ -12,6 +12,26 @@ pub enum DownloaderError {
     QBitAPIError { source: qbit_rs::Error },
     #[snafu(transparent)]
     DownloaderIOError { source: std::io::Error },
+    #[snafu(transparent)]
+    DownloadHttpError {
+        source: fetch::reqwest_middleware::Error,
+    },
+    #[snafu(display("Aria2 rpc error (method = {method}, code = {code}): {message}"))]
+    Aria2RpcError {
+        method: Cow<'static, str>,
+        code: i64,
+        message: String,
+    },
+    #[snafu(display("Transmission rpc error (method = {method}): {result}"))]
+    TransmissionRpcError {
+        method: Cow<'static, str>,
+        result: String,
+    },
+    #[snafu(display("{downloader} does not support {operation}"))]
+    UnsupportedOperationError {
+        downloader: Cow<'static, str>,
+        operation: Cow<'static, str>,
+    },
     #[snafu(display("Timeout error (action = {action}, timeout = {timeout:?})"))]
     DownloadTimeoutError {
         action: Cow<'static, str>,
@@ -29,6 +49,11 @@ pub enum DownloaderError {
         #[snafu(source(from(Box<dyn std::error::Error + Send + Sync>, OptDynErr::some)))]
         source: OptDynErr,
     },
+    #[snafu(display("Invalid file selection glob ({pattern}): {source}"))]
+    FileSelectionGlobError {
+        pattern: String,
+        source: globset::Error,
+    },
     #[snafu(display("Failed to fetch: {source}"))]
     DownloadFetchError {
         url: String,
@@ -0,0 +1,473 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Debug,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use itertools::Itertools;
+use quirks_path::PathBuf;
+use snafu::whatever;
+
+use super::task::{FakeCreation, FakeHash, FakeHashSelector, FakeSelector, FakeState, FakeTask};
+use crate::{
+    DownloaderError,
+    bittorrent::{
+        downloader::TorrentDownloaderTrait,
+        handle::{TorrentDownloadInfo, TorrentDownloaderHandleTrait},
+        source::{HashTorrentSource, HashTorrentSourceTrait, TorrentFileSource},
+        task::{TORRENT_TAG_NAME, TorrentTaskTrait},
+    },
+    core::{DownloadSimpleState, DownloadStateTrait, DownloadTaskTrait, DownloaderTrait},
+};
+
+pub const FAKE_DEFAULT_TOTAL_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FakeOperation {
+    Add,
+    Pause,
+    Resume,
+    Remove,
+    Query,
+    Move,
+    Rename,
+    Label,
+}
+
+#[derive(Debug, Default)]
+pub struct FakeDownloaderCreation {
+    pub save_path: String,
+    /// Size given to every added download, defaults to
+    /// [`FAKE_DEFAULT_TOTAL_BYTES`].
+    pub total_bytes: Option<u64>,
+}
+
+#[derive(Default)]
+struct FakeDownloaderInner {
+    tasks: HashMap<FakeHash, FakeTask>,
+    failures: HashMap<FakeOperation, VecDeque<String>>,
+    history: Vec<(FakeOperation, Vec<FakeHash>)>,
+}
+
+/// Keeps downloads in memory, progress only moves when driven by the test
+/// through [`FakeDownloader::advance`], [`FakeDownloader::set_progress`] and
+/// friends.
+pub struct FakeDownloader {
+    pub save_path: PathBuf,
+    pub total_bytes: u64,
+    inner: Mutex<FakeDownloaderInner>,
+}
+
+impl FakeDownloader {
+    pub fn from_creation(creation: FakeDownloaderCreation) -> Arc<Self> {
+        Arc::new(Self {
+            save_path: creation.save_path.into(),
+            total_bytes: creation.total_bytes.unwrap_or(FAKE_DEFAULT_TOTAL_BYTES),
+            inner: Mutex::new(FakeDownloaderInner::default()),
+        })
+    }
+
+    fn with_inner<R>(&self, f: impl FnOnce(&mut FakeDownloaderInner) -> R) -> R {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut inner)
+    }
+
+    fn record(&self, operation: FakeOperation, hashes: &[FakeHash]) -> Result<(), DownloaderError> {
+        self.with_inner(|inner| {
+            inner.history.push((operation, hashes.to_vec()));
+            match inner
+                .failures
+                .get_mut(&operation)
+                .and_then(|f| f.pop_front())
+            {
+                Some(message) => Err(DownloaderError::Whatever {
+                    message,
+                    source: None.into(),
+                }),
+                None => Ok(()),
+            }
+        })
+    }
+
+    /// The next call of `operation` fails with `message`, failures queued for
+    /// the same operation are consumed in order.
+    pub fn fail_next(&self, operation: FakeOperation, message: impl Into<String>) {
+        self.with_inner(|inner| {
+            inner
+                .failures
+                .entry(operation)
+                .or_default()
+                .push_back(message.into())
+        });
+    }
+
+    pub fn history(&self) -> Vec<(FakeOperation, Vec<FakeHash>)> {
+        self.with_inner(|inner| inner.history.clone())
+    }
+
+    pub fn task(&self, hash: &str) -> Option<FakeTask> {
+        self.with_inner(|inner| inner.tasks.get(hash).cloned())
+    }
+
+    pub fn tasks(&self) -> Vec<FakeTask> {
+        self.with_inner(|inner| inner.tasks.values().cloned().collect())
+    }
+
+    fn update_task(&self, hash: &str, f: impl FnOnce(&mut FakeTask)) -> bool {
+        self.with_inner(|inner| inner.tasks.get_mut(hash).map(f).is_some())
+    }
+
+    /// Sets the downloaded bytes of a task, reaching the total size completes
+    /// it.
+    pub fn set_progress(&self, hash: &str, dl_bytes: u64) -> bool {
+        self.update_task(hash, |task| {
+            task.dl_bytes = dl_bytes.min(task.total_bytes);
+            if task.dl_bytes == task.total_bytes {
+                task.state = FakeState(DownloadSimpleState::Completed);
+                task.speed = 0;
+            }
+        })
+    }
+
+    /// Moves every active task forward by `bytes`.
+    pub fn advance(&self, bytes: u64) {
+        self.with_inner(|inner| {
+            for task in inner
+                .tasks
+                .values_mut()
+                .filter(|t| t.state.0 == DownloadSimpleState::Active)
+            {
+                task.dl_bytes = (task.dl_bytes + bytes).min(task.total_bytes);
+                task.speed = bytes;
+                if task.dl_bytes == task.total_bytes {
+                    task.state = FakeState(DownloadSimpleState::Completed);
+                    task.speed = 0;
+                }
+            }
+        });
+    }
+
+    pub fn complete(&self, hash: &str) -> bool {
+        self.update_task(hash, |task| {
+            task.dl_bytes = task.total_bytes;
+            task.speed = 0;
+            task.state = FakeState(DownloadSimpleState::Completed);
+        })
+    }
+
+    /// Sets the upload ratio and seeding time reported for a task.
+    pub fn set_seeding(&self, hash: &str, ratio: f64, seeding_time: Duration) -> bool {
+        self.update_task(hash, |task| {
+            task.ratio = ratio;
+            task.seeding_time = Some(seeding_time);
+        })
+    }
+
+    pub fn set_error(&self, hash: &str) -> bool {
+        self.update_task(hash, |task| {
+            task.speed = 0;
+            task.state = FakeState(DownloadSimpleState::Error);
+        })
+    }
+
+    fn set_state_where(
+        &self,
+        hashes: &[FakeHash],
+        from: &[DownloadSimpleState],
+        to: DownloadSimpleState,
+    ) {
+        self.with_inner(|inner| {
+            for hash in hashes {
+                if let Some(task) = inner.tasks.get_mut(hash)
+                    && from.contains(&task.state.0)
+                {
+                    task.state = FakeState(to);
+                    task.speed = 0;
+                }
+            }
+        });
+    }
+
+    fn source_name(source: &HashTorrentSource) -> Option<String> {
+        match source {
+            HashTorrentSource::TorrentFile(TorrentFileSource { filename, .. }) => Some(
+                filename
+                    .strip_suffix(".torrent")
+                    .unwrap_or(filename)
+                    .to_string(),
+            ),
+            HashTorrentSource::MagnetUrl(_) => None,
+        }
+    }
+}
+
+#[async_trait]
+impl DownloaderTrait for FakeDownloader {
+    type State = FakeState;
+    type Id = FakeHash;
+    type Task = FakeTask;
+    type Creation = FakeCreation;
+    type Selector = FakeSelector;
+
+    async fn add_downloads(
+        &self,
+        creation: <Self as DownloaderTrait>::Creation,
+    ) -> Result<Vec<<Self as DownloaderTrait>::Id>, DownloaderError> {
+        let hashes = creation
+            .sources
+            .iter()
+            .map(|s| s.hash_info().to_lowercase())
+            .collect_vec();
+
+        self.record(FakeOperation::Add, &hashes)?;
+
+        let tags = {
+            let mut tags = vec![TORRENT_TAG_NAME.to_string()];
+            tags.extend(creation.tags);
+            tags.into_iter()
+                .filter(|s| !s.is_empty())
+                .unique()
+                .collect_vec()
+        };
+
+        self.with_inner(|inner| {
+            for (hash, source) in hashes.iter().zip(creation.sources.iter()) {
+                inner.tasks.entry(hash.clone()).or_insert_with(|| FakeTask {
+                    hash_info: hash.clone(),
+                    name: Self::source_name(source),
+                    state: FakeState(DownloadSimpleState::Active),
+                    save_path: creation.save_path.clone(),
+                    tags: tags.clone(),
+                    category: creation.category.clone(),
+                    dl_bytes: 0,
+                    total_bytes: self.total_bytes,
+                    speed: 0,
+                    ratio: 0.0,
+                    seeding_time: None,
+                    renamed_files: vec![],
+                });
+            }
+        });
+
+        Ok(hashes)
+    }
+
+    async fn pause_downloads(
+        &self,
+        selector: <Self as DownloaderTrait>::Selector,
+    ) -> Result<impl IntoIterator<Item = Self::Id>, DownloaderError> {
+        <Self as TorrentDownloaderTrait>::pause_downloads(self, selector).await
+    }
+
+    async fn resume_downloads(
+        &self,
+        selector: <Self as DownloaderTrait>::Selector,
+    ) -> Result<impl IntoIterator<Item = Self::Id>, DownloaderError> {
+        <Self as TorrentDownloaderTrait>::resume_downloads(self, selector).await
+    }
+
+    async fn remove_downloads(
+        &self,
+        selector: <Self as DownloaderTrait>::Selector,
+    ) -> Result<impl IntoIterator<Item = Self::Id>, DownloaderError> {
+        <Self as TorrentDownloaderTrait>::remove_downloads(self, selector).await
+    }
+
+    async fn query_downloads(
+        &self,
+        selector: FakeSelector,
+    ) -> Result<Vec<<Self as DownloaderTrait>::Task>, DownloaderError> {
+        self.record(FakeOperation::Query, &selector)?;
+
+        Ok(self.with_inner(|inner| {
+            selector
+                .iter()
+                .filter_map(|h| inner.tasks.get(h).cloned())
+                .collect()
+        }))
+    }
+}
+
+#[async_trait]
+impl TorrentDownloaderTrait for FakeDownloader {
+    type IdSelector = FakeHashSelector;
+
+    async fn pause_torrents(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        self.record(FakeOperation::Pause, &hashes)?;
+        self.set_state_where(
+            &hashes,
+            &[DownloadSimpleState::Active, DownloadSimpleState::Completed],
+            DownloadSimpleState::Paused,
+        );
+        Ok(hashes)
+    }
+
+    async fn resume_torrents(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        self.record(FakeOperation::Resume, &hashes)?;
+        self.set_state_where(
+            &hashes,
+            &[DownloadSimpleState::Paused, DownloadSimpleState::Error],
+            DownloadSimpleState::Active,
+        );
+        Ok(hashes)
+    }
+
+    async fn remove_torrents(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        self.record(FakeOperation::Remove, &hashes)?;
+        self.with_inner(|inner| {
+            for hash in hashes.iter() {
+                inner.tasks.remove(hash);
+            }
+        });
+        Ok(hashes)
+    }
+
+    async fn move_torrents(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+        save_path: &str,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        self.record(FakeOperation::Move, &hashes)?;
+        self.with_inner(|inner| {
+            for hash in hashes.iter() {
+                if let Some(task) = inner.tasks.get_mut(hash) {
+                    task.save_path = save_path.into();
+                }
+            }
+        });
+        Ok(hashes)
+    }
+
+    async fn rename_torrent_file(
+        &self,
+        hash: <Self as DownloaderTrait>::Id,
+        old_path: &str,
+        new_path: &str,
+    ) -> Result<(), DownloaderError> {
+        self.record(FakeOperation::Rename, std::slice::from_ref(&hash))?;
+        let renamed = self.update_task(&hash, |task| {
+            task.renamed_files
+                .push((old_path.to_string(), new_path.to_string()));
+        });
+        if !renamed {
+            whatever!("can not rename file of missing torrent {hash}");
+        }
+        Ok(())
+    }
+
+    async fn add_torrent_tags(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+        tags: Vec<String>,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        self.record(FakeOperation::Label, &hashes)?;
+        for hash in hashes.iter() {
+            self.update_task(hash, |task| {
+                task.tags = task
+                    .tags
+                    .iter()
+                    .chain(tags.iter())
+                    .unique()
+                    .cloned()
+                    .collect();
+            });
+        }
+        Ok(hashes)
+    }
+
+    async fn set_torrents_category(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+        category: &str,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        self.record(FakeOperation::Label, &hashes)?;
+        for hash in hashes.iter() {
+            self.update_task(hash, |task| {
+                task.category = Some(category.to_string());
+            });
+        }
+        Ok(hashes)
+    }
+}
+
+#[async_trait]
+impl TorrentDownloaderHandleTrait for FakeDownloader {
+    async fn add_sources(
+        &self,
+        save_path: PathBuf,
+        sources: Vec<HashTorrentSource>,
+    ) -> Result<Vec<String>, DownloaderError> {
+        DownloaderTrait::add_downloads(
+            self,
+            FakeCreation {
+                save_path,
+                sources,
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    async fn query_by_hashes(
+        &self,
+        hashes: Vec<String>,
+    ) -> Result<Vec<TorrentDownloadInfo>, DownloaderError> {
+        let tasks = DownloaderTrait::query_downloads(self, hashes.into()).await?;
+
+        Ok(tasks
+            .into_iter()
+            .map(|task| TorrentDownloadInfo {
+                name: DownloadTaskTrait::name(&task).to_string(),
+                state: task.state().to_download_state(),
+                dl_bytes: task.dl_bytes(),
+                total_bytes: task.total_bytes(),
+                save_path: Some(task.save_path.as_str().to_string()),
+                hash_info: task.hash_info().to_string(),
+                ratio: TorrentTaskTrait::ratio(&task),
+                seeding_time: TorrentTaskTrait::seeding_time(&task),
+            })
+            .collect())
+    }
+
+    async fn pause_by_hashes(&self, hashes: Vec<String>) -> Result<(), DownloaderError> {
+        TorrentDownloaderTrait::pause_torrents(self, hashes.into()).await?;
+        Ok(())
+    }
+
+    async fn resume_by_hashes(&self, hashes: Vec<String>) -> Result<(), DownloaderError> {
+        TorrentDownloaderTrait::resume_torrents(self, hashes.into()).await?;
+        Ok(())
+    }
+
+    async fn remove_by_hashes(&self, hashes: Vec<String>) -> Result<(), DownloaderError> {
+        TorrentDownloaderTrait::remove_torrents(self, hashes.into()).await?;
+        Ok(())
+    }
+
+    async fn remove_by_hashes_keep_files(
+        &self,
+        hashes: Vec<String>,
+    ) -> Result<(), DownloaderError> {
+        TorrentDownloaderTrait::remove_torrents(self, hashes.into()).await?;
+        Ok(())
+    }
+}
+
+impl Debug for FakeDownloader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FakeDownloader")
+            .field("save_path", &self.save_path)
+            .finish()
+    }
+}
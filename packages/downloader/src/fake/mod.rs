@@ -0,0 +1,8 @@
+pub mod downloader;
+pub mod task;
+
+#[cfg(test)]
+mod test;
+
+pub use downloader::{FakeDownloader, FakeDownloaderCreation, FakeOperation};
+pub use task::{FakeCreation, FakeHash, FakeHashSelector, FakeSelector, FakeState, FakeTask};
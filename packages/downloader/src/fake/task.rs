@@ -0,0 +1,136 @@
+use std::{borrow::Cow, time::Duration};
+
+use quirks_path::{Path, PathBuf};
+
+use crate::{
+    bittorrent::{
+        source::HashTorrentSource,
+        task::{SimpleTorrentHash, TorrentCreationTrait, TorrentStateTrait, TorrentTaskTrait},
+    },
+    core::{
+        DownloadCreationTrait, DownloadIdSelector, DownloadSimpleState, DownloadStateTrait,
+        DownloadTaskTrait,
+    },
+};
+
+pub type FakeHash = SimpleTorrentHash;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FakeState(pub DownloadSimpleState);
+
+impl DownloadStateTrait for FakeState {
+    fn to_download_state(&self) -> DownloadSimpleState {
+        self.0
+    }
+}
+
+impl TorrentStateTrait for FakeState {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FakeTask {
+    pub hash_info: FakeHash,
+    pub name: Option<String>,
+    pub state: FakeState,
+    pub save_path: PathBuf,
+    pub tags: Vec<String>,
+    pub category: Option<String>,
+    pub dl_bytes: u64,
+    pub total_bytes: u64,
+    pub speed: u64,
+    pub ratio: f64,
+    pub seeding_time: Option<Duration>,
+    /// Files renamed inside the torrent as `(old_path, new_path)`.
+    pub renamed_files: Vec<(String, String)>,
+}
+
+impl DownloadTaskTrait for FakeTask {
+    type State = FakeState;
+    type Id = FakeHash;
+
+    fn id(&self) -> &Self::Id {
+        &self.hash_info
+    }
+
+    fn into_id(self) -> Self::Id {
+        self.hash_info
+    }
+
+    fn name(&self) -> Cow<'_, str> {
+        self.name
+            .as_deref()
+            .map(Cow::Borrowed)
+            .unwrap_or_else(|| TorrentTaskTrait::name(self))
+    }
+
+    fn speed(&self) -> Option<u64> {
+        Some(self.speed)
+    }
+
+    fn state(&self) -> &Self::State {
+        &self.state
+    }
+
+    fn dl_bytes(&self) -> Option<u64> {
+        Some(self.dl_bytes)
+    }
+
+    fn total_bytes(&self) -> Option<u64> {
+        Some(self.total_bytes)
+    }
+
+    fn et(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl TorrentTaskTrait for FakeTask {
+    fn hash_info(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.hash_info)
+    }
+
+    fn tags(&self) -> impl Iterator<Item = Cow<'_, str>> {
+        self.tags.iter().map(|t| Cow::Borrowed(t.as_str()))
+    }
+
+    fn category(&self) -> Option<Cow<'_, str>> {
+        self.category.as_deref().map(Cow::Borrowed)
+    }
+
+    fn ratio(&self) -> Option<f64> {
+        Some(self.ratio)
+    }
+
+    fn seeding_time(&self) -> Option<Duration> {
+        self.seeding_time
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FakeCreation {
+    pub save_path: PathBuf,
+    pub tags: Vec<String>,
+    pub category: Option<String>,
+    pub sources: Vec<HashTorrentSource>,
+}
+
+impl DownloadCreationTrait for FakeCreation {
+    type Task = FakeTask;
+}
+
+impl TorrentCreationTrait for FakeCreation {
+    fn save_path(&self) -> &Path {
+        self.save_path.as_ref()
+    }
+
+    fn save_path_mut(&mut self) -> &mut PathBuf {
+        &mut self.save_path
+    }
+
+    fn sources_mut(&mut self) -> &mut Vec<HashTorrentSource> {
+        &mut self.sources
+    }
+}
+
+pub type FakeHashSelector = DownloadIdSelector<FakeTask>;
+
+pub type FakeSelector = FakeHashSelector;
@@ -0,0 +1,233 @@
+use std::time::Duration;
+
+use crate::{
+    bittorrent::{
+        downloader::TorrentDownloaderTrait,
+        handle::TorrentDownloaderHandleTrait,
+        seeding::{SeedingLimitAction, SeedingPolicy},
+        source::HashTorrentSource,
+        task::TORRENT_TAG_NAME,
+    },
+    core::{DownloadSimpleState, DownloaderTrait},
+    fake::{FakeCreation, FakeDownloader, FakeDownloaderCreation, FakeOperation},
+};
+
+const TEST_SAVE_PATH: &str = "/downloads/konobangu";
+const TEST_HASH: &str = "47ee2d69e7f19af783ad896541a07b012676f858";
+
+fn test_magnet_url() -> String {
+    format!("magnet:?xt=urn:btih:{TEST_HASH}&dn=test")
+}
+
+#[tokio::test]
+async fn test_fake_downloader_lifecycle() -> anyhow::Result<()> {
+    let downloader = FakeDownloader::from_creation(FakeDownloaderCreation {
+        save_path: TEST_SAVE_PATH.to_string(),
+        total_bytes: Some(1000),
+    });
+
+    let hashes = downloader
+        .add_downloads(FakeCreation {
+            save_path: TEST_SAVE_PATH.into(),
+            category: Some("bangumi".to_string()),
+            sources: vec![HashTorrentSource::from_magnet_url(test_magnet_url())?],
+            ..Default::default()
+        })
+        .await?;
+    assert_eq!(hashes, vec![TEST_HASH.to_string()]);
+
+    let task = downloader.task(TEST_HASH).expect("should have task");
+    assert_eq!(task.tags, vec![TORRENT_TAG_NAME.to_string()]);
+    assert_eq!(task.category.as_deref(), Some("bangumi"));
+
+    downloader.advance(400);
+    let infos = downloader
+        .query_by_hashes(vec![TEST_HASH.to_string()])
+        .await?;
+    assert_eq!(infos.len(), 1);
+    assert_eq!(infos[0].dl_bytes, Some(400));
+    assert_eq!(infos[0].state, DownloadSimpleState::Active);
+
+    downloader
+        .pause_torrents(vec![TEST_HASH.to_string()].into())
+        .await?;
+    downloader.advance(400);
+    let task = downloader.task(TEST_HASH).expect("should have task");
+    assert_eq!(task.state.0, DownloadSimpleState::Paused);
+    assert_eq!(task.dl_bytes, 400);
+
+    downloader
+        .resume_torrents(vec![TEST_HASH.to_string()].into())
+        .await?;
+    downloader.advance(800);
+    let task = downloader.task(TEST_HASH).expect("should have task");
+    assert_eq!(task.state.0, DownloadSimpleState::Completed);
+    assert_eq!(task.dl_bytes, 1000);
+
+    downloader
+        .remove_torrents(vec![TEST_HASH.to_string()].into())
+        .await?;
+    assert!(downloader.task(TEST_HASH).is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fake_downloader_scripted_failures() -> anyhow::Result<()> {
+    let downloader = FakeDownloader::from_creation(FakeDownloaderCreation::default());
+
+    downloader.fail_next(FakeOperation::Add, "tracker unreachable");
+
+    let add = || {
+        downloader.add_sources(
+            TEST_SAVE_PATH.into(),
+            vec![HashTorrentSource::from_magnet_url(test_magnet_url()).unwrap()],
+        )
+    };
+
+    assert!(add().await.is_err());
+    assert!(downloader.task(TEST_HASH).is_none());
+
+    add().await?;
+    assert!(downloader.task(TEST_HASH).is_some());
+
+    assert_eq!(
+        downloader
+            .history()
+            .into_iter()
+            .map(|(operation, _)| operation)
+            .collect::<Vec<_>>(),
+        vec![FakeOperation::Add, FakeOperation::Add]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fake_downloader_enforce_seeding_policy() -> anyhow::Result<()> {
+    let downloader = FakeDownloader::from_creation(FakeDownloaderCreation {
+        save_path: TEST_SAVE_PATH.to_string(),
+        total_bytes: Some(1000),
+    });
+    downloader
+        .add_downloads(FakeCreation {
+            save_path: TEST_SAVE_PATH.into(),
+            sources: vec![HashTorrentSource::from_magnet_url(test_magnet_url())?],
+            ..Default::default()
+        })
+        .await?;
+
+    let policy = SeedingPolicy {
+        max_ratio: Some(2.0),
+        max_seeding_time: None,
+        action: SeedingLimitAction::Pause,
+    };
+
+    downloader.set_seeding(TEST_HASH, 3.0, Duration::from_secs(60));
+    let reached = downloader
+        .enforce_seeding_policy(vec![TEST_HASH.to_string()], &policy)
+        .await?;
+    assert!(reached.is_empty(), "incomplete torrents are never limited");
+
+    downloader.complete(TEST_HASH);
+    downloader.set_seeding(TEST_HASH, 1.0, Duration::from_secs(60));
+    let reached = downloader
+        .enforce_seeding_policy(vec![TEST_HASH.to_string()], &policy)
+        .await?;
+    assert!(reached.is_empty());
+
+    downloader.set_seeding(TEST_HASH, 2.5, Duration::from_secs(120));
+    let reached = downloader
+        .enforce_seeding_policy(vec![TEST_HASH.to_string()], &policy)
+        .await?;
+    assert_eq!(reached.len(), 1);
+    assert_eq!(reached[0].hash_info, TEST_HASH);
+    assert_eq!(reached[0].action, SeedingLimitAction::Pause);
+    assert_eq!(reached[0].ratio, Some(2.5));
+    let task = downloader.task(TEST_HASH).expect("should have task");
+    assert_eq!(task.state.0, DownloadSimpleState::Paused);
+
+    let reached = downloader
+        .enforce_seeding_policy(
+            vec![TEST_HASH.to_string()],
+            &SeedingPolicy {
+                max_seeding_time: Some(Duration::from_secs(60)),
+                action: SeedingLimitAction::RemoveWithFiles,
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert!(reached.is_empty(), "paused torrents are not seeding");
+
+    downloader
+        .resume_torrents(vec![TEST_HASH.to_string()].into())
+        .await?;
+    downloader.complete(TEST_HASH);
+    let reached = downloader
+        .enforce_seeding_policy(
+            vec![TEST_HASH.to_string()],
+            &SeedingPolicy {
+                max_seeding_time: Some(Duration::from_secs(60)),
+                action: SeedingLimitAction::RemoveWithFiles,
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert_eq!(reached.len(), 1);
+    assert!(downloader.task(TEST_HASH).is_none());
+    assert!(
+        downloader
+            .history()
+            .contains(&(FakeOperation::Remove, vec![TEST_HASH.to_string()]))
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fake_downloader_organize_torrents() -> anyhow::Result<()> {
+    let downloader = FakeDownloader::from_creation(FakeDownloaderCreation {
+        save_path: TEST_SAVE_PATH.to_string(),
+        ..Default::default()
+    });
+    downloader
+        .add_downloads(FakeCreation {
+            save_path: TEST_SAVE_PATH.into(),
+            sources: vec![HashTorrentSource::from_magnet_url(test_magnet_url())?],
+            ..Default::default()
+        })
+        .await?;
+
+    let hashes = vec![TEST_HASH.to_string()];
+    downloader
+        .move_torrents(hashes.clone().into(), "/downloads/konobangu/moved")
+        .await?;
+    downloader
+        .rename_torrent_file(TEST_HASH.to_string(), "test.mkv", "S01E01.mkv")
+        .await?;
+    downloader
+        .add_torrent_tags(hashes.clone().into(), vec!["organized".to_string()])
+        .await?;
+    downloader
+        .set_torrents_category(hashes.clone().into(), "bangumi")
+        .await?;
+
+    let task = downloader.task(TEST_HASH).expect("should have task");
+    assert_eq!(task.save_path.as_str(), "/downloads/konobangu/moved");
+    assert_eq!(
+        task.renamed_files,
+        vec![("test.mkv".to_string(), "S01E01.mkv".to_string())]
+    );
+    assert!(task.tags.contains(&"organized".to_string()));
+    assert!(task.tags.contains(&TORRENT_TAG_NAME.to_string()));
+    assert_eq!(task.category.as_deref(), Some("bangumi"));
+
+    assert!(
+        downloader
+            .rename_torrent_file("missing".to_string(), "a.mkv", "b.mkv")
+            .await
+            .is_err()
+    );
+
+    Ok(())
+}
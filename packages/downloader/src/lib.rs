@@ -1,8 +1,13 @@
+pub mod aria2;
 pub mod bittorrent;
 pub mod core;
+pub mod dandanplay;
 pub mod errors;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod fake;
 pub mod qbit;
 pub mod rqbit;
+pub mod transmission;
 pub mod utils;
 
 pub use errors::DownloaderError;
@@ -8,13 +8,14 @@ use std::{
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use futures::{StreamExt, stream::BoxStream};
 use itertools::Itertools;
 use merge_struct::merge;
 use qbit_rs::{
     Qbit,
     model::{
-        AddTorrentArg, Category, Credential, GetTorrentListArg, NonEmptyStr, Sep, SyncData,
-        Torrent as QbitTorrent, TorrentFile, TorrentSource,
+        AddTorrentArg, Category, Credential, GetTorrentListArg, NonEmptyStr, Priority, Sep, State,
+        SyncData, Torrent as QbitTorrent, TorrentFile, TorrentSource,
     },
 };
 use quirks_path::PathBuf;
@@ -31,10 +32,14 @@ use crate::{
     DownloaderError,
     bittorrent::{
         downloader::TorrentDownloaderTrait,
+        handle::{TorrentDownloadInfo, TorrentDownloaderHandleTrait},
+        selection::TorrentFileSelection,
         source::{HashTorrentSource, HashTorrentSourceTrait, MagnetUrlSource, TorrentFileSource},
-        task::TORRENT_TAG_NAME,
+        task::{TORRENT_TAG_NAME, TorrentTaskTrait},
+    },
+    core::{
+        DownloadEvent, DownloadEventDiffer, DownloadStateTrait, DownloadTaskTrait, DownloaderTrait,
     },
-    core::DownloaderTrait,
     qbit::task::{
         QBittorrentCreation, QBittorrentHash, QBittorrentSelector, QBittorrentState,
         QBittorrentTask,
@@ -42,6 +47,9 @@ use crate::{
     utils::path_equals_as_file_url,
 };
 
+/// How long a magnet with a file selection may take to resolve its metadata.
+pub const QBITTORRENT_METADATA_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
 #[derive(Debug)]
 pub struct QBittorrentDownloaderCreation {
     pub endpoint: String,
@@ -129,6 +137,8 @@ pub struct QBittorrentDownloader {
     pub wait_sync_timeout: Duration,
     pub sync_watch: watch::Sender<DateTime<Utc>>,
     pub sync_data: Arc<RwLock<QBittorrentSyncData>>,
+    pub file_selection_failures: Arc<RwLock<HashSet<String>>>,
+    me: Weak<Self>,
 }
 
 impl QBittorrentDownloader {
@@ -146,7 +156,8 @@ impl QBittorrentDownloader {
 
         client.sync(None).await?;
 
-        let downloader = Arc::new(Self {
+        let downloader = Arc::new_cyclic(|me| Self {
+            me: me.clone(),
             client: Arc::new(client),
             endpoint_url,
             subscriber_id: creation.subscriber_id,
@@ -157,6 +168,7 @@ impl QBittorrentDownloader {
             downloader_id: creation.downloader_id,
             sync_watch: watch::channel(Utc::now()).0,
             sync_data: Arc::new(RwLock::new(QBittorrentSyncData::default())),
+            file_selection_failures: Arc::new(RwLock::new(HashSet::new())),
         });
 
         let event_loop_me = Arc::downgrade(&downloader);
@@ -188,6 +200,8 @@ impl QBittorrentDownloader {
                 } else {
                     tick += 1;
                 }
+            } else {
+                break;
             }
         }
     }
@@ -223,122 +237,142 @@ impl QBittorrentDownloader {
     }
 
     #[instrument(level = "debug", skip(self))]
-    pub async fn set_torrents_category(
+    pub async fn delete_torrents(
         &self,
-        hashes: Vec<String>,
-        category: &str,
-    ) -> Result<(), DownloaderError> {
+        hashes: QBittorrentHashSelector,
+        delete_files: bool,
+    ) -> Result<QBittorrentHashSelector, DownloaderError> {
+        self.client
+            .delete_torrents(hashes.clone(), Some(delete_files))
+            .await?;
         {
-            let category_no_exists = {
-                let sync_data = self.sync_data.read().await;
-                !sync_data.categories.contains_key(category)
-            };
-
-            if category_no_exists {
-                self.add_category(category).await?;
+            let mut failures = self.file_selection_failures.write().await;
+            for hash in hashes.iter() {
+                failures.remove(hash as &str);
             }
         }
-        self.client
-            .set_torrent_category(hashes.clone(), category)
-            .await?;
         self.wait_sync_until(
-            |sync_data| {
+            |sync_data| -> bool {
                 let torrents = &sync_data.torrents;
-                hashes.iter().all(|h| {
-                    torrents
-                        .get(h)
-                        .is_some_and(|t| t.category.as_deref().is_some_and(|c| c == category))
-                })
+                hashes.iter().all(|h| !torrents.contains_key(h))
             },
             None,
         )
         .await?;
-        Ok(())
+        Ok(hashes)
     }
 
     #[instrument(level = "debug", skip(self))]
-    pub async fn add_torrent_tags(
+    pub async fn get_torrent_path(
         &self,
-        hashes: Vec<String>,
-        tags: Vec<String>,
-    ) -> Result<(), DownloaderError> {
-        if tags.is_empty() {
-            whatever!("add bittorrent tags can not be empty");
-        }
-        self.client
-            .add_torrent_tags(hashes.clone(), tags.clone())
+        hashes: String,
+    ) -> Result<Option<String>, DownloaderError> {
+        let mut torrent_list = self
+            .client
+            .get_torrent_list(GetTorrentListArg {
+                hashes: Some(hashes),
+                ..Default::default()
+            })
             .await?;
-        let tag_sets = tags.iter().map(|s| s.as_str()).collect::<HashSet<&str>>();
-        self.wait_sync_until(
-            |sync_data| {
-                let torrents = &sync_data.torrents;
-
-                hashes.iter().all(|h| {
-                    torrents.get(h).is_some_and(|t| {
-                        t.tags.as_ref().is_some_and(|t| {
-                            t.split(',')
-                                .map(|s| s.trim())
-                                .filter(|s| !s.is_empty())
-                                .collect::<HashSet<&str>>()
-                                .is_superset(&tag_sets)
-                        })
-                    })
-                })
-            },
-            None,
-        )
-        .await?;
-        Ok(())
+        let torrent = torrent_list
+            .first_mut()
+            .whatever_context::<_, DownloaderError>("No bittorrent found")?;
+        Ok(torrent.save_path.take())
     }
 
+    /// Waits for the metadata of the torrents to be resolved, then marks the
+    /// files not picked by the selection as not to be downloaded.
     #[instrument(level = "debug", skip(self))]
-    pub async fn move_torrents(
+    pub async fn apply_file_selection(
         &self,
-        hashes: Vec<String>,
-        new_path: &str,
+        hashes: &HashSet<String>,
+        selection: &TorrentFileSelection,
+        timeout: Option<Duration>,
     ) -> Result<(), DownloaderError> {
-        self.client
-            .set_torrent_location(hashes.clone(), new_path)
-            .await?;
+        let matcher = selection.matcher()?;
 
         self.wait_sync_until(
-            |sync_data| -> bool {
+            |sync_data| {
                 let torrents = &sync_data.torrents;
-
-                hashes.iter().all(|h| {
-                    torrents.get(h).is_some_and(|t| {
-                        t.save_path.as_deref().is_some_and(|p| {
-                            path_equals_as_file_url(p, new_path)
-                            .inspect_err(|error| {
-                                tracing::warn!(name = "path_equals_as_file_url", error = ?error);
-                            })
-                            .unwrap_or(false)
-                        })
+                hashes.iter().all(|hash| {
+                    torrents.get(hash).is_some_and(|t| {
+                        t.state.as_ref().is_some_and(|s| *s != State::MetaDL)
+                            && t.size.is_some_and(|s| s > 0)
                     })
                 })
             },
-            None,
+            timeout,
         )
         .await?;
+
+        for hash in hashes {
+            let contents = self.client.get_torrent_contents(hash as &str, None).await?;
+            let (selected, unwanted): (Vec<_>, Vec<_>) = contents
+                .iter()
+                .partition(|c| matcher.is_selected(&c.name, c.size));
+            if selected.is_empty() {
+                whatever!("file selection excludes every file of torrent {hash}");
+            }
+            if !unwanted.is_empty() {
+                self.client
+                    .set_file_priority(
+                        hash as &str,
+                        unwanted.iter().map(|c| c.index as i64).collect_vec(),
+                        Priority::DoNotDownload,
+                    )
+                    .await?;
+            }
+        }
+
         Ok(())
     }
 
-    #[instrument(level = "debug", skip(self))]
-    pub async fn get_torrent_path(
+    /// Metadata of magnets may take long to resolve, so the selection is
+    /// applied in the background and a failure leaves the torrents paused
+    /// instead of failing the add. Failed torrents are reported as errored by
+    /// queries and download events until they are removed.
+    async fn apply_file_selection_and_resume(
         &self,
-        hashes: String,
-    ) -> Result<Option<String>, DownloaderError> {
-        let mut torrent_list = self
-            .client
-            .get_torrent_list(GetTorrentListArg {
-                hashes: Some(hashes),
-                ..Default::default()
-            })
-            .await?;
-        let torrent = torrent_list
-            .first_mut()
-            .whatever_context::<_, DownloaderError>("No bittorrent found")?;
-        Ok(torrent.save_path.take())
+        hashes: HashSet<String>,
+        selection: TorrentFileSelection,
+    ) {
+        if let Err(err) = self
+            .apply_file_selection(&hashes, &selection, Some(QBITTORRENT_METADATA_TIMEOUT))
+            .await
+        {
+            tracing::error!(
+                err = ?err,
+                hashes = ?hashes,
+                "Failed to apply file selection, torrents are left paused"
+            );
+            self.file_selection_failures
+                .write()
+                .await
+                .extend(hashes.iter().cloned());
+            self.sync_watch.send_replace(Utc::now());
+            if let Err(err) = TorrentDownloaderTrait::pause_torrents(
+                self,
+                hashes.iter().cloned().collect_vec().into(),
+            )
+            .await
+            {
+                tracing::error!(err = ?err, "Failed to pause torrents");
+            }
+            return;
+        }
+
+        if let Err(err) = TorrentDownloaderTrait::resume_torrents(
+            self,
+            hashes.iter().cloned().collect_vec().into(),
+        )
+        .await
+        {
+            tracing::error!(
+                err = ?err,
+                hashes = ?hashes,
+                "Failed to resume torrents after applying file selection"
+            );
+        }
     }
 
     #[instrument(level = "debug", skip(self))]
@@ -454,6 +488,7 @@ impl DownloaderTrait for QBittorrentDownloader {
         };
 
         let category = creation.category;
+        let file_selection = creation.file_selection.filter(|s| !s.is_empty());
 
         if let Some(category) = category.as_deref() {
             let has_caetgory = {
@@ -468,6 +503,9 @@ impl DownloaderTrait for QBittorrentDownloader {
             }
         }
 
+        // torrents with a file selection are only resumed once unwanted files
+        // are excluded, magnets are stopped as soon as their metadata is
+        // received as qBittorrent does not fetch the metadata of paused torrents
         if let Some(source) = urls_source {
             self.client
                 .add_torrent(AddTorrentArg {
@@ -476,6 +514,9 @@ impl DownloaderTrait for QBittorrentDownloader {
                     auto_torrent_management: Some(false),
                     category: category.clone(),
                     tags: tags.clone(),
+                    stop_condition: file_selection
+                        .is_some()
+                        .then(|| "MetadataReceived".to_string()),
                     ..Default::default()
                 })
                 .await?;
@@ -489,6 +530,7 @@ impl DownloaderTrait for QBittorrentDownloader {
                     auto_torrent_management: Some(false),
                     category,
                     tags,
+                    paused: file_selection.is_some().then(|| "true".to_string()),
                     ..Default::default()
                 })
                 .await?;
@@ -501,6 +543,15 @@ impl DownloaderTrait for QBittorrentDownloader {
             None,
         )
         .await?;
+        if let Some(file_selection) = file_selection
+            && let Some(me) = self.me.upgrade()
+        {
+            let hashes = hashes.clone();
+            tokio::spawn(async move {
+                me.apply_file_selection_and_resume(hashes, file_selection)
+                    .await
+            });
+        }
         Ok(hashes)
     }
 
@@ -546,13 +597,60 @@ impl DownloaderTrait for QBittorrentDownloader {
         }))
         .await?;
 
-        let tasks = torrent_list
+        let mut tasks = torrent_list
             .into_iter()
             .zip(torrent_contents)
             .map(|(t, c)| Self::Task::from_query(t, c))
             .collect::<Result<Vec<Self::Task>, _>>()?;
+        {
+            let failures = self.file_selection_failures.read().await;
+            for task in tasks.iter_mut() {
+                if failures.contains(&task.hash_info) {
+                    task.mark_file_selection_failed();
+                }
+            }
+        }
         Ok(tasks)
     }
+
+    /// Driven by the sync event loop instead of polling, every patch of
+    /// [`QBittorrentSyncData`] is diffed against the previous one.
+    fn watch_downloads(
+        &self,
+        ids: Vec<QBittorrentHash>,
+    ) -> BoxStream<'_, Result<DownloadEvent<QBittorrentHash>, DownloaderError>> {
+        let receiver = self.sync_watch.subscribe();
+
+        let snapshots = futures::stream::unfold((receiver, true), move |(mut receiver, first)| {
+            let ids = ids.clone();
+            async move {
+                if !first && receiver.changed().await.is_err() {
+                    return None;
+                }
+                let tasks = {
+                    let sync_data = self.sync_data.read().await;
+                    let failures = self.file_selection_failures.read().await;
+                    ids.iter()
+                        .filter_map(|id| {
+                            let mut torrent = sync_data.torrents.get(id)?.clone();
+                            torrent.hash = Some(id.clone());
+                            Some(
+                                QBittorrentTask::from_query(torrent, vec![]).map(|mut task| {
+                                    if failures.contains(id) {
+                                        task.mark_file_selection_failed();
+                                    }
+                                    task
+                                }),
+                            )
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                };
+                Some((tasks, (receiver, false)))
+            }
+        });
+
+        DownloadEventDiffer::default().stream(snapshots).boxed()
+    }
 }
 
 #[async_trait]
@@ -581,20 +679,192 @@ impl TorrentDownloaderTrait for QBittorrentDownloader {
     async fn remove_torrents(
         &self,
         hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        self.delete_torrents(hashes, true).await
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn move_torrents(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+        save_path: &str,
     ) -> Result<Self::IdSelector, DownloaderError> {
         self.client
-            .delete_torrents(hashes.clone(), Some(true))
+            .set_torrent_location(hashes.clone(), save_path)
             .await?;
+
         self.wait_sync_until(
             |sync_data| -> bool {
                 let torrents = &sync_data.torrents;
-                hashes.iter().all(|h| !torrents.contains_key(h))
+
+                hashes.iter().all(|h| {
+                    torrents.get(h).is_some_and(|t| {
+                        t.save_path.as_deref().is_some_and(|p| {
+                            path_equals_as_file_url(p, save_path)
+                            .inspect_err(|error| {
+                                tracing::warn!(name = "path_equals_as_file_url", error = ?error);
+                            })
+                            .unwrap_or(false)
+                        })
+                    })
+                })
             },
             None,
         )
         .await?;
         Ok(hashes)
     }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn rename_torrent_file(
+        &self,
+        hash: <Self as DownloaderTrait>::Id,
+        old_path: &str,
+        new_path: &str,
+    ) -> Result<(), DownloaderError> {
+        self.client.rename_file(&hash, old_path, new_path).await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn add_torrent_tags(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+        tags: Vec<String>,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        if tags.is_empty() {
+            whatever!("add bittorrent tags can not be empty");
+        }
+        self.client
+            .add_torrent_tags(hashes.clone(), tags.clone())
+            .await?;
+        let tag_sets = tags.iter().map(|s| s.as_str()).collect::<HashSet<&str>>();
+        self.wait_sync_until(
+            |sync_data| {
+                let torrents = &sync_data.torrents;
+
+                hashes.iter().all(|h| {
+                    torrents.get(h).is_some_and(|t| {
+                        t.tags.as_ref().is_some_and(|t| {
+                            t.split(',')
+                                .map(|s| s.trim())
+                                .filter(|s| !s.is_empty())
+                                .collect::<HashSet<&str>>()
+                                .is_superset(&tag_sets)
+                        })
+                    })
+                })
+            },
+            None,
+        )
+        .await?;
+        Ok(hashes)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn set_torrents_category(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+        category: &str,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        {
+            let category_no_exists = {
+                let sync_data = self.sync_data.read().await;
+                !sync_data.categories.contains_key(category)
+            };
+
+            if category_no_exists {
+                self.add_category(category).await?;
+            }
+        }
+        self.client
+            .set_torrent_category(hashes.clone(), category)
+            .await?;
+        self.wait_sync_until(
+            |sync_data| {
+                let torrents = &sync_data.torrents;
+                hashes.iter().all(|h| {
+                    torrents
+                        .get(h)
+                        .is_some_and(|t| t.category.as_deref().is_some_and(|c| c == category))
+                })
+            },
+            None,
+        )
+        .await?;
+        Ok(hashes)
+    }
+}
+
+#[async_trait]
+impl TorrentDownloaderHandleTrait for QBittorrentDownloader {
+    async fn add_sources(
+        &self,
+        save_path: PathBuf,
+        sources: Vec<HashTorrentSource>,
+    ) -> Result<Vec<String>, DownloaderError> {
+        let hashes = DownloaderTrait::add_downloads(
+            self,
+            QBittorrentCreation {
+                save_path,
+                sources,
+                ..Default::default()
+            },
+        )
+        .await?;
+        Ok(hashes.into_iter().collect())
+    }
+
+    async fn query_by_hashes(
+        &self,
+        hashes: Vec<String>,
+    ) -> Result<Vec<TorrentDownloadInfo>, DownloaderError> {
+        if hashes.is_empty() {
+            return Ok(vec![]);
+        }
+        let tasks = DownloaderTrait::query_downloads(
+            self,
+            QBittorrentSelector::Hash(QBittorrentHashSelector::from(hashes)),
+        )
+        .await?;
+
+        Ok(tasks
+            .into_iter()
+            .map(|task| TorrentDownloadInfo {
+                name: DownloadTaskTrait::name(&task).to_string(),
+                state: task.state().to_download_state(),
+                dl_bytes: task.dl_bytes(),
+                total_bytes: task.total_bytes(),
+                save_path: task.torrent.save_path.clone(),
+                hash_info: task.hash_info().to_string(),
+                ratio: TorrentTaskTrait::ratio(&task),
+                seeding_time: TorrentTaskTrait::seeding_time(&task),
+            })
+            .collect())
+    }
+
+    async fn pause_by_hashes(&self, hashes: Vec<String>) -> Result<(), DownloaderError> {
+        TorrentDownloaderTrait::pause_torrents(self, hashes.into()).await?;
+        Ok(())
+    }
+
+    async fn resume_by_hashes(&self, hashes: Vec<String>) -> Result<(), DownloaderError> {
+        TorrentDownloaderTrait::resume_torrents(self, hashes.into()).await?;
+        Ok(())
+    }
+
+    async fn remove_by_hashes(&self, hashes: Vec<String>) -> Result<(), DownloaderError> {
+        TorrentDownloaderTrait::remove_torrents(self, hashes.into()).await?;
+        Ok(())
+    }
+
+    async fn remove_by_hashes_keep_files(
+        &self,
+        hashes: Vec<String>,
+    ) -> Result<(), DownloaderError> {
+        self.delete_torrents(hashes.into(), false).await?;
+        Ok(())
+    }
 }
 
 impl Debug for QBittorrentDownloader {
@@ -9,6 +9,7 @@ use quirks_path::{Path, PathBuf};
 use crate::{
     DownloaderError,
     bittorrent::{
+        selection::TorrentFileSelection,
         source::HashTorrentSource,
         task::{SimpleTorrentHash, TorrentCreationTrait, TorrentStateTrait, TorrentTaskTrait},
     },
@@ -94,6 +95,12 @@ impl QBittorrentTask {
             torrent,
         })
     }
+
+    /// Reports the torrent as errored, its file selection could not be applied
+    /// so it is left paused with unwanted files.
+    pub fn mark_file_selection_failed(&mut self) {
+        self.state = State::Error.into();
+    }
 }
 
 impl DownloadTaskTrait for QBittorrentTask {
@@ -113,7 +120,7 @@ impl DownloadTaskTrait for QBittorrentTask {
             .name
             .as_deref()
             .map(Cow::Borrowed)
-            .unwrap_or_else(|| DownloadTaskTrait::name(self))
+            .unwrap_or_else(|| TorrentTaskTrait::name(self))
     }
 
     fn speed(&self) -> Option<u64> {
@@ -174,6 +181,17 @@ impl TorrentTaskTrait for QBittorrentTask {
     fn category(&self) -> Option<Cow<'_, str>> {
         self.torrent.category.as_deref().map(Cow::Borrowed)
     }
+
+    fn ratio(&self) -> Option<f64> {
+        self.torrent.ratio
+    }
+
+    fn seeding_time(&self) -> Option<Duration> {
+        self.torrent
+            .seeding_time
+            .and_then(|v| u64::try_from(v).ok())
+            .map(Duration::from_secs)
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -182,6 +200,7 @@ pub struct QBittorrentCreation {
     pub tags: Vec<String>,
     pub category: Option<String>,
     pub sources: Vec<HashTorrentSource>,
+    pub file_selection: Option<TorrentFileSelection>,
 }
 
 impl DownloadCreationTrait for QBittorrentCreation {
@@ -200,6 +219,10 @@ impl TorrentCreationTrait for QBittorrentCreation {
     fn sources_mut(&mut self) -> &mut Vec<HashTorrentSource> {
         &mut self.sources
     }
+
+    fn file_selection(&self) -> Option<&TorrentFileSelection> {
+        self.file_selection.as_ref()
+    }
 }
 
 pub type QBittorrentHashSelector = DownloadIdSelector<QBittorrentTask>;
@@ -10,7 +10,10 @@ use crate::{
     bittorrent::{
         downloader::TorrentDownloaderTrait, source::HashTorrentSource, task::TorrentTaskTrait,
     },
-    core::{DownloadIdSelectorTrait, DownloaderTrait},
+    core::{
+        DownloadIdSelectorTrait, DownloadSimpleState, DownloadStateTrait, DownloadTaskTrait,
+        DownloaderTrait,
+    },
     qbit::{
         QBittorrentDownloader, QBittorrentDownloaderCreation,
         task::{
@@ -58,6 +61,24 @@ pub async fn create_qbit_testcontainers()
     Ok(container)
 }
 
+#[test]
+fn test_qbittorrent_task_reports_failed_file_selection_as_error() -> anyhow::Result<()> {
+    let torrent = serde_json::from_value(serde_json::json!({
+        "hash": "47ee2d69e7f19af783ad896541a07b012676f858",
+        "state": "pausedDL",
+    }))?;
+    let mut task = QBittorrentTask::from_query(torrent, vec![])?;
+    assert_eq!(
+        task.state().to_download_state(),
+        DownloadSimpleState::Paused
+    );
+
+    task.mark_file_selection_failed();
+    assert_eq!(task.state().to_download_state(), DownloadSimpleState::Error);
+
+    Ok(())
+}
+
 #[cfg(not(feature = "testcontainers"))]
 #[tokio::test]
 async fn test_qbittorrent_downloader() {
@@ -70,7 +91,7 @@ async fn test_qbittorrent_downloader() {
 #[tokio::test(flavor = "multi_thread")]
 async fn test_qbittorrent_downloader() -> anyhow::Result<()> {
     use testcontainers::runners::AsyncRunner;
-    use testing_torrents::{TestTorrentRequest, TestTorrentResponse, TestingTorrentFileItem};
+    use testing_torrents::{TestTorrentRequest, TestingTorrentFileItem, TestingTorrentSeeder};
     use tokio::io::AsyncReadExt;
 
     tracing_subscriber::fmt()
@@ -78,8 +99,7 @@ async fn test_qbittorrent_downloader() -> anyhow::Result<()> {
         .with_test_writer()
         .init();
 
-    let torrents_image = testing_torrents::create_testcontainers().await?;
-    let _torrents_container = torrents_image.start().await?;
+    let seeder = TestingTorrentSeeder::start().await?;
 
     let torrents_req = TestTorrentRequest {
         id: "f10ebdda-dd2e-43f8-b80c-bf0884d071c4".into(),
@@ -91,13 +111,7 @@ async fn test_qbittorrent_downloader() -> anyhow::Result<()> {
         }],
     };
 
-    let torrent_res: TestTorrentResponse = reqwest::Client::new()
-        .post("http://127.0.0.1:6080/api/torrents/mock")
-        .json(&torrents_req)
-        .send()
-        .await?
-        .json()
-        .await?;
+    let torrent_res = seeder.mock_torrent(torrents_req).await?;
 
     let qbit_image = create_qbit_testcontainers().await?;
     let qbit_container = qbit_image.start().await?;
@@ -143,6 +157,8 @@ async fn test_qbittorrent_downloader() -> anyhow::Result<()> {
     )
     .await?;
 
+    seeder.shutdown().await?;
+
     Ok(())
 }
 
@@ -183,6 +199,7 @@ async fn test_qbittorrent_downloader_impl(
         tags: vec![],
         sources: vec![torrent_source],
         category: None,
+        file_selection: None,
     };
 
     downloader.add_downloads(torrent_creation).await?;
@@ -216,7 +233,7 @@ async fn test_qbittorrent_downloader_impl(
     let test_tag = "test_tag".to_string();
 
     downloader
-        .add_torrent_tags(vec![torrent_hash.clone()], vec![test_tag.clone()])
+        .add_torrent_tags(vec![torrent_hash.clone()].into(), vec![test_tag.clone()])
         .await?;
 
     let target_torrent = get_torrent().await?;
@@ -226,7 +243,7 @@ async fn test_qbittorrent_downloader_impl(
     let test_category = format!("test_category_{}", Utc::now().timestamp());
 
     downloader
-        .set_torrents_category(vec![torrent_hash.clone()], &test_category)
+        .set_torrents_category(vec![torrent_hash.clone()].into(), &test_category)
         .await?;
 
     let target_torrent = get_torrent().await?;
@@ -239,7 +256,10 @@ async fn test_qbittorrent_downloader_impl(
     let moved_torrent_path = base_save_path.join(format!("moved_{}", Utc::now().timestamp()));
 
     downloader
-        .move_torrents(vec![torrent_hash.clone()], moved_torrent_path.as_str())
+        .move_torrents(
+            vec![torrent_hash.clone()].into(),
+            moved_torrent_path.as_str(),
+        )
         .await?;
 
     let target_torrent = get_torrent().await?;
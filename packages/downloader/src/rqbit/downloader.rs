@@ -1,39 +1,70 @@
-use std::{str::FromStr, sync::Arc};
+use std::{fmt::Debug, str::FromStr, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
+use chrono::Utc;
+use futures::{StreamExt, stream::BoxStream};
+use itertools::Itertools;
 use librqbit::{
-    AddTorrent, AddTorrentOptions, ManagedTorrent, Session, SessionOptions, api::TorrentIdOrHash,
+    AddTorrent, AddTorrentOptions, ManagedTorrent, Session, SessionOptions,
+    SessionPersistenceConfig, api::TorrentIdOrHash,
 };
 use librqbit_core::Id20;
-use snafu::ResultExt;
+use quirks_path::PathBuf;
+use snafu::{OptionExt, ResultExt, whatever};
 use tracing::instrument;
 use util::errors::AnyhowResultExt;
 
-use super::task::{RqbitCreation, RqbitHash, RqbitSelector, RqbitState, RqbitTask};
+use super::{
+    store::{
+        RQBIT_LABELS_FILE_NAME, RQBIT_SESSION_FOLDER_NAME, RQBIT_STATE_FOLDER_NAME, RqbitLabelStore,
+    },
+    task::{RqbitCreation, RqbitHash, RqbitHashSelector, RqbitSelector, RqbitState, RqbitTask},
+};
 use crate::{
     DownloaderError,
     bittorrent::{
         downloader::TorrentDownloaderTrait,
+        handle::{TorrentDownloadInfo, TorrentDownloaderHandleTrait},
+        selection::TorrentFileSelection,
         source::{HashTorrentSource, HashTorrentSourceTrait},
+        task::{TORRENT_TAG_NAME, TorrentTaskTrait},
     },
-    core::{DownloadIdSelector, DownloaderTrait},
-    errors::RqbitSnafu,
+    core::{
+        DownloadEvent, DownloadEventDiffer, DownloadIdSelector, DownloadSimpleState,
+        DownloadStateTrait, DownloadTaskTrait, DownloaderTrait, download_event_ticks,
+    },
+    errors::{RqbitSnafu, UnsupportedOperationSnafu},
 };
 
+/// Stats of rqbit torrents are kept live in the embedded session, reading them
+/// is cheap enough to watch downloads far more often than other downloaders.
+pub const RQBIT_EVENT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
 #[derive(Debug)]
 pub struct RqbitDownloaderCreation {
     pub save_path: String,
+    /// Where the session state and fast-resume data are kept, defaults to a
+    /// hidden folder inside the save path.
+    pub data_dir: Option<String>,
     pub subscriber_id: i32,
     pub downloader_id: i32,
 }
 
-impl RqbitDownloaderCreation {}
+impl RqbitDownloaderCreation {
+    pub fn state_dir(&self) -> std::path::PathBuf {
+        self.data_dir
+            .as_ref()
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(|| std::path::Path::new(&self.save_path).join(RQBIT_STATE_FOLDER_NAME))
+    }
+}
 
 pub struct RqbitDownloader {
     pub save_path: String,
     pub subscriber_id: i32,
     pub downloader_id: i32,
     pub session: Arc<Session>,
+    pub labels: RqbitLabelStore,
 }
 
 impl RqbitDownloader {
@@ -41,15 +72,27 @@ impl RqbitDownloader {
     pub async fn from_creation(
         creation: RqbitDownloaderCreation,
     ) -> Result<Arc<Self>, DownloaderError> {
+        let state_dir = creation.state_dir();
+        let session_dir = state_dir.join(RQBIT_SESSION_FOLDER_NAME);
+        tokio::fs::create_dir_all(&session_dir).await?;
+
+        // torrents are restored from the persisted session on start, active ones
+        // resume from the fast-resume data instead of rechecking every piece
         let session_opt = SessionOptions {
+            persistence: Some(SessionPersistenceConfig::Json {
+                folder: Some(session_dir),
+            }),
+            fastresume: true,
             ..Default::default()
         };
         let session = Session::new_with_opts(creation.save_path.clone().into(), session_opt)
             .await
             .to_dyn_boxed()
             .context(RqbitSnafu {})?;
+        let labels = RqbitLabelStore::load(state_dir.join(RQBIT_LABELS_FILE_NAME)).await?;
         Ok(Arc::new(Self {
             session,
+            labels,
             save_path: creation.save_path,
             subscriber_id: creation.subscriber_id,
             downloader_id: creation.downloader_id,
@@ -102,14 +145,54 @@ impl RqbitDownloader {
         Ok(torrent)
     }
 
+    /// Torrents missing from the session are left out for the caller to treat
+    /// as removed, any other failure is returned.
+    fn query_existing_torrents(
+        &self,
+        hashes: impl IntoIterator<Item = RqbitHash>,
+    ) -> Result<Vec<RqbitTask>, DownloaderError> {
+        hashes
+            .into_iter()
+            .filter_map(|hash| {
+                self.session
+                    .get(TorrentIdOrHash::Hash(hash))
+                    .map(|torrent| RqbitTask::from_query(torrent, self.labels.get(&hash)))
+            })
+            .collect()
+    }
+
     pub fn query_torrent(&self, hash: RqbitHash) -> Result<RqbitTask, DownloaderError> {
         let torrent = self.query_torrent_impl(hash)?;
 
-        let task = RqbitTask::from_query(torrent)?;
+        let task = RqbitTask::from_query(torrent, self.labels.get(&hash))?;
 
         Ok(task)
     }
 
+    /// Stamps the completion time of finished torrents the first time they are
+    /// queried, seeding time is measured from it.
+    async fn record_completion(&self, tasks: &mut [RqbitTask]) -> Result<(), DownloaderError> {
+        let completed_at = Utc::now();
+        let newly_completed = tasks
+            .iter_mut()
+            .filter(|t| t.stats.finished && t.labels.completed_at.is_none())
+            .map(|t| {
+                t.labels.completed_at = Some(completed_at);
+                t.hash_info
+            })
+            .collect_vec();
+
+        if !newly_completed.is_empty() {
+            self.labels
+                .update(&newly_completed, |labels| {
+                    labels.completed_at.get_or_insert(completed_at);
+                })
+                .await?;
+        }
+
+        Ok(())
+    }
+
     pub async fn pause_torrent(&self, hash: RqbitHash) -> Result<(), DownloaderError> {
         let t = self.query_torrent_impl(hash)?;
         self.session
@@ -132,15 +215,202 @@ impl RqbitDownloader {
         Ok(())
     }
 
-    pub async fn delete_torrent(&self, hash: RqbitHash) -> Result<(), DownloaderError> {
+    fn parse_torrent_hashes(hashes: Vec<String>) -> Result<Vec<RqbitHash>, DownloaderError> {
+        hashes
+            .iter()
+            .map(|h| Id20::from_str(h).to_dyn_boxed().context(RqbitSnafu {}))
+            .collect()
+    }
+
+    /// Restricts an initialized torrent to the files picked by the selection.
+    pub async fn apply_file_selection(
+        &self,
+        hash: RqbitHash,
+        selection: &TorrentFileSelection,
+    ) -> Result<(), DownloaderError> {
+        let matcher = selection.matcher()?;
+        let torrent = self.query_torrent_impl(hash)?;
+
+        let files = torrent
+            .metadata
+            .load_full()
+            .map(|m| {
+                m.file_infos
+                    .iter()
+                    .map(|f| (f.relative_filename.to_string_lossy().into_owned(), f.len))
+                    .collect::<Vec<_>>()
+            })
+            .ok_or_else(|| {
+                anyhow::anyhow!("metadata of torrent {} is not resolved", hash.as_string())
+            })
+            .to_dyn_boxed()
+            .context(RqbitSnafu {})?;
+
+        let only_files = matcher.select_indexes(files.iter().map(|(p, s)| (p.as_str(), *s)));
+        if only_files.is_empty() {
+            whatever!(
+                "file selection excludes every file of torrent {}",
+                hash.as_string()
+            );
+        }
+
         self.session
-            .delete(TorrentIdOrHash::Hash(hash), true)
+            .update_only_files(&torrent, &only_files.into_iter().collect())
             .await
             .to_dyn_boxed()
             .context(RqbitSnafu {})?;
 
         Ok(())
     }
+
+    pub async fn delete_torrent(
+        &self,
+        hash: RqbitHash,
+        delete_files: bool,
+    ) -> Result<(), DownloaderError> {
+        self.session
+            .delete(TorrentIdOrHash::Hash(hash), delete_files)
+            .await
+            .to_dyn_boxed()
+            .context(RqbitSnafu {})?;
+
+        self.labels.remove(&[hash]).await?;
+
+        Ok(())
+    }
+
+    /// rqbit can not change the output folder of a torrent, the torrent is
+    /// paused and its files are moved, then it is removed from the session
+    /// and added back from its metadata so it resumes from the moved files.
+    /// Labels are kept throughout and files are moved back on failure.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn move_torrent(
+        &self,
+        hash: RqbitHash,
+        save_path: &str,
+    ) -> Result<(), DownloaderError> {
+        let torrent = self.query_torrent_impl(hash)?;
+        let old_folder = torrent.shared().options.output_folder.clone();
+        let new_folder = std::path::PathBuf::from(save_path);
+        if old_folder == new_folder {
+            return Ok(());
+        }
+
+        let metadata = torrent
+            .metadata
+            .load_full()
+            .whatever_context::<_, DownloaderError>(
+                "can not move torrent before its metadata is resolved",
+            )?;
+        let relative_files = metadata
+            .file_infos
+            .iter()
+            .map(|f| f.relative_filename.clone())
+            .collect_vec();
+        let paused =
+            self.query_torrent(hash)?.state().to_download_state() == DownloadSimpleState::Paused;
+
+        if !paused {
+            self.pause_torrent(hash).await?;
+        }
+
+        let mut moved_files = vec![];
+        for relative_file in &relative_files {
+            let from = old_folder.join(relative_file);
+            let to = new_folder.join(relative_file);
+            match Self::move_file(&from, &to).await {
+                Ok(true) => moved_files.push((from, to)),
+                Ok(false) => {}
+                Err(e) => {
+                    Self::move_files_back(&moved_files).await;
+                    if !paused {
+                        self.resume_torrent(hash).await?;
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        let add_torrent = |output_folder: String| {
+            self.session.add_torrent(
+                AddTorrent::TorrentFileBytes(metadata.torrent_bytes.clone()),
+                Some(AddTorrentOptions {
+                    paused,
+                    overwrite: true,
+                    output_folder: Some(output_folder),
+                    ..Default::default()
+                }),
+            )
+        };
+
+        self.session
+            .delete(TorrentIdOrHash::Hash(hash), false)
+            .await
+            .to_dyn_boxed()
+            .context(RqbitSnafu {})?;
+
+        let added = add_torrent(save_path.to_string()).await;
+        if added.is_err() {
+            Self::move_files_back(&moved_files).await;
+            if let Err(e) = add_torrent(old_folder.to_string_lossy().into_owned()).await {
+                tracing::error!(
+                    err = ?e,
+                    hash = hash.as_string(),
+                    "Failed to add back torrent to its previous folder"
+                );
+            }
+        }
+        added.to_dyn_boxed().context(RqbitSnafu {})?;
+
+        Ok(())
+    }
+
+    /// Moves a file, falling back to copying it across devices, returns
+    /// `false` if there is nothing to move.
+    async fn move_file(
+        from: &std::path::Path,
+        to: &std::path::Path,
+    ) -> Result<bool, DownloaderError> {
+        if !tokio::fs::try_exists(from).await? {
+            return Ok(false);
+        }
+        if let Some(parent) = to.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        match tokio::fs::rename(from, to).await {
+            Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+                tokio::fs::copy(from, to).await?;
+                tokio::fs::remove_file(from).await?;
+            }
+            result => result?,
+        }
+        Ok(true)
+    }
+
+    async fn move_files_back(moved_files: &[(std::path::PathBuf, std::path::PathBuf)]) {
+        for (from, to) in moved_files {
+            if let Err(e) = Self::move_file(to, from).await {
+                tracing::error!(
+                    err = ?e,
+                    from = %to.display(),
+                    to = %from.display(),
+                    "Failed to move back file of torrent"
+                );
+            }
+        }
+    }
+
+    pub fn select_by_tag(&self, tag: &str) -> RqbitHashSelector {
+        self.labels
+            .find_hashes(|labels| labels.tags.iter().any(|t| t == tag))
+            .into()
+    }
+
+    pub fn select_by_category(&self, category: &str) -> RqbitHashSelector {
+        self.labels
+            .find_hashes(|labels| labels.category.as_deref() == Some(category))
+            .into()
+    }
 }
 
 #[async_trait]
@@ -156,36 +426,55 @@ impl DownloaderTrait for RqbitDownloader {
         &self,
         creation: RqbitCreation,
     ) -> Result<Vec<<Self as DownloaderTrait>::Id>, DownloaderError> {
-        let mut sources = creation.sources;
-        if sources.len() == 1 {
-            let hash = self
-                .add_torrent(
-                    sources.pop().unwrap(),
-                    Some(AddTorrentOptions {
-                        paused: false,
-                        output_folder: Some(self.save_path.clone()),
-                        ..Default::default()
-                    }),
-                )
-                .await?;
-            Ok(vec![hash])
+        let save_path = if creation.save_path.as_str().is_empty() {
+            self.save_path.clone()
         } else {
-            let tasks = sources
-                .into_iter()
-                .map(|s| {
-                    self.add_torrent(
+            creation.save_path.into_string()
+        };
+        let file_selection = creation.file_selection.filter(|s| !s.is_empty());
+        let tags = {
+            let mut tags = vec![TORRENT_TAG_NAME.to_string()];
+            tags.extend(creation.tags);
+            tags.into_iter()
+                .filter(|s| !s.is_empty())
+                .unique()
+                .collect_vec()
+        };
+        let category = creation.category.filter(|s| !s.is_empty());
+
+        // torrents with a file selection are added paused and only resumed
+        // once unwanted files are excluded
+        let tasks = creation.sources.into_iter().map(|s| {
+            let file_selection = file_selection.as_ref();
+            let save_path = save_path.clone();
+            async move {
+                let hash = self
+                    .add_torrent(
                         s,
                         Some(AddTorrentOptions {
-                            paused: false,
-                            output_folder: Some(self.save_path.clone()),
+                            paused: file_selection.is_some(),
+                            output_folder: Some(save_path),
                             ..Default::default()
                         }),
                     )
-                })
-                .collect::<Vec<_>>();
-            let results = futures::future::try_join_all(tasks).await?;
-            Ok(results)
-        }
+                    .await?;
+                if let Some(file_selection) = file_selection {
+                    self.apply_file_selection(hash, file_selection).await?;
+                    self.resume_torrent(hash).await?;
+                }
+                Ok::<_, DownloaderError>(hash)
+            }
+        });
+        let results = futures::future::try_join_all(tasks).await?;
+
+        self.labels
+            .update(&results, |labels| {
+                labels.tags = tags.clone();
+                labels.category = category.clone();
+            })
+            .await?;
+
+        Ok(results)
     }
 
     async fn pause_downloads(
@@ -216,12 +505,37 @@ impl DownloaderTrait for RqbitDownloader {
     ) -> Result<Vec<<Self as DownloaderTrait>::Task>, DownloaderError> {
         let hashes = selector.into_iter();
 
-        let tasks = hashes
+        let mut tasks = hashes
             .map(|h| self.query_torrent(h))
             .collect::<Result<Vec<_>, DownloaderError>>()?;
+        self.record_completion(&mut tasks).await?;
 
         Ok(tasks)
     }
+
+    /// Reads the live stats of the session every [`RQBIT_EVENT_POLL_INTERVAL`],
+    /// torrents deleted from the session are reported as removed.
+    fn watch_downloads(
+        &self,
+        ids: Vec<Self::Id>,
+    ) -> BoxStream<'_, Result<DownloadEvent<Self::Id>, DownloaderError>>
+    where
+        Self: Sync,
+        Self::Selector: From<Vec<Self::Id>>,
+    {
+        DownloadEventDiffer::<Self::Id>::default()
+            .stream(
+                download_event_ticks(RQBIT_EVENT_POLL_INTERVAL).then(move |_| {
+                    let ids = ids.clone();
+                    async move {
+                        let mut tasks = self.query_existing_torrents(ids)?;
+                        self.record_completion(&mut tasks).await?;
+                        Ok(tasks)
+                    }
+                }),
+            )
+            .boxed()
+    }
 }
 
 #[async_trait]
@@ -268,11 +582,159 @@ impl TorrentDownloaderTrait for RqbitDownloader {
         let mut hashes: Vec<_> = selector.clone();
 
         if hashes.len() == 1 {
-            self.delete_torrent(hashes.pop().unwrap()).await?;
+            self.delete_torrent(hashes.pop().unwrap(), true).await?;
         } else {
-            futures::future::try_join_all(hashes.into_iter().map(|h| self.delete_torrent(h)))
+            futures::future::try_join_all(hashes.into_iter().map(|h| self.delete_torrent(h, true)))
                 .await?;
         }
         Ok(selector)
     }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn move_torrents(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+        save_path: &str,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        for hash in hashes.iter() {
+            self.move_torrent(*hash, save_path).await?;
+        }
+        Ok(hashes)
+    }
+
+    async fn rename_torrent_file(
+        &self,
+        _hash: <Self as DownloaderTrait>::Id,
+        _old_path: &str,
+        _new_path: &str,
+    ) -> Result<(), DownloaderError> {
+        UnsupportedOperationSnafu {
+            downloader: "rqbit",
+            operation: "renaming files inside torrents",
+        }
+        .fail()
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn add_torrent_tags(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+        tags: Vec<String>,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        if tags.is_empty() {
+            whatever!("add bittorrent tags can not be empty");
+        }
+        self.labels
+            .update(&hashes, |labels| {
+                labels.tags = labels
+                    .tags
+                    .iter()
+                    .chain(tags.iter())
+                    .unique()
+                    .cloned()
+                    .collect();
+            })
+            .await?;
+        Ok(hashes)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn set_torrents_category(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+        category: &str,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        self.labels
+            .update(&hashes, |labels| {
+                labels.category = Some(category.to_string());
+            })
+            .await?;
+        Ok(hashes)
+    }
+}
+
+#[async_trait]
+impl TorrentDownloaderHandleTrait for RqbitDownloader {
+    async fn add_sources(
+        &self,
+        save_path: PathBuf,
+        sources: Vec<HashTorrentSource>,
+    ) -> Result<Vec<String>, DownloaderError> {
+        let hashes = DownloaderTrait::add_downloads(
+            self,
+            RqbitCreation {
+                save_path,
+                sources,
+                ..Default::default()
+            },
+        )
+        .await?;
+        Ok(hashes.into_iter().map(|h| h.as_string()).collect())
+    }
+
+    async fn query_by_hashes(
+        &self,
+        hashes: Vec<String>,
+    ) -> Result<Vec<TorrentDownloadInfo>, DownloaderError> {
+        let hashes = Self::parse_torrent_hashes(hashes)?;
+
+        let mut tasks = self.query_existing_torrents(hashes)?;
+        self.record_completion(&mut tasks).await?;
+
+        Ok(tasks
+            .into_iter()
+            .map(|task| TorrentDownloadInfo {
+                name: DownloadTaskTrait::name(&task).to_string(),
+                state: task.state().to_download_state(),
+                dl_bytes: task.dl_bytes(),
+                total_bytes: task.total_bytes(),
+                save_path: Some(task.save_path()),
+                hash_info: task.hash_info().to_string(),
+                ratio: TorrentTaskTrait::ratio(&task),
+                seeding_time: TorrentTaskTrait::seeding_time(&task),
+            })
+            .collect())
+    }
+
+    async fn pause_by_hashes(&self, hashes: Vec<String>) -> Result<(), DownloaderError> {
+        let hashes = Self::parse_torrent_hashes(hashes)?;
+        TorrentDownloaderTrait::pause_torrents(self, hashes.into()).await?;
+        Ok(())
+    }
+
+    async fn resume_by_hashes(&self, hashes: Vec<String>) -> Result<(), DownloaderError> {
+        let hashes = Self::parse_torrent_hashes(hashes)?;
+        TorrentDownloaderTrait::resume_torrents(self, hashes.into()).await?;
+        Ok(())
+    }
+
+    async fn remove_by_hashes(&self, hashes: Vec<String>) -> Result<(), DownloaderError> {
+        let hashes = Self::parse_torrent_hashes(hashes)?;
+        TorrentDownloaderTrait::remove_torrents(self, hashes.into()).await?;
+        Ok(())
+    }
+
+    async fn remove_by_hashes_keep_files(
+        &self,
+        hashes: Vec<String>,
+    ) -> Result<(), DownloaderError> {
+        let hashes = Self::parse_torrent_hashes(hashes)?;
+        futures::future::try_join_all(hashes.into_iter().map(|h| self.delete_torrent(h, false)))
+            .await?;
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> Result<(), DownloaderError> {
+        self.session.stop().await;
+        Ok(())
+    }
+}
+
+impl Debug for RqbitDownloader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RqbitDownloader")
+            .field("subscriber_id", &self.subscriber_id)
+            .field("save_path", &self.save_path)
+            .finish()
+    }
 }
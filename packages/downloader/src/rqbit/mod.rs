@@ -1,4 +1,5 @@
 pub mod downloader;
+pub mod store;
 pub mod task;
 #[cfg(test)]
 mod test;
@@ -0,0 +1,74 @@
+use std::str::FromStr;
+
+use librqbit_core::Id20;
+
+use super::task::RqbitHash;
+use crate::bittorrent::labels::{
+    TORRENT_STATE_FOLDER_NAME, TorrentLabelKey, TorrentLabelStore, TorrentLabels,
+};
+
+/// Folder inside the downloader save path that keeps the state konobangu
+/// needs on top of the rqbit session.
+pub const RQBIT_STATE_FOLDER_NAME: &str = TORRENT_STATE_FOLDER_NAME;
+
+pub const RQBIT_LABELS_FILE_NAME: &str = "rqbit-labels.json";
+
+pub const RQBIT_SESSION_FOLDER_NAME: &str = "rqbit-session";
+
+impl TorrentLabelKey for RqbitHash {
+    fn to_label_key(&self) -> String {
+        self.as_string()
+    }
+
+    fn from_label_key(key: &str) -> Option<Self> {
+        Id20::from_str(key).ok()
+    }
+}
+
+pub type RqbitTorrentLabels = TorrentLabels;
+
+/// rqbit has no tags or categories, they are kept in a sidecar file next to
+/// the rqbit session.
+pub type RqbitLabelStore = TorrentLabelStore<RqbitHash>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rqbit_label_store_persists_labels() -> anyhow::Result<()> {
+        let path = std::env::temp_dir()
+            .join(format!("konobangu-rqbit-labels-{}", std::process::id()))
+            .join(RQBIT_LABELS_FILE_NAME);
+        let hash = Id20::from_str("47ee2d69e7f19af783ad896541a07b012676f858")?;
+
+        let store = RqbitLabelStore::load(path.clone()).await?;
+        store
+            .update(&[hash], |labels| {
+                labels.tags.push("konobangu".to_string());
+                labels.category = Some("bangumi".to_string());
+            })
+            .await?;
+
+        let reloaded = RqbitLabelStore::load(path.clone()).await?;
+        assert_eq!(
+            reloaded.get(&hash),
+            RqbitTorrentLabels {
+                tags: vec!["konobangu".to_string()],
+                category: Some("bangumi".to_string()),
+                completed_at: None,
+            }
+        );
+        assert_eq!(reloaded.find_hashes(|l| l.tags.is_empty()), vec![]);
+
+        reloaded.remove(&[hash]).await?;
+        let reloaded = RqbitLabelStore::load(path.clone()).await?;
+        assert_eq!(reloaded.get(&hash), RqbitTorrentLabels::default());
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::remove_dir_all(parent).await?;
+        }
+
+        Ok(())
+    }
+}
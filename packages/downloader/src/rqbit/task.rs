@@ -4,9 +4,11 @@ use librqbit::{ManagedTorrent, ManagedTorrentState, TorrentStats, TorrentStatsSt
 use librqbit_core::Id20;
 use quirks_path::{Path, PathBuf};
 
+use super::store::RqbitTorrentLabels;
 use crate::{
     DownloaderError,
     bittorrent::{
+        selection::TorrentFileSelection,
         source::HashTorrentSource,
         task::{TorrentCreationTrait, TorrentHashTrait, TorrentStateTrait, TorrentTaskTrait},
     },
@@ -55,10 +57,14 @@ pub struct RqbitTask {
     pub torrent: Arc<ManagedTorrent>,
     pub state: RqbitState,
     pub stats: Arc<TorrentStats>,
+    pub labels: RqbitTorrentLabels,
 }
 
 impl RqbitTask {
-    pub fn from_query(torrent: Arc<ManagedTorrent>) -> Result<Self, DownloaderError> {
+    pub fn from_query(
+        torrent: Arc<ManagedTorrent>,
+        labels: RqbitTorrentLabels,
+    ) -> Result<Self, DownloaderError> {
         let hash = torrent.info_hash();
         let stats = Arc::new(torrent.stats());
         Ok(Self {
@@ -66,8 +72,19 @@ impl RqbitTask {
             state: stats.clone().into(),
             stats,
             torrent,
+            labels,
         })
     }
+
+    /// The folder the torrent is downloaded into, which follows moves.
+    pub fn save_path(&self) -> String {
+        self.torrent
+            .shared()
+            .options
+            .output_folder
+            .to_string_lossy()
+            .into_owned()
+    }
 }
 
 impl Debug for RqbitTask {
@@ -97,7 +114,7 @@ impl DownloadTaskTrait for RqbitTask {
             .load_full()
             .and_then(|m| m.name.to_owned())
             .map(Cow::Owned)
-            .unwrap_or_else(|| DownloadTaskTrait::name(self))
+            .unwrap_or_else(|| TorrentTaskTrait::name(self))
     }
 
     fn speed(&self) -> Option<u64> {
@@ -150,11 +167,24 @@ impl TorrentTaskTrait for RqbitTask {
     }
 
     fn tags(&self) -> impl Iterator<Item = Cow<'_, str>> {
-        std::iter::empty()
+        self.labels.tags.iter().map(|t| Cow::Borrowed(t.as_str()))
     }
 
     fn category(&self) -> Option<Cow<'_, str>> {
-        None
+        self.labels.category.as_deref().map(Cow::Borrowed)
+    }
+
+    fn ratio(&self) -> Option<f64> {
+        if self.stats.total_bytes == 0 {
+            return None;
+        }
+        Some(self.stats.uploaded_bytes as f64 / self.stats.total_bytes as f64)
+    }
+
+    fn seeding_time(&self) -> Option<Duration> {
+        self.labels
+            .completed_at
+            .and_then(|t| (chrono::Utc::now() - t).to_std().ok())
     }
 }
 
@@ -164,6 +194,7 @@ pub struct RqbitCreation {
     pub tags: Vec<String>,
     pub category: Option<String>,
     pub sources: Vec<HashTorrentSource>,
+    pub file_selection: Option<TorrentFileSelection>,
 }
 
 impl DownloadCreationTrait for RqbitCreation {
@@ -182,6 +213,10 @@ impl TorrentCreationTrait for RqbitCreation {
     fn sources_mut(&mut self) -> &mut Vec<HashTorrentSource> {
         &mut self.sources
     }
+
+    fn file_selection(&self) -> Option<&TorrentFileSelection> {
+        self.file_selection.as_ref()
+    }
 }
 
 pub type RqbitHashSelector = DownloadIdSelector<RqbitTask>;
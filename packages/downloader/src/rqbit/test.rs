@@ -0,0 +1,220 @@
+use std::{sync::Arc, time::Duration};
+
+use quirks_path::PathBuf;
+use testing_torrents::{TestTorrentRequest, TestingTorrentFileItem, TestingTorrentSeeder};
+
+use crate::{
+    DownloaderError,
+    bittorrent::{
+        downloader::TorrentDownloaderTrait, handle::TorrentDownloaderHandleTrait,
+        source::HashTorrentSource, task::TorrentTaskTrait,
+    },
+    core::{
+        DownloadIdSelectorTrait, DownloadSimpleState, DownloadStateTrait, DownloadTaskTrait,
+        DownloaderTrait,
+    },
+    rqbit::{
+        downloader::{RqbitDownloader, RqbitDownloaderCreation},
+        task::{RqbitCreation, RqbitHash, RqbitHashSelector, RqbitTask},
+    },
+};
+
+async fn wait_until_completed(
+    downloader: &RqbitDownloader,
+    hash: RqbitHash,
+) -> anyhow::Result<RqbitTask> {
+    tokio::time::timeout(Duration::from_secs(60), async {
+        loop {
+            let task: Option<RqbitTask> = downloader
+                .query_downloads(RqbitHashSelector::from_id(hash))
+                .await?
+                .into_iter()
+                .find(|t| t.state().to_download_state() == DownloadSimpleState::Completed);
+            if let Some(task) = task {
+                return anyhow::Ok(task);
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    })
+    .await?
+}
+
+async fn mock_test_torrent(
+    seeder: &TestingTorrentSeeder,
+    id: &str,
+) -> anyhow::Result<HashTorrentSource> {
+    let torrent_res = seeder
+        .mock_torrent(TestTorrentRequest {
+            id: id.into(),
+            file_list: vec![
+                TestingTorrentFileItem {
+                    path: "[LoliHouse] Test Bangumi - 01 [WebRip 1080p HEVC-10bit AAC].mkv".into(),
+                    size: 600 * 1024,
+                },
+                TestingTorrentFileItem {
+                    path: "subs/[LoliHouse] Test Bangumi - 01.ass".into(),
+                    size: 1024,
+                },
+            ],
+        })
+        .await?;
+
+    let http_client = fetch::test_util::build_testing_http_client()?;
+    let torrent_source =
+        HashTorrentSource::from_url_and_http_client(&http_client, torrent_res.torrent_url).await?;
+
+    Ok(torrent_source)
+}
+
+async fn create_test_downloader(
+    save_path: &std::path::Path,
+) -> anyhow::Result<Arc<RqbitDownloader>> {
+    let downloader = RqbitDownloader::from_creation(RqbitDownloaderCreation {
+        save_path: save_path.to_string_lossy().into_owned(),
+        data_dir: None,
+        subscriber_id: 0,
+        downloader_id: 0,
+    })
+    .await?;
+
+    Ok(downloader)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rqbit_downloader_from_seeder() -> anyhow::Result<()> {
+    let seeder = TestingTorrentSeeder::start().await?;
+
+    let torrent_res = seeder
+        .mock_torrent(TestTorrentRequest {
+            id: "rqbit-downloader".into(),
+            file_list: vec![
+                TestingTorrentFileItem {
+                    path: "[LoliHouse] Test Bangumi - 01 [WebRip 1080p HEVC-10bit AAC].mkv".into(),
+                    size: 600 * 1024,
+                },
+                TestingTorrentFileItem {
+                    path: "subs/[LoliHouse] Test Bangumi - 01.ass".into(),
+                    size: 1024,
+                },
+            ],
+        })
+        .await?;
+
+    let save_path = seeder.workspace().join("rqbit-downloader-save");
+    let downloader = create_test_downloader(&save_path).await?;
+
+    let http_client = fetch::test_util::build_testing_http_client()?;
+    let torrent_source =
+        HashTorrentSource::from_url_and_http_client(&http_client, torrent_res.torrent_url).await?;
+
+    let hashes = downloader
+        .add_downloads(RqbitCreation {
+            save_path: PathBuf::from(save_path.to_string_lossy().as_ref()),
+            tags: vec!["test_tag".to_string()],
+            category: None,
+            sources: vec![torrent_source],
+            file_selection: None,
+        })
+        .await?;
+    let hash = hashes.into_iter().next().expect("should have added hash");
+    assert_eq!(hash.as_string(), torrent_res.hash);
+
+    let task = wait_until_completed(&downloader, hash).await?;
+
+    assert_eq!(task.dl_bytes(), Some(600 * 1024 + 1024));
+    assert!(task.tags().any(|t| t == "test_tag"));
+
+    downloader.remove_torrents(vec![hash].into()).await?;
+    seeder.shutdown().await?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rqbit_downloader_restores_torrents_after_restart() -> anyhow::Result<()> {
+    let seeder = TestingTorrentSeeder::start().await?;
+    let torrent_source = mock_test_torrent(&seeder, "rqbit-downloader-restart").await?;
+    let save_path = seeder.workspace().join("rqbit-downloader-restart-save");
+
+    let downloader = create_test_downloader(&save_path).await?;
+    let hash = downloader
+        .add_downloads(RqbitCreation {
+            save_path: PathBuf::from(save_path.to_string_lossy().as_ref()),
+            tags: vec!["test_tag".to_string()],
+            category: Some("bangumi".to_string()),
+            sources: vec![torrent_source],
+            file_selection: None,
+        })
+        .await?
+        .into_iter()
+        .next()
+        .expect("should have added hash");
+    wait_until_completed(&downloader, hash).await?;
+
+    downloader.shutdown().await?;
+    drop(downloader);
+
+    // the torrent is restored from the session persisted in the state folder
+    let downloader = create_test_downloader(&save_path).await?;
+    let task = wait_until_completed(&downloader, hash).await?;
+
+    assert_eq!(task.dl_bytes(), Some(600 * 1024 + 1024));
+    assert!(task.tags().any(|t| t == "test_tag"));
+    assert_eq!(task.category().as_deref(), Some("bangumi"));
+
+    downloader.remove_torrents(vec![hash].into()).await?;
+    seeder.shutdown().await?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rqbit_downloader_move_and_rename_torrent() -> anyhow::Result<()> {
+    let seeder = TestingTorrentSeeder::start().await?;
+    let torrent_source = mock_test_torrent(&seeder, "rqbit-downloader-move").await?;
+    let save_path = seeder.workspace().join("rqbit-downloader-move-save");
+    let moved_path = seeder.workspace().join("rqbit-downloader-move-moved");
+
+    let downloader = create_test_downloader(&save_path).await?;
+    let hash = downloader
+        .add_downloads(RqbitCreation {
+            save_path: PathBuf::from(save_path.to_string_lossy().as_ref()),
+            tags: vec!["test_tag".to_string()],
+            category: Some("bangumi".to_string()),
+            sources: vec![torrent_source],
+            file_selection: None,
+        })
+        .await?
+        .into_iter()
+        .next()
+        .expect("should have added hash");
+    wait_until_completed(&downloader, hash).await?;
+
+    downloader
+        .move_torrents(vec![hash].into(), moved_path.to_string_lossy().as_ref())
+        .await?;
+
+    let task = wait_until_completed(&downloader, hash).await?;
+    assert_eq!(task.save_path(), moved_path.to_string_lossy());
+    assert!(task.tags().any(|t| t == "test_tag"));
+    assert_eq!(task.category().as_deref(), Some("bangumi"));
+
+    let subtitle = "subs/[LoliHouse] Test Bangumi - 01.ass";
+    assert!(!save_path.join(subtitle).exists());
+    assert!(moved_path.join(subtitle).is_file());
+
+    // rqbit can not remap file names, the file must be left untouched
+    assert!(matches!(
+        downloader
+            .rename_torrent_file(hash, subtitle, "subs/Test Bangumi - 01.ass")
+            .await,
+        Err(DownloaderError::UnsupportedOperationError { .. })
+    ));
+    assert!(moved_path.join(subtitle).is_file());
+    assert!(!moved_path.join("subs/Test Bangumi - 01.ass").exists());
+
+    downloader.remove_torrents(vec![hash].into()).await?;
+    seeder.shutdown().await?;
+
+    Ok(())
+}
@@ -0,0 +1,524 @@
+use std::{
+    collections::HashSet,
+    fmt::Debug,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use async_trait::async_trait;
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64_STANDARD};
+use fetch::{
+    HttpClient,
+    reqwest::{StatusCode, header::CONTENT_TYPE},
+};
+use itertools::Itertools;
+use quirks_path::PathBuf;
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use snafu::whatever;
+use tokio::sync::RwLock;
+use tracing::instrument;
+use url::Url;
+
+use super::task::{
+    TRANSMISSION_CATEGORY_LABEL_PREFIX, TransmissionCreation, TransmissionHash,
+    TransmissionHashSelector, TransmissionSelector, TransmissionState, TransmissionTask,
+    TransmissionTorrent,
+};
+use crate::{
+    DownloaderError,
+    bittorrent::{
+        downloader::TorrentDownloaderTrait,
+        handle::{TorrentDownloadInfo, TorrentDownloaderHandleTrait},
+        source::{HashTorrentSource, HashTorrentSourceTrait, MagnetUrlSource, TorrentFileSource},
+        task::{TORRENT_TAG_NAME, TorrentTaskTrait},
+    },
+    core::{DownloadStateTrait, DownloadTaskTrait, DownloaderTrait},
+};
+
+pub const TRANSMISSION_SESSION_ID_HEADER: &str = "X-Transmission-Session-Id";
+pub const TRANSMISSION_DEFAULT_RPC_PATH: &str = "/transmission/rpc";
+
+#[derive(Debug)]
+pub struct TransmissionDownloaderCreation {
+    pub endpoint: String,
+    pub username: String,
+    pub password: String,
+    pub save_path: String,
+    pub subscriber_id: i32,
+    pub downloader_id: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct TransmissionRpcResponse<T> {
+    result: String,
+    arguments: Option<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TransmissionTorrentList {
+    torrents: Vec<TransmissionTorrent>,
+}
+
+pub struct TransmissionDownloader {
+    pub subscriber_id: i32,
+    pub downloader_id: i32,
+    pub endpoint_url: Url,
+    pub username: String,
+    pub password: String,
+    pub save_path: PathBuf,
+    pub client: HttpClient,
+    session_id: RwLock<Option<String>>,
+    request_tag: AtomicU64,
+}
+
+impl TransmissionDownloader {
+    #[instrument(level = "debug")]
+    pub async fn from_creation(
+        creation: TransmissionDownloaderCreation,
+    ) -> Result<Arc<Self>, DownloaderError> {
+        let mut endpoint_url = Url::parse(&creation.endpoint)?;
+        if matches!(endpoint_url.path(), "" | "/") {
+            endpoint_url.set_path(TRANSMISSION_DEFAULT_RPC_PATH);
+        }
+
+        let downloader = Arc::new(Self {
+            subscriber_id: creation.subscriber_id,
+            downloader_id: creation.downloader_id,
+            endpoint_url,
+            username: creation.username,
+            password: creation.password,
+            save_path: creation.save_path.into(),
+            client: HttpClient::default(),
+            session_id: RwLock::new(None),
+            request_tag: AtomicU64::new(0),
+        });
+
+        downloader.check_connection().await?;
+
+        Ok(downloader)
+    }
+
+    async fn send_rpc_request(
+        &self,
+        body: &Value,
+    ) -> Result<fetch::reqwest::Response, DownloaderError> {
+        let session_id = { self.session_id.read().await.clone() };
+
+        let mut request = self
+            .client
+            .post(self.endpoint_url.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_string());
+
+        if !self.username.is_empty() || !self.password.is_empty() {
+            request = request.basic_auth(&self.username, Some(&self.password));
+        }
+
+        if let Some(session_id) = session_id {
+            request = request.header(TRANSMISSION_SESSION_ID_HEADER, session_id);
+        }
+
+        let response = request.send().await?;
+
+        Ok(response)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn delete_torrents(
+        &self,
+        hashes: TransmissionHashSelector,
+        delete_files: bool,
+    ) -> Result<TransmissionHashSelector, DownloaderError> {
+        self.call::<Value>(
+            "torrent-remove",
+            json!({ "ids": &hashes.ids, "delete-local-data": delete_files }),
+        )
+        .await?;
+        Ok(hashes)
+    }
+
+    #[instrument(level = "debug", skip(self, arguments))]
+    async fn call<T: DeserializeOwned>(
+        &self,
+        method: &'static str,
+        arguments: Value,
+    ) -> Result<T, DownloaderError> {
+        let body = json!({
+            "method": method,
+            "arguments": arguments,
+            "tag": self.request_tag.fetch_add(1, Ordering::Relaxed),
+        });
+
+        let mut response = self.send_rpc_request(&body).await?;
+
+        if response.status() == StatusCode::CONFLICT {
+            let session_id = response
+                .headers()
+                .get(TRANSMISSION_SESSION_ID_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string());
+            if session_id.is_none() {
+                whatever!("transmission responded 409 without a session id");
+            }
+            {
+                *self.session_id.write().await = session_id;
+            }
+            response = self.send_rpc_request(&body).await?;
+        }
+
+        let response: TransmissionRpcResponse<T> = response
+            .error_for_status()
+            .map_err(fetch::reqwest_middleware::Error::from)?
+            .json()
+            .await
+            .map_err(fetch::reqwest_middleware::Error::from)?;
+
+        match response {
+            TransmissionRpcResponse {
+                result,
+                arguments: Some(arguments),
+            } if result == "success" => Ok(arguments),
+            TransmissionRpcResponse { result, .. } => Err(DownloaderError::TransmissionRpcError {
+                method: method.into(),
+                result,
+            }),
+        }
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn api_version(&self) -> Result<String, DownloaderError> {
+        #[derive(Deserialize)]
+        struct TransmissionSession {
+            version: String,
+        }
+
+        let session: TransmissionSession = self
+            .call("session-get", json!({ "fields": ["version"] }))
+            .await?;
+        Ok(session.version)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn check_connection(&self) -> Result<(), DownloaderError> {
+        self.api_version().await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_torrents(
+        &self,
+        hashes: &[TransmissionHash],
+    ) -> Result<Vec<TransmissionTorrent>, DownloaderError> {
+        if hashes.is_empty() {
+            return Ok(vec![]);
+        }
+        let list: TransmissionTorrentList = self
+            .call(
+                "torrent-get",
+                json!({ "ids": hashes, "fields": TransmissionTorrent::FIELDS }),
+            )
+            .await?;
+        Ok(list.torrents)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn set_torrent_labels(
+        &self,
+        hash: &str,
+        labels: Vec<String>,
+    ) -> Result<(), DownloaderError> {
+        self.call::<Value>("torrent-set", json!({ "ids": [hash], "labels": labels }))
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self, source))]
+    async fn add_torrent(
+        &self,
+        source: HashTorrentSource,
+        save_path: &str,
+        labels: &[String],
+    ) -> Result<(), DownloaderError> {
+        let mut arguments = json!({
+            "download-dir": save_path,
+            "paused": false,
+            "labels": labels,
+        });
+        match source {
+            HashTorrentSource::MagnetUrl(MagnetUrlSource { url, .. }) => {
+                arguments["filename"] = json!(url);
+            }
+            HashTorrentSource::TorrentFile(TorrentFileSource { payload, .. }) => {
+                arguments["metainfo"] = json!(BASE64_STANDARD.encode(payload));
+            }
+        }
+        self.call::<Value>("torrent-add", arguments).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DownloaderTrait for TransmissionDownloader {
+    type State = TransmissionState;
+    type Id = TransmissionHash;
+    type Task = TransmissionTask;
+    type Creation = TransmissionCreation;
+    type Selector = TransmissionSelector;
+
+    #[instrument(level = "debug", skip(self))]
+    async fn add_downloads(
+        &self,
+        creation: <Self as DownloaderTrait>::Creation,
+    ) -> Result<HashSet<<Self as DownloaderTrait>::Id>, DownloaderError> {
+        let labels = {
+            let mut labels = vec![TORRENT_TAG_NAME.to_string()];
+            labels.extend(creation.tags);
+            if let Some(category) = creation.category {
+                labels.push(format!("{TRANSMISSION_CATEGORY_LABEL_PREFIX}{category}"));
+            }
+            labels
+                .into_iter()
+                .filter(|s| !s.is_empty())
+                .unique()
+                .collect_vec()
+        };
+
+        let save_path = creation.save_path.into_string();
+        let hashes = HashSet::from_iter(
+            creation
+                .sources
+                .iter()
+                .map(|s| s.hash_info().to_lowercase()),
+        );
+
+        futures::future::try_join_all(
+            creation
+                .sources
+                .into_iter()
+                .map(|s| self.add_torrent(s, &save_path, &labels)),
+        )
+        .await?;
+
+        Ok(hashes)
+    }
+
+    async fn pause_downloads(
+        &self,
+        selector: <Self as DownloaderTrait>::Selector,
+    ) -> Result<impl IntoIterator<Item = Self::Id>, DownloaderError> {
+        <Self as TorrentDownloaderTrait>::pause_downloads(self, selector).await
+    }
+
+    async fn resume_downloads(
+        &self,
+        selector: <Self as DownloaderTrait>::Selector,
+    ) -> Result<impl IntoIterator<Item = Self::Id>, DownloaderError> {
+        <Self as TorrentDownloaderTrait>::resume_downloads(self, selector).await
+    }
+
+    async fn remove_downloads(
+        &self,
+        selector: <Self as DownloaderTrait>::Selector,
+    ) -> Result<impl IntoIterator<Item = Self::Id>, DownloaderError> {
+        <Self as TorrentDownloaderTrait>::remove_downloads(self, selector).await
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn query_downloads(
+        &self,
+        selector: TransmissionSelector,
+    ) -> Result<Vec<<Self as DownloaderTrait>::Task>, DownloaderError> {
+        let torrents = self.get_torrents(&selector).await?;
+
+        Ok(torrents
+            .into_iter()
+            .map(TransmissionTask::from_query)
+            .collect())
+    }
+}
+
+#[async_trait]
+impl TorrentDownloaderTrait for TransmissionDownloader {
+    type IdSelector = TransmissionHashSelector;
+
+    #[instrument(level = "debug", skip(self))]
+    async fn pause_torrents(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        self.call::<Value>("torrent-stop", json!({ "ids": &hashes.ids }))
+            .await?;
+        Ok(hashes)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn resume_torrents(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        self.call::<Value>("torrent-start", json!({ "ids": &hashes.ids }))
+            .await?;
+        Ok(hashes)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn remove_torrents(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        self.delete_torrents(hashes, true).await
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn move_torrents(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+        save_path: &str,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        self.call::<Value>(
+            "torrent-set-location",
+            json!({ "ids": &hashes.ids, "location": save_path, "move": true }),
+        )
+        .await?;
+        Ok(hashes)
+    }
+
+    /// Transmission only renames the last component of a path, the parent of
+    /// both paths has to be the same.
+    #[instrument(level = "debug", skip(self))]
+    async fn rename_torrent_file(
+        &self,
+        hash: <Self as DownloaderTrait>::Id,
+        old_path: &str,
+        new_path: &str,
+    ) -> Result<(), DownloaderError> {
+        let (old_parent, _) = old_path.rsplit_once('/').unwrap_or(("", old_path));
+        let (new_parent, new_name) = new_path.rsplit_once('/').unwrap_or(("", new_path));
+        if old_parent != new_parent {
+            whatever!(
+                "transmission can not move {old_path} to another folder, only rename it in place"
+            );
+        }
+        self.call::<Value>(
+            "torrent-rename-path",
+            json!({ "ids": [hash], "path": old_path, "name": new_name }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn add_torrent_tags(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+        tags: Vec<String>,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        if tags.is_empty() {
+            whatever!("add bittorrent tags can not be empty");
+        }
+        let torrents = self.get_torrents(&hashes.ids).await?;
+        futures::future::try_join_all(torrents.into_iter().map(|t| {
+            let labels = t.labels.into_iter().chain(tags.clone()).unique().collect();
+            async move { self.set_torrent_labels(&t.hash_string, labels).await }
+        }))
+        .await?;
+        Ok(hashes)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn set_torrents_category(
+        &self,
+        hashes: <Self as TorrentDownloaderTrait>::IdSelector,
+        category: &str,
+    ) -> Result<Self::IdSelector, DownloaderError> {
+        let category_label = format!("{TRANSMISSION_CATEGORY_LABEL_PREFIX}{category}");
+        let torrents = self.get_torrents(&hashes.ids).await?;
+        futures::future::try_join_all(torrents.into_iter().map(|t| {
+            let labels = t
+                .labels
+                .into_iter()
+                .filter(|l| !l.starts_with(TRANSMISSION_CATEGORY_LABEL_PREFIX))
+                .chain([category_label.clone()])
+                .collect();
+            async move { self.set_torrent_labels(&t.hash_string, labels).await }
+        }))
+        .await?;
+        Ok(hashes)
+    }
+}
+
+#[async_trait]
+impl TorrentDownloaderHandleTrait for TransmissionDownloader {
+    async fn add_sources(
+        &self,
+        save_path: PathBuf,
+        sources: Vec<HashTorrentSource>,
+    ) -> Result<Vec<String>, DownloaderError> {
+        let hashes = DownloaderTrait::add_downloads(
+            self,
+            TransmissionCreation {
+                save_path,
+                sources,
+                ..Default::default()
+            },
+        )
+        .await?;
+        Ok(hashes.into_iter().collect())
+    }
+
+    async fn query_by_hashes(
+        &self,
+        hashes: Vec<String>,
+    ) -> Result<Vec<TorrentDownloadInfo>, DownloaderError> {
+        let tasks = DownloaderTrait::query_downloads(self, hashes.into()).await?;
+
+        Ok(tasks
+            .into_iter()
+            .map(|task| TorrentDownloadInfo {
+                name: DownloadTaskTrait::name(&task).to_string(),
+                state: task.state().to_download_state(),
+                dl_bytes: task.dl_bytes(),
+                total_bytes: task.total_bytes(),
+                save_path: task.torrent.download_dir.clone(),
+                hash_info: task.hash_info().to_string(),
+                ratio: TorrentTaskTrait::ratio(&task),
+                seeding_time: TorrentTaskTrait::seeding_time(&task),
+            })
+            .collect())
+    }
+
+    async fn pause_by_hashes(&self, hashes: Vec<String>) -> Result<(), DownloaderError> {
+        TorrentDownloaderTrait::pause_torrents(self, hashes.into()).await?;
+        Ok(())
+    }
+
+    async fn resume_by_hashes(&self, hashes: Vec<String>) -> Result<(), DownloaderError> {
+        TorrentDownloaderTrait::resume_torrents(self, hashes.into()).await?;
+        Ok(())
+    }
+
+    async fn remove_by_hashes(&self, hashes: Vec<String>) -> Result<(), DownloaderError> {
+        TorrentDownloaderTrait::remove_torrents(self, hashes.into()).await?;
+        Ok(())
+    }
+
+    async fn remove_by_hashes_keep_files(
+        &self,
+        hashes: Vec<String>,
+    ) -> Result<(), DownloaderError> {
+        self.delete_torrents(hashes.into(), false).await?;
+        Ok(())
+    }
+}
+
+impl Debug for TransmissionDownloader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TransmissionDownloader")
+            .field("subscriber_id", &self.subscriber_id)
+            .field("client", &self.endpoint_url.as_str())
+            .finish()
+    }
+}
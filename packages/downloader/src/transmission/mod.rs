@@ -0,0 +1,12 @@
+pub mod downloader;
+pub mod task;
+
+#[cfg(test)]
+mod test;
+
+pub use downloader::{TransmissionDownloader, TransmissionDownloaderCreation};
+pub use task::{
+    TRANSMISSION_CATEGORY_LABEL_PREFIX, TransmissionCreation, TransmissionHash,
+    TransmissionHashSelector, TransmissionSelector, TransmissionState, TransmissionTask,
+    TransmissionTorrent,
+};
@@ -0,0 +1,228 @@
+use std::{borrow::Cow, time::Duration};
+
+use quirks_path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bittorrent::{
+        source::HashTorrentSource,
+        task::{SimpleTorrentHash, TorrentCreationTrait, TorrentStateTrait, TorrentTaskTrait},
+    },
+    core::{
+        DownloadCreationTrait, DownloadIdSelector, DownloadSimpleState, DownloadStateTrait,
+        DownloadTaskTrait,
+    },
+};
+
+pub type TransmissionHash = SimpleTorrentHash;
+
+/// Transmission only knows labels, categories are stored as a label with this
+/// prefix.
+pub const TRANSMISSION_CATEGORY_LABEL_PREFIX: &str = "category:";
+
+/// `error` of a torrent that failed locally, such as a missing or full
+/// download dir, `1` and `2` are tracker warnings and errors that the torrent
+/// usually recovers from.
+pub const TRANSMISSION_LOCAL_ERROR: i64 = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransmissionState {
+    /// `0` stopped, `1` queued to verify, `2` verifying, `3` queued to
+    /// download, `4` downloading, `5` queued to seed, `6` seeding
+    pub status: i64,
+    pub error: i64,
+    pub percent_done: f64,
+}
+
+impl DownloadStateTrait for TransmissionState {
+    fn to_download_state(&self) -> DownloadSimpleState {
+        if self.error == TRANSMISSION_LOCAL_ERROR {
+            return DownloadSimpleState::Error;
+        }
+        match self.status {
+            0 if self.percent_done >= 1.0 => DownloadSimpleState::Completed,
+            0 => DownloadSimpleState::Paused,
+            1..=4 => DownloadSimpleState::Active,
+            5 | 6 => DownloadSimpleState::Completed,
+            _ => DownloadSimpleState::Unknown,
+        }
+    }
+}
+
+impl TorrentStateTrait for TransmissionState {}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransmissionTorrent {
+    pub id: i64,
+    pub hash_string: String,
+    pub name: Option<String>,
+    pub status: i64,
+    #[serde(default)]
+    pub error: i64,
+    pub error_string: Option<String>,
+    pub size_when_done: Option<u64>,
+    pub left_until_done: Option<u64>,
+    pub rate_download: Option<u64>,
+    #[serde(default)]
+    pub percent_done: f64,
+    pub eta: Option<i64>,
+    pub seconds_downloading: Option<u64>,
+    pub download_dir: Option<String>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+}
+
+impl TransmissionTorrent {
+    pub const FIELDS: &[&str] = &[
+        "id",
+        "hashString",
+        "name",
+        "status",
+        "error",
+        "errorString",
+        "sizeWhenDone",
+        "leftUntilDone",
+        "rateDownload",
+        "percentDone",
+        "eta",
+        "secondsDownloading",
+        "downloadDir",
+        "labels",
+    ];
+}
+
+#[derive(Debug)]
+pub struct TransmissionTask {
+    pub hash_info: TransmissionHash,
+    pub torrent: TransmissionTorrent,
+    pub state: TransmissionState,
+}
+
+impl TransmissionTask {
+    pub fn from_query(torrent: TransmissionTorrent) -> Self {
+        Self {
+            hash_info: torrent.hash_string.to_lowercase(),
+            state: TransmissionState {
+                status: torrent.status,
+                error: torrent.error,
+                percent_done: torrent.percent_done,
+            },
+            torrent,
+        }
+    }
+}
+
+impl DownloadTaskTrait for TransmissionTask {
+    type State = TransmissionState;
+    type Id = TransmissionHash;
+
+    fn id(&self) -> &Self::Id {
+        &self.hash_info
+    }
+
+    fn into_id(self) -> Self::Id {
+        self.hash_info
+    }
+
+    fn name(&self) -> Cow<'_, str> {
+        self.torrent
+            .name
+            .as_deref()
+            .map(Cow::Borrowed)
+            .unwrap_or_else(|| TorrentTaskTrait::name(self))
+    }
+
+    fn speed(&self) -> Option<u64> {
+        self.torrent.rate_download
+    }
+
+    fn state(&self) -> &Self::State {
+        &self.state
+    }
+
+    fn dl_bytes(&self) -> Option<u64> {
+        if let (Some(total), Some(left)) =
+            (self.torrent.size_when_done, self.torrent.left_until_done)
+        {
+            total.checked_sub(left)
+        } else {
+            None
+        }
+    }
+
+    fn total_bytes(&self) -> Option<u64> {
+        self.torrent.size_when_done
+    }
+
+    fn left_bytes(&self) -> Option<u64> {
+        self.torrent.left_until_done
+    }
+
+    fn et(&self) -> Option<Duration> {
+        self.torrent.seconds_downloading.map(Duration::from_secs)
+    }
+
+    fn eta(&self) -> Option<Duration> {
+        self.torrent
+            .eta
+            .and_then(|v| u64::try_from(v).ok())
+            .map(Duration::from_secs)
+    }
+
+    fn progress(&self) -> Option<f32> {
+        Some(self.torrent.percent_done as f32)
+    }
+}
+
+impl TorrentTaskTrait for TransmissionTask {
+    fn hash_info(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.hash_info)
+    }
+
+    fn tags(&self) -> impl Iterator<Item = Cow<'_, str>> {
+        self.torrent
+            .labels
+            .iter()
+            .filter(|l| !l.starts_with(TRANSMISSION_CATEGORY_LABEL_PREFIX))
+            .map(|l| Cow::Borrowed(l.as_str()))
+    }
+
+    fn category(&self) -> Option<Cow<'_, str>> {
+        self.torrent
+            .labels
+            .iter()
+            .find_map(|l| l.strip_prefix(TRANSMISSION_CATEGORY_LABEL_PREFIX))
+            .map(Cow::Borrowed)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TransmissionCreation {
+    pub save_path: PathBuf,
+    pub tags: Vec<String>,
+    pub category: Option<String>,
+    pub sources: Vec<HashTorrentSource>,
+}
+
+impl DownloadCreationTrait for TransmissionCreation {
+    type Task = TransmissionTask;
+}
+
+impl TorrentCreationTrait for TransmissionCreation {
+    fn save_path(&self) -> &Path {
+        self.save_path.as_ref()
+    }
+
+    fn save_path_mut(&mut self) -> &mut PathBuf {
+        &mut self.save_path
+    }
+
+    fn sources_mut(&mut self) -> &mut Vec<HashTorrentSource> {
+        &mut self.sources
+    }
+}
+
+pub type TransmissionHashSelector = DownloadIdSelector<TransmissionTask>;
+
+pub type TransmissionSelector = TransmissionHashSelector;
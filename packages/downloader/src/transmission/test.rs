@@ -0,0 +1,385 @@
+use std::sync::Arc;
+
+use mockito::{Matcher, Mock, ServerGuard};
+use serde_json::{Value, json};
+
+use crate::{
+    DownloaderError,
+    bittorrent::{
+        downloader::TorrentDownloaderTrait,
+        handle::TorrentDownloaderHandleTrait,
+        source::HashTorrentSource,
+        task::{TORRENT_TAG_NAME, TorrentTaskTrait},
+    },
+    core::{
+        DownloadIdSelectorTrait, DownloadSimpleState, DownloadStateTrait, DownloadTaskTrait,
+        DownloaderTrait,
+    },
+    transmission::{
+        TransmissionCreation, TransmissionDownloader, TransmissionDownloaderCreation,
+        TransmissionHashSelector, TransmissionState, downloader::TRANSMISSION_SESSION_ID_HEADER,
+        task::TRANSMISSION_LOCAL_ERROR,
+    },
+};
+
+const TEST_SESSION_ID: &str = "konobangu-session";
+const TEST_SAVE_PATH: &str = "/downloads/konobangu";
+const TEST_HASH: &str = "47ee2d69e7f19af783ad896541a07b012676f858";
+
+fn test_magnet_url() -> String {
+    format!("magnet:?xt=urn:btih:{TEST_HASH}&dn=test")
+}
+
+fn mock_rpc(server: &mut ServerGuard, body: Value, arguments: Value) -> Mock {
+    server
+        .mock("POST", "/transmission/rpc")
+        .match_header(TRANSMISSION_SESSION_ID_HEADER, TEST_SESSION_ID)
+        .match_body(Matcher::PartialJson(body))
+        .with_header("content-type", "application/json")
+        .with_body(json!({ "result": "success", "arguments": arguments }).to_string())
+}
+
+fn test_torrent(status: i64, labels: Vec<&str>) -> Value {
+    json!({
+        "id": 1,
+        "hashString": TEST_HASH,
+        "name": "test torrent",
+        "status": status,
+        "error": 0,
+        "errorString": "",
+        "sizeWhenDone": 1024,
+        "leftUntilDone": 256,
+        "rateDownload": 128,
+        "percentDone": 0.75,
+        "eta": 6,
+        "secondsDownloading": 10,
+        "downloadDir": format!("{TEST_SAVE_PATH}/bangumi"),
+        "labels": labels
+    })
+}
+
+async fn create_test_downloader(
+    server: &mut ServerGuard,
+) -> Result<Arc<TransmissionDownloader>, DownloaderError> {
+    server
+        .mock("POST", "/transmission/rpc")
+        .match_header(TRANSMISSION_SESSION_ID_HEADER, Matcher::Missing)
+        .with_status(409)
+        .with_header(TRANSMISSION_SESSION_ID_HEADER, TEST_SESSION_ID)
+        .create_async()
+        .await;
+
+    mock_rpc(
+        server,
+        json!({ "method": "session-get" }),
+        json!({ "version": "4.0.6", "rpc-version": 18 }),
+    )
+    .create_async()
+    .await;
+
+    TransmissionDownloader::from_creation(TransmissionDownloaderCreation {
+        endpoint: server.url(),
+        username: String::new(),
+        password: String::new(),
+        save_path: TEST_SAVE_PATH.to_string(),
+        subscriber_id: 0,
+        downloader_id: 0,
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_transmission_downloader_session_handshake() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let downloader = create_test_downloader(&mut server).await?;
+
+    assert_eq!(downloader.api_version().await?, "4.0.6");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transmission_downloader_add_and_query() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let downloader = create_test_downloader(&mut server).await?;
+
+    let add = mock_rpc(
+        &mut server,
+        json!({
+            "method": "torrent-add",
+            "arguments": {
+                "filename": test_magnet_url(),
+                "download-dir": format!("{TEST_SAVE_PATH}/bangumi"),
+                "labels": [TORRENT_TAG_NAME, "test_tag", "category:test_category"]
+            }
+        }),
+        json!({ "torrent-added": { "id": 1, "hashString": TEST_HASH, "name": "test torrent" } }),
+    )
+    .create_async()
+    .await;
+
+    let hashes = downloader
+        .add_downloads(TransmissionCreation {
+            save_path: format!("{TEST_SAVE_PATH}/bangumi").into(),
+            tags: vec!["test_tag".to_string()],
+            category: Some("test_category".to_string()),
+            sources: vec![HashTorrentSource::from_magnet_url(test_magnet_url())?],
+        })
+        .await?;
+
+    add.assert_async().await;
+    assert!(hashes.contains(TEST_HASH));
+
+    mock_rpc(
+        &mut server,
+        json!({ "method": "torrent-get", "arguments": { "ids": [TEST_HASH] } }),
+        json!({
+            "torrents": [test_torrent(
+                4,
+                vec![TORRENT_TAG_NAME, "test_tag", "category:test_category"]
+            )]
+        }),
+    )
+    .create_async()
+    .await;
+
+    let tasks = downloader
+        .query_downloads(TransmissionHashSelector::from_id(TEST_HASH.to_string()))
+        .await?;
+
+    assert_eq!(tasks.len(), 1);
+    let task = tasks.first().expect("should have task");
+    assert_eq!(task.hash_info(), TEST_HASH);
+    assert_eq!(DownloadTaskTrait::name(task), "test torrent");
+    assert_eq!(
+        task.state().to_download_state(),
+        DownloadSimpleState::Active
+    );
+    assert_eq!(task.dl_bytes(), Some(768));
+    assert_eq!(task.total_bytes(), Some(1024));
+    assert!(task.tags().any(|t| t == TORRENT_TAG_NAME));
+    assert!(task.tags().all(|t| t != "category:test_category"));
+    assert_eq!(task.category().as_deref(), Some("test_category"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transmission_downloader_pause_resume_and_remove() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let downloader = create_test_downloader(&mut server).await?;
+
+    let stop = mock_rpc(
+        &mut server,
+        json!({ "method": "torrent-stop", "arguments": { "ids": [TEST_HASH] } }),
+        json!({}),
+    )
+    .create_async()
+    .await;
+    let start = mock_rpc(
+        &mut server,
+        json!({ "method": "torrent-start", "arguments": { "ids": [TEST_HASH] } }),
+        json!({}),
+    )
+    .create_async()
+    .await;
+    let remove = mock_rpc(
+        &mut server,
+        json!({
+            "method": "torrent-remove",
+            "arguments": { "ids": [TEST_HASH], "delete-local-data": true }
+        }),
+        json!({}),
+    )
+    .create_async()
+    .await;
+
+    downloader
+        .pause_torrents(vec![TEST_HASH.to_string()].into())
+        .await?;
+    downloader
+        .resume_torrents(vec![TEST_HASH.to_string()].into())
+        .await?;
+    downloader
+        .remove_torrents(vec![TEST_HASH.to_string()].into())
+        .await?;
+
+    stop.assert_async().await;
+    start.assert_async().await;
+    remove.assert_async().await;
+
+    let remove_keep_files = mock_rpc(
+        &mut server,
+        json!({
+            "method": "torrent-remove",
+            "arguments": { "ids": [TEST_HASH], "delete-local-data": false }
+        }),
+        json!({}),
+    )
+    .create_async()
+    .await;
+
+    downloader
+        .remove_by_hashes_keep_files(vec![TEST_HASH.to_string()])
+        .await?;
+
+    remove_keep_files.assert_async().await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transmission_downloader_tags_and_category() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let downloader = create_test_downloader(&mut server).await?;
+
+    mock_rpc(
+        &mut server,
+        json!({ "method": "torrent-get" }),
+        json!({ "torrents": [test_torrent(6, vec![TORRENT_TAG_NAME, "category:old"])] }),
+    )
+    .create_async()
+    .await;
+
+    let add_tags = mock_rpc(
+        &mut server,
+        json!({
+            "method": "torrent-set",
+            "arguments": {
+                "ids": [TEST_HASH],
+                "labels": [TORRENT_TAG_NAME, "category:old", "test_tag"]
+            }
+        }),
+        json!({}),
+    )
+    .create_async()
+    .await;
+
+    let set_category = mock_rpc(
+        &mut server,
+        json!({
+            "method": "torrent-set",
+            "arguments": { "ids": [TEST_HASH], "labels": [TORRENT_TAG_NAME, "category:new"] }
+        }),
+        json!({}),
+    )
+    .create_async()
+    .await;
+
+    downloader
+        .add_torrent_tags(
+            vec![TEST_HASH.to_string()].into(),
+            vec!["test_tag".to_string()],
+        )
+        .await?;
+    downloader
+        .set_torrents_category(vec![TEST_HASH.to_string()].into(), "new")
+        .await?;
+
+    add_tags.assert_async().await;
+    set_category.assert_async().await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transmission_downloader_move_and_rename() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let downloader = create_test_downloader(&mut server).await?;
+
+    let set_location = mock_rpc(
+        &mut server,
+        json!({
+            "method": "torrent-set-location",
+            "arguments": {
+                "ids": [TEST_HASH],
+                "location": format!("{TEST_SAVE_PATH}/moved"),
+                "move": true
+            }
+        }),
+        json!({}),
+    )
+    .create_async()
+    .await;
+    let rename_path = mock_rpc(
+        &mut server,
+        json!({
+            "method": "torrent-rename-path",
+            "arguments": { "ids": [TEST_HASH], "path": "test/a.mkv", "name": "b.mkv" }
+        }),
+        json!({ "id": 1, "path": "test/a.mkv", "name": "b.mkv" }),
+    )
+    .create_async()
+    .await;
+
+    downloader
+        .move_torrents(
+            vec![TEST_HASH.to_string()].into(),
+            &format!("{TEST_SAVE_PATH}/moved"),
+        )
+        .await?;
+    downloader
+        .rename_torrent_file(TEST_HASH.to_string(), "test/a.mkv", "test/b.mkv")
+        .await?;
+
+    set_location.assert_async().await;
+    rename_path.assert_async().await;
+
+    assert!(
+        downloader
+            .rename_torrent_file(TEST_HASH.to_string(), "test/a.mkv", "other/b.mkv")
+            .await
+            .is_err()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transmission_downloader_rpc_error() -> anyhow::Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let downloader = create_test_downloader(&mut server).await?;
+
+    server
+        .mock("POST", "/transmission/rpc")
+        .match_header(TRANSMISSION_SESSION_ID_HEADER, TEST_SESSION_ID)
+        .match_body(Matcher::PartialJson(json!({ "method": "torrent-add" })))
+        .with_header("content-type", "application/json")
+        .with_body(json!({ "result": "invalid or corrupt torrent file" }).to_string())
+        .create_async()
+        .await;
+
+    let result = downloader
+        .add_downloads(TransmissionCreation {
+            save_path: TEST_SAVE_PATH.into(),
+            sources: vec![HashTorrentSource::from_magnet_url(test_magnet_url())?],
+            ..Default::default()
+        })
+        .await;
+
+    assert!(matches!(
+        result,
+        Err(DownloaderError::TransmissionRpcError { .. })
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_transmission_state_tracker_errors_are_not_failures() {
+    let state = |status, error| TransmissionState {
+        status,
+        error,
+        percent_done: 0.5,
+    };
+
+    // tracker warnings and errors keep the torrent downloading
+    assert_eq!(state(4, 1).to_download_state(), DownloadSimpleState::Active);
+    assert_eq!(state(4, 2).to_download_state(), DownloadSimpleState::Active);
+    assert_eq!(state(0, 2).to_download_state(), DownloadSimpleState::Paused);
+    assert_eq!(
+        state(0, TRANSMISSION_LOCAL_ERROR).to_download_state(),
+        DownloadSimpleState::Error
+    );
+}
@@ -0,0 +1,365 @@
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use async_trait::async_trait;
+use http_cache_reqwest::{CACacheManager, CacheManager, MokaManager};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HttpClientCacheStats {
+    pub backend: String,
+    pub entry_count: u64,
+    /// Bytes for disk-backed backends, weighted entry count for in-memory ones
+    pub size: u64,
+    pub max_size: Option<u64>,
+    pub evictions: u64,
+}
+
+#[async_trait]
+pub(crate) trait HttpClientCacheBackend: CacheManager {
+    async fn stats(&self) -> http_cache::Result<HttpClientCacheStats>;
+}
+
+#[derive(Clone)]
+pub(crate) struct CacheBackend(Arc<dyn HttpClientCacheBackend>);
+
+impl CacheBackend {
+    pub(crate) fn new<T: HttpClientCacheBackend>(backend: T) -> Self {
+        Self(Arc::new(backend))
+    }
+
+    pub(crate) async fn stats(&self) -> http_cache::Result<HttpClientCacheStats> {
+        self.0.stats().await
+    }
+}
+
+impl Debug for CacheBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CacheBackend").finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl CacheManager for CacheBackend {
+    async fn get(
+        &self,
+        cache_key: &str,
+    ) -> http_cache::Result<Option<(http_cache::HttpResponse, http_cache_semantics::CachePolicy)>>
+    {
+        self.0.get(cache_key).await
+    }
+
+    /// Attempts to cache a response and related policy.
+    async fn put(
+        &self,
+        cache_key: String,
+        res: http_cache::HttpResponse,
+        policy: http_cache_semantics::CachePolicy,
+    ) -> http_cache::Result<http_cache::HttpResponse> {
+        self.0.put(cache_key, res, policy).await
+    }
+    /// Attempts to remove a record from cache.
+    async fn delete(&self, cache_key: &str) -> http_cache::Result<()> {
+        self.0.delete(cache_key).await
+    }
+}
+
+pub(crate) struct MokaCacheBackend {
+    manager: MokaManager,
+    evictions: Arc<AtomicU64>,
+}
+
+impl Default for MokaCacheBackend {
+    fn default() -> Self {
+        Self {
+            manager: MokaManager::default(),
+            evictions: Arc::new(AtomicU64::new(0)),
+        }
+    }
+}
+
+impl MokaCacheBackend {
+    pub(crate) fn new(cache_size: u64) -> Self {
+        let evictions = Arc::new(AtomicU64::new(0));
+        let cache = {
+            let evictions = evictions.clone();
+            moka::future::Cache::builder()
+                .max_capacity(cache_size)
+                .eviction_listener(move |_key, _value, cause| {
+                    if cause.was_evicted() {
+                        evictions.fetch_add(1, Ordering::Relaxed);
+                    }
+                })
+                .build()
+        };
+
+        Self {
+            manager: MokaManager {
+                cache: Arc::new(cache),
+            },
+            evictions,
+        }
+    }
+}
+
+#[async_trait]
+impl CacheManager for MokaCacheBackend {
+    async fn get(
+        &self,
+        cache_key: &str,
+    ) -> http_cache::Result<Option<(http_cache::HttpResponse, http_cache_semantics::CachePolicy)>>
+    {
+        self.manager.get(cache_key).await
+    }
+
+    async fn put(
+        &self,
+        cache_key: String,
+        res: http_cache::HttpResponse,
+        policy: http_cache_semantics::CachePolicy,
+    ) -> http_cache::Result<http_cache::HttpResponse> {
+        self.manager.put(cache_key, res, policy).await
+    }
+
+    async fn delete(&self, cache_key: &str) -> http_cache::Result<()> {
+        self.manager.delete(cache_key).await
+    }
+}
+
+#[async_trait]
+impl HttpClientCacheBackend for MokaCacheBackend {
+    async fn stats(&self) -> http_cache::Result<HttpClientCacheStats> {
+        let cache = &self.manager.cache;
+        cache.run_pending_tasks().await;
+
+        Ok(HttpClientCacheStats {
+            backend: String::from("moka"),
+            entry_count: cache.entry_count(),
+            size: cache.weighted_size(),
+            max_size: cache.policy().max_capacity(),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        })
+    }
+}
+
+/// Disk-backed cache, entries survive restarts. When `max_size` (in bytes) is
+/// exceeded the least recently written entries are removed.
+pub(crate) struct CacacheBackend {
+    manager: CACacheManager,
+    path: PathBuf,
+    max_size: Option<u64>,
+    size: AtomicU64,
+    evictions: AtomicU64,
+    eviction_lock: Mutex<()>,
+}
+
+struct CacacheIndexEntry {
+    key: String,
+    time: u128,
+    size: u64,
+}
+
+fn list_cacache_index(path: &Path) -> http_cache::Result<Vec<CacacheIndexEntry>> {
+    let mut entries = vec![];
+    for metadata in cacache::index::ls(path) {
+        let metadata = metadata?;
+        entries.push(CacacheIndexEntry {
+            key: metadata.key,
+            time: metadata.time,
+            size: metadata.size as u64,
+        });
+    }
+    Ok(entries)
+}
+
+impl CacacheBackend {
+    pub(crate) fn new(path: PathBuf, max_size: Option<u64>) -> http_cache::Result<Self> {
+        let size = if path.exists() {
+            list_cacache_index(&path)?.iter().map(|e| e.size).sum()
+        } else {
+            0
+        };
+
+        Ok(Self {
+            manager: CACacheManager {
+                path: path.clone(),
+                ..Default::default()
+            },
+            path,
+            max_size,
+            size: AtomicU64::new(size),
+            evictions: AtomicU64::new(0),
+            eviction_lock: Mutex::new(()),
+        })
+    }
+
+    async fn entry_size(&self, cache_key: &str) -> http_cache::Result<u64> {
+        Ok(cacache::metadata(&self.path, cache_key)
+            .await?
+            .map(|m| m.size as u64)
+            .unwrap_or_default())
+    }
+
+    async fn list_index(&self) -> http_cache::Result<Vec<CacacheIndexEntry>> {
+        let path = self.path.clone();
+        tokio::task::spawn_blocking(move || list_cacache_index(&path)).await?
+    }
+
+    async fn evict_if_needed(&self) -> http_cache::Result<()> {
+        let Some(max_size) = self.max_size else {
+            return Ok(());
+        };
+        if self.size.load(Ordering::Relaxed) <= max_size {
+            return Ok(());
+        }
+
+        let _guard = self.eviction_lock.lock().await;
+
+        let mut entries = self.list_index().await?;
+        let mut size: u64 = entries.iter().map(|e| e.size).sum();
+        entries.sort_by_key(|e| e.time);
+
+        for entry in entries {
+            if size <= max_size {
+                break;
+            }
+            cacache::RemoveOpts::new()
+                .remove_fully(true)
+                .remove(&self.path, &entry.key)
+                .await?;
+            size = size.saturating_sub(entry.size);
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+
+        self.size.store(size, Ordering::Relaxed);
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CacheManager for CacacheBackend {
+    async fn get(
+        &self,
+        cache_key: &str,
+    ) -> http_cache::Result<Option<(http_cache::HttpResponse, http_cache_semantics::CachePolicy)>>
+    {
+        self.manager.get(cache_key).await
+    }
+
+    async fn put(
+        &self,
+        cache_key: String,
+        res: http_cache::HttpResponse,
+        policy: http_cache_semantics::CachePolicy,
+    ) -> http_cache::Result<http_cache::HttpResponse> {
+        let previous_size = self.entry_size(&cache_key).await?;
+        let res = self.manager.put(cache_key.clone(), res, policy).await?;
+        let current_size = self.entry_size(&cache_key).await?;
+
+        self.size.fetch_add(current_size, Ordering::Relaxed);
+        let _ = self
+            .size
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |s| {
+                Some(s.saturating_sub(previous_size))
+            });
+
+        if let Err(err) = self.evict_if_needed().await {
+            tracing::warn!(error = %err, "Failed to evict http client cache entries");
+        }
+
+        Ok(res)
+    }
+
+    async fn delete(&self, cache_key: &str) -> http_cache::Result<()> {
+        let previous_size = self.entry_size(cache_key).await?;
+        self.manager.delete(cache_key).await?;
+        let _ = self
+            .size
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |s| {
+                Some(s.saturating_sub(previous_size))
+            });
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl HttpClientCacheBackend for CacacheBackend {
+    async fn stats(&self) -> http_cache::Result<HttpClientCacheStats> {
+        let entries = if self.path.exists() {
+            self.list_index().await?
+        } else {
+            vec![]
+        };
+        let size = entries.iter().map(|e| e.size).sum();
+        self.size.store(size, Ordering::Relaxed);
+
+        Ok(HttpClientCacheStats {
+            backend: String::from("cacache"),
+            entry_count: entries.len() as u64,
+            size,
+            max_size: self.max_size,
+            evictions: self.evictions.load(Ordering::Relaxed),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http_cache::{HttpResponse, HttpVersion};
+    use http_cache_semantics::CachePolicy;
+    use reqwest::Url;
+
+    use super::*;
+
+    fn build_response(url: &Url, body: Vec<u8>) -> (HttpResponse, CachePolicy) {
+        let res = HttpResponse {
+            body,
+            headers: Default::default(),
+            status: 200,
+            url: url.clone(),
+            version: HttpVersion::Http11,
+        };
+        let req = axum::http::Request::get(url.as_str()).body(()).unwrap();
+        let parts = axum::http::Response::builder()
+            .status(200)
+            .header("cache-control", "max-age=3600")
+            .body(())
+            .unwrap();
+        let policy = CachePolicy::new(&req, &parts);
+        (res, policy)
+    }
+
+    #[tokio::test]
+    async fn test_cacache_backend_evicts_oldest_entries() -> http_cache::Result<()> {
+        let dir = std::env::temp_dir().join(format!("fetch-cacache-{}", fastrand::u64(..)));
+
+        let backend = CacacheBackend::new(dir.clone(), Some(3 * 1024))?;
+
+        for i in 0..5 {
+            let url = Url::parse(&format!("https://example.com/{i}"))?;
+            let (res, policy) = build_response(&url, vec![0u8; 1024]);
+            backend.put(format!("GET:{url}"), res, policy).await?;
+        }
+
+        let stats = backend.stats().await?;
+        assert!(stats.size <= 3 * 1024);
+        assert!(stats.evictions >= 2);
+        assert!(backend.get("GET:https://example.com/0").await?.is_none());
+        assert!(backend.get("GET:https://example.com/4").await?.is_some());
+
+        drop(backend);
+        let reopened = CacacheBackend::new(dir.clone(), Some(3 * 1024))?;
+        assert_eq!(reopened.stats().await?.entry_count, stats.entry_count);
+
+        let _ = std::fs::remove_dir_all(dir);
+        Ok(())
+    }
+}
@@ -1,23 +1,38 @@
-use std::{fmt::Debug, ops::Deref, sync::Arc, time::Duration};
+use std::{
+    fmt::Debug,
+    ops::Deref,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use async_trait::async_trait;
 use axum::http::Extensions;
-use http_cache_reqwest::{
-    Cache, CacheManager, CacheMode, HttpCache, HttpCacheOptions, MokaManager,
-};
+use http_cache_reqwest::{Cache, CacheMode, HttpCache, HttpCacheOptions};
 use leaky_bucket::RateLimiter;
 use reqwest::{self, ClientBuilder, Request, Response};
 use reqwest_cookie_store::{CookieStore, CookieStoreRwLock};
 use reqwest_middleware::{
     ClientBuilder as ClientWithMiddlewareBuilder, ClientWithMiddleware, Middleware, Next,
 };
-use reqwest_retry::{RetryTransientMiddleware, policies::ExponentialBackoff};
 use reqwest_tracing::TracingMiddleware;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use util::OptDynErr;
 
-use crate::{HttpClientError, client::proxy::HttpClientProxyConfig, get_random_ua};
+use crate::{
+    HttpClientError,
+    client::{
+        cache::{CacacheBackend, CacheBackend, HttpClientCacheStats, MokaCacheBackend},
+        metrics::{MetricsMiddleware, http_client_metrics},
+        proxy::{HttpClientProxyConfig, HttpClientProxyRouter, ProxyFailoverMiddleware},
+        rate_limit::{HostRateLimiterMiddleware, HttpClientHostRateLimitConfig},
+        redirect::HttpClientRedirectConfig,
+        retry::{HttpClientRetryConfig, RetryMiddleware},
+    },
+    get_random_ua,
+    replay::{HttpReplayConfig, HttpReplayMiddleware},
+};
 
 pub struct RateLimiterMiddleware {
     rate_limiter: RateLimiter,
@@ -31,7 +46,9 @@ impl Middleware for RateLimiterMiddleware {
         extensions: &'_ mut Extensions,
         next: Next<'_>,
     ) -> reqwest_middleware::Result<Response> {
+        let start = Instant::now();
         self.rate_limiter.acquire_one().await;
+        http_client_metrics().observe_rate_limit_wait(req.url(), "global", start.elapsed());
         next.run(req, extensions).await
     }
 }
@@ -39,7 +56,15 @@ impl Middleware for RateLimiterMiddleware {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum HttpClientCacheBackendConfig {
-    Moka { cache_size: u64 },
+    Moka {
+        cache_size: u64,
+    },
+    Cacache {
+        path: PathBuf,
+        /// Max total size of cached bodies in bytes, unlimited if not set
+        #[serde(default)]
+        max_size: Option<u64>,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -58,48 +83,24 @@ impl Default for HttpClientCachePresetConfig {
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct HttpClientConfig {
     pub exponential_backoff_max_retries: Option<u32>,
+    /// Takes precedence over `exponential_backoff_max_retries` when set
+    pub retry_policy: Option<HttpClientRetryConfig>,
     pub leaky_bucket_max_tokens: Option<u32>,
     pub leaky_bucket_initial_tokens: Option<u32>,
     pub leaky_bucket_refill_tokens: Option<u32>,
     #[serde_as(as = "Option<serde_with::DurationMilliSeconds>")]
     pub leaky_bucket_refill_interval: Option<Duration>,
+    /// Per-host limits applied on top of the global leaky bucket, the first
+    /// matching rule wins
+    pub host_rate_limits: Option<Vec<HttpClientHostRateLimitConfig>>,
     pub user_agent: Option<String>,
     pub cache_backend: Option<HttpClientCacheBackendConfig>,
     pub cache_preset: Option<HttpClientCachePresetConfig>,
     pub proxy: Option<HttpClientProxyConfig>,
-}
-
-pub(crate) struct CacheBackend(Box<dyn CacheManager>);
-
-impl CacheBackend {
-    pub(crate) fn new<T: CacheManager>(backend: T) -> Self {
-        Self(Box::new(backend))
-    }
-}
-
-#[async_trait::async_trait]
-impl CacheManager for CacheBackend {
-    async fn get(
-        &self,
-        cache_key: &str,
-    ) -> http_cache::Result<Option<(http_cache::HttpResponse, http_cache_semantics::CachePolicy)>>
-    {
-        self.0.get(cache_key).await
-    }
-
-    /// Attempts to cache a response and related policy.
-    async fn put(
-        &self,
-        cache_key: String,
-        res: http_cache::HttpResponse,
-        policy: http_cache_semantics::CachePolicy,
-    ) -> http_cache::Result<http_cache::HttpResponse> {
-        self.0.put(cache_key, res, policy).await
-    }
-    /// Attempts to remove a record from cache.
-    async fn delete(&self, cache_key: &str) -> http_cache::Result<()> {
-        self.0.delete(cache_key).await
-    }
+    /// Defaults to following a limited number of redirects
+    pub redirect_policy: Option<HttpClientRedirectConfig>,
+    /// Record responses to or replay them from disk, mostly for tests
+    pub replay: Option<HttpReplayConfig>,
 }
 
 pub trait HttpClientTrait: Deref<Target = ClientWithMiddleware> + Debug {}
@@ -109,6 +110,8 @@ pub struct HttpClientFork {
     pub middleware_stack: Vec<Arc<dyn Middleware>>,
     pub config: HttpClientConfig,
     pub cookie_store: Option<Arc<CookieStoreRwLock>>,
+    pub(crate) cache_backend: Option<CacheBackend>,
+    pub(crate) proxy_router: Option<Arc<HttpClientProxyRouter>>,
 }
 
 impl HttpClientFork {
@@ -137,6 +140,8 @@ pub struct HttpClient {
     pub cookie_store: Option<Arc<CookieStoreRwLock>>,
     client: ClientWithMiddleware,
     middleware_stack: Vec<Arc<dyn Middleware>>,
+    cache_backend: Option<CacheBackend>,
+    proxy_router: Option<Arc<HttpClientProxyRouter>>,
     pub config: HttpClientConfig,
 }
 
@@ -172,28 +177,33 @@ impl HttpClient {
                 .unwrap_or_else(|| get_random_ua()),
         );
 
-        if let Some(proxy) = config.proxy.as_ref() {
-            let accept_invalid_certs = proxy
-                .accept_invalid_certs
-                .as_ref()
-                .map(|b| *b)
-                .unwrap_or_default();
-            let proxy = proxy.clone().into_proxy()?;
-            if let Some(proxy) = proxy {
+        let proxy_router = if let Some(proxy) = config.proxy.as_ref() {
+            HttpClientProxyRouter::from_config(proxy)?
+        } else {
+            None
+        };
+
+        if let Some(proxy_router) = proxy_router.as_ref() {
+            for proxy in proxy_router.proxies()? {
                 reqwest_client_builder = reqwest_client_builder.proxy(proxy);
-                if accept_invalid_certs {
-                    reqwest_client_builder =
-                        reqwest_client_builder.danger_accept_invalid_certs(true);
-                }
+            }
+            if proxy_router.accept_invalid_certs() {
+                reqwest_client_builder = reqwest_client_builder.danger_accept_invalid_certs(true);
             }
         }
 
         #[cfg(not(target_arch = "wasm32"))]
-        let reqwest_client_builder =
-            reqwest_client_builder.redirect(reqwest::redirect::Policy::none());
+        let reqwest_client_builder = reqwest_client_builder.redirect(
+            config
+                .redirect_policy
+                .clone()
+                .unwrap_or_default()
+                .into_policy(),
+        );
 
         let reqwest_client = reqwest_client_builder.build()?;
         let mut reqwest_with_middleware_builder = ClientWithMiddlewareBuilder::new(reqwest_client);
+        let mut cache_backend = None;
 
         {
             let tracing_middleware = Arc::new(TracingMiddleware::default());
@@ -205,16 +215,28 @@ impl HttpClient {
         }
 
         {
-            if let Some(ref x) = config.exponential_backoff_max_retries {
-                let retry_policy = ExponentialBackoff::builder().build_with_max_retries(*x);
+            let metrics_middleware = Arc::new(MetricsMiddleware::default());
 
-                let retry_transient_middleware =
-                    Arc::new(RetryTransientMiddleware::new_with_policy(retry_policy));
+            middleware_stack.push(metrics_middleware.clone());
 
-                middleware_stack.push(retry_transient_middleware.clone());
+            reqwest_with_middleware_builder =
+                reqwest_with_middleware_builder.with_arc(metrics_middleware)
+        }
+
+        {
+            let retry_config = config.retry_policy.clone().or_else(|| {
+                config
+                    .exponential_backoff_max_retries
+                    .map(HttpClientRetryConfig::with_max_retries)
+            });
+
+            if let Some(retry_config) = retry_config {
+                let retry_middleware = Arc::new(RetryMiddleware::new(retry_config));
+
+                middleware_stack.push(retry_middleware.clone());
 
                 reqwest_with_middleware_builder =
-                    reqwest_with_middleware_builder.with_arc(retry_transient_middleware);
+                    reqwest_with_middleware_builder.with_arc(retry_middleware);
             }
         }
 
@@ -256,22 +278,25 @@ impl HttpClient {
             if let (None, None) = (config.cache_backend.as_ref(), config.cache_preset.as_ref()) {
             } else {
                 let cache_preset = config.cache_preset.as_ref().cloned().unwrap_or_default();
-                let cache_backend = config
-                    .cache_backend
-                    .as_ref()
-                    .map(|b| match b {
-                        HttpClientCacheBackendConfig::Moka { cache_size } => {
-                            CacheBackend::new(MokaManager {
-                                cache: Arc::new(moka::future::Cache::new(*cache_size)),
-                            })
-                        }
-                    })
-                    .unwrap_or_else(|| CacheBackend::new(MokaManager::default()));
+                let backend = match config.cache_backend.as_ref() {
+                    Some(HttpClientCacheBackendConfig::Moka { cache_size }) => {
+                        CacheBackend::new(MokaCacheBackend::new(*cache_size))
+                    }
+                    Some(HttpClientCacheBackendConfig::Cacache { path, max_size }) => {
+                        CacheBackend::new(
+                            CacacheBackend::new(path.clone(), *max_size)
+                                .map_err(|source| HttpClientError::CacheError { source })?,
+                        )
+                    }
+                    None => CacheBackend::new(MokaCacheBackend::default()),
+                };
+
+                cache_backend = Some(backend.clone());
 
                 let http_cache = match cache_preset {
                     HttpClientCachePresetConfig::RFC7234 => HttpCache {
                         mode: CacheMode::Default,
-                        manager: cache_backend,
+                        manager: backend,
                         options: HttpCacheOptions::default(),
                     },
                 };
@@ -285,11 +310,50 @@ impl HttpClient {
             }
         }
 
+        {
+            if let Some(replay) = config.replay.as_ref() {
+                let replay_middleware = Arc::new(HttpReplayMiddleware::new(replay.clone()));
+
+                middleware_stack.push(replay_middleware.clone());
+
+                reqwest_with_middleware_builder =
+                    reqwest_with_middleware_builder.with_arc(replay_middleware);
+            }
+        }
+
+        {
+            if let Some(host_rate_limits) = config.host_rate_limits.as_ref()
+                && !host_rate_limits.is_empty()
+            {
+                let host_rate_limiter_middleware =
+                    Arc::new(HostRateLimiterMiddleware::new(host_rate_limits.clone()));
+
+                middleware_stack.push(host_rate_limiter_middleware.clone());
+
+                reqwest_with_middleware_builder =
+                    reqwest_with_middleware_builder.with_arc(host_rate_limiter_middleware);
+            }
+        }
+
+        {
+            if let Some(proxy_router) = proxy_router.as_ref() {
+                let proxy_failover_middleware =
+                    Arc::new(ProxyFailoverMiddleware::new(proxy_router.clone()));
+
+                middleware_stack.push(proxy_failover_middleware.clone());
+
+                reqwest_with_middleware_builder =
+                    reqwest_with_middleware_builder.with_arc(proxy_failover_middleware);
+            }
+        }
+
         let reqwest_with_middleware = reqwest_with_middleware_builder.build();
 
         Ok(Self {
             client: reqwest_with_middleware,
             middleware_stack,
+            cache_backend,
+            proxy_router,
             config,
             cookie_store: None,
         })
@@ -303,31 +367,31 @@ impl HttpClient {
                 .unwrap_or_else(|| get_random_ua()),
         );
 
-        if let Some(proxy) = self.config.proxy.as_ref() {
-            let accept_invalid_certs = proxy
-                .accept_invalid_certs
-                .as_ref()
-                .map(|b| *b)
-                .unwrap_or_default();
-            let proxy = proxy.clone().into_proxy().unwrap_or_default();
-            if let Some(proxy) = proxy {
+        if let Some(proxy_router) = self.proxy_router.as_ref() {
+            for proxy in proxy_router.proxies().unwrap_or_default() {
                 reqwest_client_builder = reqwest_client_builder.proxy(proxy);
-                if accept_invalid_certs {
-                    reqwest_client_builder =
-                        reqwest_client_builder.danger_accept_invalid_certs(true);
-                }
+            }
+            if proxy_router.accept_invalid_certs() {
+                reqwest_client_builder = reqwest_client_builder.danger_accept_invalid_certs(true);
             }
         }
 
         #[cfg(not(target_arch = "wasm32"))]
-        let reqwest_client_builder =
-            reqwest_client_builder.redirect(reqwest::redirect::Policy::none());
+        let reqwest_client_builder = reqwest_client_builder.redirect(
+            self.config
+                .redirect_policy
+                .clone()
+                .unwrap_or_default()
+                .into_policy(),
+        );
 
         HttpClientFork {
             client_builder: reqwest_client_builder,
             middleware_stack: self.middleware_stack.clone(),
             config: self.config.clone(),
             cookie_store: self.cookie_store.clone(),
+            cache_backend: self.cache_backend.clone(),
+            proxy_router: self.proxy_router.clone(),
         }
     }
 
@@ -337,6 +401,8 @@ impl HttpClient {
             middleware_stack,
             config,
             cookie_store,
+            cache_backend,
+            proxy_router,
         } = fork;
         let reqwest_client = client_builder.build()?;
         let mut reqwest_with_middleware_builder = ClientWithMiddlewareBuilder::new(reqwest_client);
@@ -350,11 +416,25 @@ impl HttpClient {
         Ok(Self {
             client: reqwest_with_middleware,
             middleware_stack,
+            cache_backend,
+            proxy_router,
             config,
             cookie_store,
         })
     }
 
+    pub async fn cache_stats(&self) -> Result<Option<HttpClientCacheStats>, HttpClientError> {
+        if let Some(cache_backend) = self.cache_backend.as_ref() {
+            let stats = cache_backend
+                .stats()
+                .await
+                .map_err(|source| HttpClientError::CacheError { source })?;
+            Ok(Some(stats))
+        } else {
+            Ok(None)
+        }
+    }
+
     pub fn save_cookie_store_to_json(&self) -> Result<Option<String>, HttpClientError> {
         if let Some(cookie_store) = self.cookie_store.as_ref() {
             let json = {
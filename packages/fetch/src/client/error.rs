@@ -16,6 +16,15 @@ pub enum HttpClientError {
     SaveCookiesError { message: String, source: OptDynErr },
     #[snafu(display("Failed to parse fetch client proxy: {source}"))]
     ProxyParseError { source: reqwest::Error },
+    #[snafu(display("Failed to parse fetch client proxy server {server}: {source}"))]
+    ProxyServerParseError {
+        server: String,
+        source: url::ParseError,
+    },
+    #[snafu(display("Fetch client proxy rule for {hosts:?} is not direct but has no servers"))]
+    ProxyRuleWithoutServers { hosts: Vec<String> },
     #[snafu(display("Failed to parse fetch client proxy auth header"))]
     ProxyAuthHeaderParseError,
+    #[snafu(display("Failed to access http client cache: {source}"))]
+    CacheError { source: http_cache::BoxError },
 }
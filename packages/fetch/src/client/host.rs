@@ -0,0 +1,37 @@
+/// Matches a host pattern against a request host. Patterns are an exact host
+/// (`mikanani.me`), a wildcard that also matches the bare domain
+/// (`*.mikanani.me` or `.mikanani.me`) or `*` for any host, optionally
+/// followed by a port (`localhost:5001`).
+pub(crate) fn match_host_pattern(pattern: &str, host: &str, port: Option<u16>) -> bool {
+    let (pattern, pattern_port) = match pattern.rsplit_once(':') {
+        Some((host_pattern, pattern_port)) => match pattern_port.parse::<u16>() {
+            Ok(pattern_port) => (host_pattern, Some(pattern_port)),
+            Err(_) => (pattern, None),
+        },
+        None => (pattern, None),
+    };
+
+    if pattern_port.is_some() && pattern_port != port {
+        return false;
+    }
+
+    if pattern == "*" {
+        return true;
+    }
+
+    if let Some(domain) = pattern
+        .strip_prefix("*.")
+        .or_else(|| pattern.strip_prefix('.'))
+    {
+        host.eq_ignore_ascii_case(domain)
+            || host
+                .len()
+                .checked_sub(domain.len() + 1)
+                .and_then(|idx| host.get(idx..))
+                .is_some_and(|suffix| {
+                    suffix.starts_with('.') && suffix[1..].eq_ignore_ascii_case(domain)
+                })
+    } else {
+        host.eq_ignore_ascii_case(pattern)
+    }
+}
@@ -0,0 +1,224 @@
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use axum::http::Extensions;
+use lazy_static::lazy_static;
+use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry};
+use reqwest::{Request, Response, Url};
+use reqwest_middleware::{Middleware, Next};
+
+const CACHE_STATUS_HEADER: &str = "x-cache";
+
+/// Outbound request metrics shared by every [`crate::HttpClient`], labelled
+/// by request host.
+#[derive(Clone)]
+pub struct HttpClientMetrics {
+    /// Labels: `host`, `method`, `status_class` (`2xx`... or `error`)
+    pub requests_total: IntCounterVec,
+    /// Labels: `host`, including retries and rate limiter waits
+    pub request_duration_seconds: HistogramVec,
+    /// Labels: `host`, `result` (`hit` or `miss`)
+    pub cache_lookups_total: IntCounterVec,
+    /// Labels: `host`
+    pub retries_total: IntCounterVec,
+    /// Labels: `host`, `limiter` (`global` or `host`)
+    pub rate_limit_wait_seconds: HistogramVec,
+}
+
+impl HttpClientMetrics {
+    pub fn new() -> prometheus::Result<Self> {
+        Ok(Self {
+            requests_total: IntCounterVec::new(
+                Opts::new(
+                    "fetch_http_client_requests_total",
+                    "Outbound http requests by host, method and status class",
+                ),
+                &["host", "method", "status_class"],
+            )?,
+            request_duration_seconds: HistogramVec::new(
+                HistogramOpts::new(
+                    "fetch_http_client_request_duration_seconds",
+                    "Outbound http request latency by host",
+                ),
+                &["host"],
+            )?,
+            cache_lookups_total: IntCounterVec::new(
+                Opts::new(
+                    "fetch_http_client_cache_lookups_total",
+                    "Outbound http cache lookups by host and result",
+                ),
+                &["host", "result"],
+            )?,
+            retries_total: IntCounterVec::new(
+                Opts::new(
+                    "fetch_http_client_retries_total",
+                    "Outbound http request retries by host",
+                ),
+                &["host"],
+            )?,
+            rate_limit_wait_seconds: HistogramVec::new(
+                HistogramOpts::new(
+                    "fetch_http_client_rate_limit_wait_seconds",
+                    "Time outbound http requests spent waiting for a rate limiter",
+                )
+                .buckets(vec![
+                    0.001, 0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+                ]),
+                &["host", "limiter"],
+            )?,
+        })
+    }
+
+    pub fn register(&self, registry: &Registry) -> prometheus::Result<()> {
+        registry.register(Box::new(self.requests_total.clone()))?;
+        registry.register(Box::new(self.request_duration_seconds.clone()))?;
+        registry.register(Box::new(self.cache_lookups_total.clone()))?;
+        registry.register(Box::new(self.retries_total.clone()))?;
+        registry.register(Box::new(self.rate_limit_wait_seconds.clone()))?;
+        Ok(())
+    }
+
+    pub(crate) fn observe_rate_limit_wait(&self, url: &Url, limiter: &str, wait: Duration) {
+        self.rate_limit_wait_seconds
+            .with_label_values(&[host_label(url), limiter])
+            .observe(wait.as_secs_f64());
+    }
+
+    pub(crate) fn inc_retries(&self, url: &Url) {
+        self.retries_total
+            .with_label_values(&[host_label(url)])
+            .inc();
+    }
+}
+
+lazy_static! {
+    static ref HTTP_CLIENT_METRICS: HttpClientMetrics = {
+        let metrics = HttpClientMetrics::new().expect("Failed to create http client metrics");
+        metrics
+            .register(prometheus::default_registry())
+            .expect("Failed to register http client metrics");
+        metrics
+    };
+}
+
+/// Metrics registered in the prometheus default registry, exported by
+/// `prometheus::gather()`.
+pub fn http_client_metrics() -> &'static HttpClientMetrics {
+    &HTTP_CLIENT_METRICS
+}
+
+pub(crate) fn host_label(url: &Url) -> &str {
+    url.host_str().unwrap_or("unknown")
+}
+
+fn status_class(result: &reqwest_middleware::Result<Response>) -> &'static str {
+    match result {
+        Ok(response) => match response.status().as_u16() / 100 {
+            1 => "1xx",
+            2 => "2xx",
+            3 => "3xx",
+            4 => "4xx",
+            5 => "5xx",
+            _ => "other",
+        },
+        Err(_) => "error",
+    }
+}
+
+fn cache_result(response: &Response) -> Option<&'static str> {
+    let value = response.headers().get(CACHE_STATUS_HEADER)?.to_str().ok()?;
+    if value.eq_ignore_ascii_case("hit") {
+        Some("hit")
+    } else if value.eq_ignore_ascii_case("miss") {
+        Some("miss")
+    } else {
+        None
+    }
+}
+
+/// Records request counts and latency, and cache results from the status
+/// header set by the http cache layer below it.
+pub struct MetricsMiddleware {
+    metrics: &'static HttpClientMetrics,
+}
+
+impl Default for MetricsMiddleware {
+    fn default() -> Self {
+        Self {
+            metrics: http_client_metrics(),
+        }
+    }
+}
+
+#[async_trait]
+impl Middleware for MetricsMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &'_ mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let url = req.url().clone();
+        let method = req.method().clone();
+        let start = Instant::now();
+
+        let result = next.run(req, extensions).await;
+
+        let host = host_label(&url);
+        self.metrics
+            .request_duration_seconds
+            .with_label_values(&[host])
+            .observe(start.elapsed().as_secs_f64());
+        self.metrics
+            .requests_total
+            .with_label_values(&[host, method.as_str(), status_class(&result)])
+            .inc();
+        if let Some(cache_result) = result.as_ref().ok().and_then(cache_result) {
+            self.metrics
+                .cache_lookups_total
+                .with_label_values(&[host, cache_result])
+                .inc();
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{self, HeaderValue};
+
+    use super::*;
+
+    fn response(status: u16, cache_status: Option<&'static str>) -> Response {
+        let mut builder = http::Response::builder().status(status);
+        if let Some(cache_status) = cache_status {
+            builder = builder.header(CACHE_STATUS_HEADER, HeaderValue::from_static(cache_status));
+        }
+        Response::from(builder.body(Vec::<u8>::new()).unwrap())
+    }
+
+    #[test]
+    fn test_http_client_metrics_labels() {
+        assert_eq!(status_class(&Ok(response(204, None))), "2xx");
+        assert_eq!(status_class(&Ok(response(503, None))), "5xx");
+
+        assert_eq!(cache_result(&response(200, Some("HIT"))), Some("hit"));
+        assert_eq!(cache_result(&response(200, Some("MISS"))), Some("miss"));
+        assert_eq!(cache_result(&response(200, None)), None);
+
+        let registry = Registry::new();
+        let metrics = HttpClientMetrics::new().unwrap();
+        metrics.register(&registry).unwrap();
+        metrics.inc_retries(&Url::parse("https://mikanani.me/Home").unwrap());
+
+        assert_eq!(registry.gather().len(), 5);
+        assert_eq!(
+            metrics
+                .retries_total
+                .with_label_values(&["mikanani.me"])
+                .get(),
+            1
+        );
+    }
+}
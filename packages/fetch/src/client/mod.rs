@@ -1,11 +1,22 @@
+mod cache;
 mod core;
 mod error;
+mod host;
+mod metrics;
 mod proxy;
+mod rate_limit;
+mod redirect;
+mod retry;
 
 pub use core::{
     HttpClient, HttpClientCacheBackendConfig, HttpClientCachePresetConfig, HttpClientConfig,
     HttpClientTrait,
 };
 
+pub use cache::HttpClientCacheStats;
 pub use error::HttpClientError;
-pub use proxy::HttpClientProxyConfig;
+pub use metrics::{HttpClientMetrics, MetricsMiddleware, http_client_metrics};
+pub use proxy::{HttpClientProxyConfig, HttpClientProxyRuleConfig};
+pub use rate_limit::{HostRateLimiterMiddleware, HttpClientHostRateLimitConfig};
+pub use redirect::HttpClientRedirectConfig;
+pub use retry::{HttpClientRetryConfig, HttpClientRetryJitter, RetryMiddleware};
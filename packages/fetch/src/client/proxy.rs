@@ -1,9 +1,17 @@
-use axum::http::{HeaderMap, HeaderValue};
-use reqwest::{NoProxy, Proxy};
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use async_trait::async_trait;
+use axum::http::{Extensions, HeaderMap, HeaderValue};
+use reqwest::{NoProxy, Proxy, Request, Response};
+use reqwest_middleware::{Middleware, Next};
 use serde::{Deserialize, Serialize};
 use serde_with::{NoneAsEmptyString, serde_as};
+use url::Url;
 
-use crate::HttpClientError;
+use crate::{HttpClientError, client::host::match_host_pattern};
 
 #[serde_as]
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +28,29 @@ pub struct HttpClientProxyConfig {
     #[serde_as(as = "NoneAsEmptyString")]
     pub no_proxy: Option<String>,
     pub accept_invalid_certs: Option<bool>,
+    /// Ordered routing rules, the first rule matching the request host wins.
+    /// Requests matching no rule fall back to `server`, or go direct without
+    /// one.
+    #[serde(default)]
+    pub rules: Vec<HttpClientProxyRuleConfig>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpClientProxyRuleConfig {
+    /// Host patterns, e.g. `mikanani.me`, `*.mikanani.me` or `*`
+    pub hosts: Vec<String>,
+    /// Connect to matching hosts without any proxy
+    #[serde(default)]
+    pub direct: bool,
+    /// Proxies tried in order, a connect error switches to the next one
+    #[serde(default)]
+    pub servers: Vec<String>,
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub auth_header: Option<String>,
+    #[serde(default, with = "http_serde::option::header_map")]
+    pub headers: Option<HeaderMap>,
 }
 
 impl HttpClientProxyConfig {
@@ -56,3 +87,353 @@ impl TryFrom<HttpClientProxyConfig> for Option<Proxy> {
         value.into_proxy()
     }
 }
+
+struct ProxyRoute {
+    hosts: Vec<String>,
+    servers: Vec<Url>,
+    active: AtomicUsize,
+    auth_header: Option<HeaderValue>,
+    headers: Option<HeaderMap>,
+}
+
+impl ProxyRoute {
+    fn from_config(config: &HttpClientProxyRuleConfig) -> Result<Self, HttpClientError> {
+        let servers = if config.direct {
+            vec![]
+        } else if config.servers.is_empty() {
+            return Err(HttpClientError::ProxyRuleWithoutServers {
+                hosts: config.hosts.clone(),
+            });
+        } else {
+            config
+                .servers
+                .iter()
+                .map(|server| {
+                    Url::parse(server).map_err(|source| HttpClientError::ProxyServerParseError {
+                        server: server.clone(),
+                        source,
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        let auth_header = config
+            .auth_header
+            .as_deref()
+            .map(HeaderValue::from_str)
+            .transpose()
+            .map_err(|_| HttpClientError::ProxyAuthHeaderParseError)?;
+
+        Ok(Self {
+            hosts: config.hosts.clone(),
+            servers,
+            active: AtomicUsize::new(0),
+            auth_header,
+            headers: config.headers.clone(),
+        })
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let port = url.port_or_known_default();
+        self.hosts
+            .iter()
+            .any(|pattern| match_host_pattern(pattern, host, port))
+    }
+
+    fn active_server(&self) -> Option<&Url> {
+        if self.servers.is_empty() {
+            return None;
+        }
+        self.servers
+            .get(self.active.load(Ordering::Relaxed) % self.servers.len())
+    }
+
+    fn failover(&self, from: usize) -> bool {
+        let len = self.servers.len();
+        len > 1
+            && self
+                .active
+                .compare_exchange(from, (from + 1) % len, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+    }
+}
+
+/// Routes requests to the proxy of the first matching rule. Shared by an
+/// [`crate::HttpClient`] and its forks so that failover state survives
+/// forking.
+pub(crate) struct HttpClientProxyRouter {
+    routes: Vec<ProxyRoute>,
+    fallback: HttpClientProxyConfig,
+}
+
+impl HttpClientProxyRouter {
+    pub(crate) fn from_config(
+        config: &HttpClientProxyConfig,
+    ) -> Result<Option<Arc<Self>>, HttpClientError> {
+        if config.rules.is_empty() && config.server.is_none() {
+            return Ok(None);
+        }
+
+        let routes = config
+            .rules
+            .iter()
+            .map(ProxyRoute::from_config)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Some(Arc::new(Self {
+            routes,
+            fallback: HttpClientProxyConfig {
+                rules: vec![],
+                ..config.clone()
+            },
+        })))
+    }
+
+    fn route_index(&self, url: &Url) -> Option<usize> {
+        self.routes.iter().position(|route| route.matches(url))
+    }
+
+    pub(crate) fn accept_invalid_certs(&self) -> bool {
+        self.fallback.accept_invalid_certs.unwrap_or_default()
+    }
+
+    pub(crate) fn proxies(self: &Arc<Self>) -> Result<Vec<Proxy>, HttpClientError> {
+        if self.routes.is_empty() {
+            return Ok(self.fallback.clone().into_proxy()?.into_iter().collect());
+        }
+
+        let mut proxies = vec![];
+
+        for (idx, route) in self.routes.iter().enumerate() {
+            if route.servers.is_empty() {
+                continue;
+            }
+
+            let router = self.clone();
+            let mut proxy = Proxy::custom(move |url| {
+                if router.route_index(url) == Some(idx) {
+                    router.routes[idx].active_server().cloned()
+                } else {
+                    None
+                }
+            });
+            if let Some(auth_header) = route.auth_header.clone() {
+                proxy = proxy.custom_http_auth(auth_header);
+            }
+            if let Some(headers) = route.headers.clone() {
+                proxy = proxy.headers(headers);
+            }
+            proxies.push(proxy);
+        }
+
+        if let Some(server) = self.fallback.server.as_ref() {
+            let server =
+                Url::parse(server).map_err(|source| HttpClientError::ProxyServerParseError {
+                    server: server.clone(),
+                    source,
+                })?;
+            let router = self.clone();
+            let mut proxy = Proxy::custom(move |url| {
+                if router.route_index(url).is_none() {
+                    Some(server.clone())
+                } else {
+                    None
+                }
+            });
+            if let Some(auth_header) = self.fallback.auth_header.as_deref() {
+                let auth_header = HeaderValue::from_str(auth_header)
+                    .map_err(|_| HttpClientError::ProxyAuthHeaderParseError)?;
+                proxy = proxy.custom_http_auth(auth_header);
+            }
+            if let Some(headers) = self.fallback.headers.clone() {
+                proxy = proxy.headers(headers);
+            }
+            if let Some(no_proxy) = self.fallback.no_proxy.as_deref() {
+                proxy = proxy.no_proxy(NoProxy::from_string(no_proxy));
+            }
+            proxies.push(proxy);
+        }
+
+        Ok(proxies)
+    }
+}
+
+/// Retries requests through the next proxy of their rule when connecting to
+/// the active one fails.
+pub(crate) struct ProxyFailoverMiddleware {
+    router: Arc<HttpClientProxyRouter>,
+}
+
+impl ProxyFailoverMiddleware {
+    pub(crate) fn new(router: Arc<HttpClientProxyRouter>) -> Self {
+        Self { router }
+    }
+}
+
+#[async_trait]
+impl Middleware for ProxyFailoverMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &'_ mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let Some(route) = self
+            .router
+            .route_index(req.url())
+            .map(|idx| &self.router.routes[idx])
+            .filter(|route| route.servers.len() > 1)
+        else {
+            return next.run(req, extensions).await;
+        };
+
+        let mut attempts = 1;
+        loop {
+            let Some(duplicate_request) = req.try_clone() else {
+                return next.run(req, extensions).await;
+            };
+            let active = route.active.load(Ordering::Relaxed);
+
+            let result = next.clone().run(duplicate_request, extensions).await;
+
+            match &result {
+                Err(reqwest_middleware::Error::Reqwest(err))
+                    if err.is_connect() && attempts < route.servers.len() =>
+                {
+                    route.failover(active);
+                    tracing::warn!(
+                        url = %req.url(),
+                        proxy = ?route.active_server().map(|u| u.as_str()),
+                        error = %err,
+                        "Failed to connect through proxy, switching to the next one"
+                    );
+                    attempts += 1;
+                }
+                _ => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(hosts: &[&str], direct: bool, servers: &[&str]) -> HttpClientProxyRuleConfig {
+        HttpClientProxyRuleConfig {
+            hosts: hosts.iter().map(|h| h.to_string()).collect(),
+            direct,
+            servers: servers.iter().map(|s| s.to_string()).collect(),
+            auth_header: None,
+            headers: None,
+        }
+    }
+
+    #[test]
+    fn test_proxy_router_routes_and_failover() -> Result<(), HttpClientError> {
+        let router = HttpClientProxyRouter::from_config(&HttpClientProxyConfig {
+            server: Some(String::from("http://fallback:8080")),
+            auth_header: None,
+            headers: None,
+            no_proxy: None,
+            accept_invalid_certs: None,
+            rules: vec![
+                rule(&["localhost", "127.0.0.1"], true, &[]),
+                rule(
+                    &["*.mikanani.me"],
+                    false,
+                    &["http://proxy-a:7890", "socks5://proxy-b:1080"],
+                ),
+            ],
+        })?
+        .unwrap();
+
+        let mikan = Url::parse("https://mikanani.me/Home").unwrap();
+        let local = Url::parse("http://localhost:8080/api").unwrap();
+        let other = Url::parse("https://example.com").unwrap();
+
+        assert_eq!(router.route_index(&local), Some(0));
+        assert_eq!(router.route_index(&mikan), Some(1));
+        assert_eq!(router.route_index(&other), None);
+
+        let route = &router.routes[1];
+        assert_eq!(
+            route.active_server().map(|u| u.as_str()),
+            Some("http://proxy-a:7890/")
+        );
+        assert!(route.failover(0));
+        assert!(!route.failover(0));
+        assert_eq!(
+            route.active_server().map(|u| u.as_str()),
+            Some("socks5://proxy-b:1080")
+        );
+
+        assert_eq!(router.proxies()?.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_proxy_router_rejects_rule_without_servers() {
+        let result = HttpClientProxyRouter::from_config(&HttpClientProxyConfig {
+            server: None,
+            auth_header: None,
+            headers: None,
+            no_proxy: None,
+            accept_invalid_certs: None,
+            rules: vec![rule(&["*.mikanani.me"], false, &[])],
+        });
+
+        assert!(matches!(
+            result,
+            Err(HttpClientError::ProxyRuleWithoutServers { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_proxy_failover_on_connect_error() -> Result<(), Box<dyn std::error::Error>> {
+        // nothing listens on the port of a dropped listener, connecting fails
+        let unreachable_proxy = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+            format!("http://{}", listener.local_addr()?)
+        };
+        // mockito serves the absolute form requests sent to a http proxy
+        let mut proxy = mockito::Server::new_async().await;
+        let proxied = proxy
+            .mock("GET", "/Home")
+            .match_header("host", "mikanani.me")
+            .with_body("proxied")
+            .expect(2)
+            .create_async()
+            .await;
+
+        let client = crate::HttpClient::from_config(crate::HttpClientConfig {
+            proxy: Some(HttpClientProxyConfig {
+                server: None,
+                auth_header: None,
+                headers: None,
+                no_proxy: None,
+                accept_invalid_certs: None,
+                rules: vec![rule(
+                    &["mikanani.me"],
+                    false,
+                    &[unreachable_proxy.as_str(), proxy.url().as_str()],
+                )],
+            }),
+            ..Default::default()
+        })?;
+
+        let response = client.get("http://mikanani.me/Home").send().await?;
+        assert_eq!(response.text().await?, "proxied");
+
+        // the working proxy stays active for later requests
+        let response = client.get("http://mikanani.me/Home").send().await?;
+        assert_eq!(response.text().await?, "proxied");
+        proxied.assert_async().await;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,265 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use axum::http::Extensions;
+use leaky_bucket::RateLimiter;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::client::{host::match_host_pattern, metrics::http_client_metrics};
+
+/// Rate limit and concurrency cap applied to requests whose host matches the
+/// `host` pattern, e.g. `mikanani.me`, `*.mikanani.me` or `localhost:5001`.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HttpClientHostRateLimitConfig {
+    pub host: String,
+    pub leaky_bucket_max_tokens: Option<u32>,
+    pub leaky_bucket_initial_tokens: Option<u32>,
+    pub leaky_bucket_refill_tokens: Option<u32>,
+    #[serde_as(as = "Option<serde_with::DurationMilliSeconds>")]
+    #[serde(default)]
+    pub leaky_bucket_refill_interval: Option<Duration>,
+    pub max_in_flight: Option<usize>,
+}
+
+impl HttpClientHostRateLimitConfig {
+    fn has_rate_limit(&self) -> bool {
+        self.leaky_bucket_max_tokens.is_some()
+            || self.leaky_bucket_initial_tokens.is_some()
+            || self.leaky_bucket_refill_tokens.is_some()
+            || self.leaky_bucket_refill_interval.is_some()
+    }
+
+    pub fn matches(&self, host: &str, port: Option<u16>) -> bool {
+        match_host_pattern(&self.host, host, port)
+    }
+}
+
+struct HostRateLimitRule {
+    config: HttpClientHostRateLimitConfig,
+    rate_limiter: Option<RateLimiter>,
+    semaphore: Option<Arc<Semaphore>>,
+}
+
+impl HostRateLimitRule {
+    fn new(config: HttpClientHostRateLimitConfig) -> Self {
+        let rate_limiter = if config.has_rate_limit() {
+            let mut rate_limiter_builder = RateLimiter::builder();
+
+            if let Some(ref x) = config.leaky_bucket_max_tokens {
+                rate_limiter_builder.max(*x as usize);
+            }
+            if let Some(ref x) = config.leaky_bucket_initial_tokens {
+                rate_limiter_builder.initial(*x as usize);
+            }
+            if let Some(ref x) = config.leaky_bucket_refill_tokens {
+                rate_limiter_builder.refill(*x as usize);
+            }
+            if let Some(ref x) = config.leaky_bucket_refill_interval {
+                rate_limiter_builder.interval(*x);
+            }
+
+            Some(rate_limiter_builder.build())
+        } else {
+            None
+        };
+
+        let semaphore = config
+            .max_in_flight
+            .map(|permits| Arc::new(Semaphore::new(permits.max(1))));
+
+        Self {
+            config,
+            rate_limiter,
+            semaphore,
+        }
+    }
+}
+
+/// `max_in_flight` permit kept in the response extensions, so it is only
+/// released once the response is dropped after its body is read.
+#[derive(Clone)]
+struct HostRateLimitPermit {
+    _permit: Arc<OwnedSemaphorePermit>,
+}
+
+/// Applies the first matching [`HttpClientHostRateLimitConfig`] rule to each
+/// request, requests to other hosts pass through untouched. It sits below the
+/// cache so cache hits do not use up tokens or permits.
+pub struct HostRateLimiterMiddleware {
+    rules: Vec<HostRateLimitRule>,
+}
+
+impl HostRateLimiterMiddleware {
+    pub fn new(configs: Vec<HttpClientHostRateLimitConfig>) -> Self {
+        Self {
+            rules: configs.into_iter().map(HostRateLimitRule::new).collect(),
+        }
+    }
+
+    fn find_rule(&self, req: &Request) -> Option<&HostRateLimitRule> {
+        let url = req.url();
+        let host = url.host_str()?;
+        let port = url.port_or_known_default();
+        self.rules
+            .iter()
+            .find(|rule| rule.config.matches(host, port))
+    }
+}
+
+#[async_trait]
+impl Middleware for HostRateLimiterMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &'_ mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let Some(rule) = self.find_rule(&req) else {
+            return next.run(req, extensions).await;
+        };
+
+        let start = Instant::now();
+
+        let permit = if let Some(semaphore) = rule.semaphore.as_ref() {
+            Some(
+                semaphore
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .map_err(|err| reqwest_middleware::Error::Middleware(err.into()))?,
+            )
+        } else {
+            None
+        };
+
+        if let Some(rate_limiter) = rule.rate_limiter.as_ref() {
+            rate_limiter.acquire_one().await;
+        }
+
+        http_client_metrics().observe_rate_limit_wait(req.url(), "host", start.elapsed());
+
+        let mut response = next.run(req, extensions).await?;
+        if let Some(permit) = permit {
+            response.extensions_mut().insert(HostRateLimitPermit {
+                _permit: Arc::new(permit),
+            });
+        }
+
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(host: &str) -> HttpClientHostRateLimitConfig {
+        HttpClientHostRateLimitConfig {
+            host: host.to_string(),
+            leaky_bucket_max_tokens: None,
+            leaky_bucket_initial_tokens: None,
+            leaky_bucket_refill_tokens: None,
+            leaky_bucket_refill_interval: None,
+            max_in_flight: None,
+        }
+    }
+
+    fn local_rule(server: &mockito::ServerGuard) -> HttpClientHostRateLimitConfig {
+        rule(&format!("127.0.0.1:{}", server.socket_address().port()))
+    }
+
+    fn build_client(rule: HttpClientHostRateLimitConfig) -> crate::HttpClient {
+        crate::HttpClient::from_config(crate::HttpClientConfig {
+            host_rate_limits: Some(vec![rule]),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_host_rate_limiter_throttles_requests() -> Result<(), Box<dyn std::error::Error>> {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/feed")
+            .with_body("ok")
+            .expect(3)
+            .create_async()
+            .await;
+
+        let client = build_client(HttpClientHostRateLimitConfig {
+            leaky_bucket_max_tokens: Some(1),
+            leaky_bucket_initial_tokens: Some(1),
+            leaky_bucket_refill_tokens: Some(1),
+            leaky_bucket_refill_interval: Some(Duration::from_millis(200)),
+            ..local_rule(&server)
+        });
+
+        let start = Instant::now();
+        for _ in 0..3 {
+            client
+                .get(format!("{}/feed", server.url()))
+                .send()
+                .await?
+                .text()
+                .await?;
+        }
+
+        assert!(start.elapsed() >= Duration::from_millis(400));
+        mock.assert_async().await;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_host_rate_limiter_holds_permit_until_body_dropped()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/torrent")
+            .with_body("ok")
+            .expect(2)
+            .create_async()
+            .await;
+
+        let client = build_client(HttpClientHostRateLimitConfig {
+            max_in_flight: Some(1),
+            ..local_rule(&server)
+        });
+        let url = format!("{}/torrent", server.url());
+
+        let first = client.get(&url).send().await?;
+
+        let second =
+            tokio::time::timeout(Duration::from_millis(200), client.get(&url).send()).await;
+        assert!(second.is_err(), "second request should wait for the permit");
+
+        drop(first);
+        let second =
+            tokio::time::timeout(Duration::from_secs(5), client.get(&url).send()).await??;
+        assert_eq!(second.text().await?, "ok");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_host_rate_limit_rule_matches() {
+        assert!(rule("mikanani.me").matches("mikanani.me", Some(443)));
+        assert!(rule("MIKANANI.me").matches("mikanani.me", Some(443)));
+        assert!(!rule("mikanani.me").matches("www.mikanani.me", Some(443)));
+
+        assert!(rule("*.mikanani.me").matches("mikanani.me", Some(443)));
+        assert!(rule("*.mikanani.me").matches("www.mikanani.me", Some(443)));
+        assert!(!rule("*.mikanani.me").matches("notmikanani.me", Some(443)));
+
+        assert!(rule("localhost:5001").matches("localhost", Some(5001)));
+        assert!(!rule("localhost:5001").matches("localhost", Some(5002)));
+    }
+}
@@ -0,0 +1,42 @@
+use reqwest::redirect::Policy;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum HttpClientRedirectConfig {
+    /// Never follow, redirect responses are returned to the caller
+    None,
+    /// Follow up to `max_hops` redirects
+    Limited { max_hops: usize },
+    /// Follow up to `max_hops` redirects as long as they stay on the origin of
+    /// the initial request, cross-origin redirects are returned to the caller
+    SameOrigin { max_hops: usize },
+}
+
+impl Default for HttpClientRedirectConfig {
+    fn default() -> Self {
+        Self::Limited { max_hops: 10 }
+    }
+}
+
+impl HttpClientRedirectConfig {
+    pub fn into_policy(self) -> Policy {
+        match self {
+            Self::None => Policy::none(),
+            Self::Limited { max_hops } => Policy::limited(max_hops),
+            Self::SameOrigin { max_hops } => Policy::custom(move |attempt| {
+                let previous = attempt.previous();
+                if previous.len() > max_hops {
+                    attempt.error("too many redirects")
+                } else if previous
+                    .first()
+                    .is_some_and(|initial| initial.origin() != attempt.url().origin())
+                {
+                    attempt.stop()
+                } else {
+                    attempt.follow()
+                }
+            }),
+        }
+    }
+}
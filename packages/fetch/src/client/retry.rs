@@ -0,0 +1,246 @@
+use std::time::{Duration, SystemTime};
+
+use async_trait::async_trait;
+use axum::http::{Extensions, HeaderMap, StatusCode, header::RETRY_AFTER};
+use chrono::{DateTime, Utc};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next};
+use reqwest_retry::{
+    Jitter, RetryDecision, RetryPolicy, Retryable, default_on_request_failure,
+    policies::ExponentialBackoff,
+};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+use crate::client::metrics::http_client_metrics;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HttpClientRetryJitter {
+    None,
+    #[default]
+    Full,
+    Bounded,
+}
+
+impl From<HttpClientRetryJitter> for Jitter {
+    fn from(value: HttpClientRetryJitter) -> Self {
+        match value {
+            HttpClientRetryJitter::None => Jitter::None,
+            HttpClientRetryJitter::Full => Jitter::Full,
+            HttpClientRetryJitter::Bounded => Jitter::Bounded,
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpClientRetryConfig {
+    pub max_retries: u32,
+    #[serde_as(as = "serde_with::DurationMilliSeconds")]
+    pub min_retry_interval: Duration,
+    #[serde_as(as = "serde_with::DurationMilliSeconds")]
+    pub max_retry_interval: Duration,
+    pub jitter: HttpClientRetryJitter,
+    /// Response statuses that are retried, transient connection errors are
+    /// always retried
+    pub retryable_statuses: Vec<u16>,
+    /// Upper-case method names that are retried, all methods if empty
+    pub retryable_methods: Vec<String>,
+    pub respect_retry_after: bool,
+    /// Responses asking to wait longer than this are returned as-is
+    #[serde_as(as = "serde_with::DurationMilliSeconds")]
+    pub max_retry_after: Duration,
+}
+
+impl Default for HttpClientRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            min_retry_interval: Duration::from_secs(1),
+            max_retry_interval: Duration::from_secs(30 * 60),
+            jitter: HttpClientRetryJitter::default(),
+            retryable_statuses: vec![408, 429, 500, 502, 503, 504],
+            retryable_methods: vec![],
+            respect_retry_after: true,
+            max_retry_after: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+impl HttpClientRetryConfig {
+    pub fn with_max_retries(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            ..Default::default()
+        }
+    }
+}
+
+/// Retries transient failures with exponential backoff, waiting for the
+/// server provided `Retry-After` instead when there is one.
+pub struct RetryMiddleware {
+    config: HttpClientRetryConfig,
+    policy: ExponentialBackoff,
+}
+
+impl RetryMiddleware {
+    pub fn new(config: HttpClientRetryConfig) -> Self {
+        let policy = ExponentialBackoff::builder()
+            .retry_bounds(config.min_retry_interval, config.max_retry_interval)
+            .jitter(config.jitter.into())
+            .build_with_max_retries(config.max_retries);
+
+        Self { config, policy }
+    }
+
+    fn is_retryable_method(&self, req: &Request) -> bool {
+        self.config.retryable_methods.is_empty()
+            || self
+                .config
+                .retryable_methods
+                .iter()
+                .any(|m| m.eq_ignore_ascii_case(req.method().as_str()))
+    }
+
+    fn is_retryable_status(&self, status: StatusCode) -> bool {
+        self.config.retryable_statuses.contains(&status.as_u16())
+    }
+}
+
+pub(crate) fn parse_retry_after(headers: &HeaderMap, now: SystemTime) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let at: SystemTime = DateTime::parse_from_rfc2822(value)
+        .ok()?
+        .with_timezone(&Utc)
+        .into();
+
+    Some(at.duration_since(now).unwrap_or_default())
+}
+
+#[async_trait]
+impl Middleware for RetryMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &'_ mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        if !self.is_retryable_method(&req) {
+            return next.run(req, extensions).await;
+        }
+
+        let start_time = SystemTime::now();
+        let mut n_past_retries = 0;
+
+        loop {
+            let Some(duplicate_request) = req.try_clone() else {
+                return next.run(req, extensions).await;
+            };
+
+            let result = next.clone().run(duplicate_request, extensions).await;
+
+            let retry_after = match &result {
+                Ok(response) if self.is_retryable_status(response.status()) => {
+                    let retry_after = if self.config.respect_retry_after {
+                        parse_retry_after(response.headers(), SystemTime::now())
+                    } else {
+                        None
+                    };
+                    if retry_after.is_some_and(|d| d > self.config.max_retry_after) {
+                        tracing::warn!(
+                            url = %req.url(),
+                            status = %response.status(),
+                            ?retry_after,
+                            "Retry-After exceeds the configured limit, giving up"
+                        );
+                        return result;
+                    }
+                    Some(retry_after)
+                }
+                Ok(_) => None,
+                Err(err) => match default_on_request_failure(err) {
+                    Some(Retryable::Transient) => Some(None),
+                    _ => None,
+                },
+            };
+
+            let Some(retry_after) = retry_after else {
+                return result;
+            };
+
+            let delay = match self.policy.should_retry(start_time, n_past_retries) {
+                RetryDecision::Retry { execute_after } => retry_after.unwrap_or_else(|| {
+                    execute_after
+                        .duration_since(SystemTime::now())
+                        .unwrap_or_default()
+                }),
+                RetryDecision::DoNotRetry => return result,
+            };
+
+            n_past_retries += 1;
+            http_client_metrics().inc_retries(req.url());
+
+            let span = tracing::Span::current();
+            span.record("http.request.resend_count", n_past_retries);
+            tracing::warn!(
+                url = %req.url(),
+                attempt = n_past_retries,
+                delay_ms = delay.as_millis() as u64,
+                status = result.as_ref().ok().map(|r| r.status().as_u16()),
+                error = result.as_ref().err().map(tracing::field::display),
+                "Retrying http request"
+            );
+
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now: SystemTime = DateTime::parse_from_rfc3339("2015-10-21T07:28:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+            .into();
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_retry_after(&headers, now), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("120"));
+        assert_eq!(
+            parse_retry_after(&headers, now),
+            Some(Duration::from_secs(120))
+        );
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:30:00 GMT"),
+        );
+        assert_eq!(
+            parse_retry_after(&headers, now),
+            Some(Duration::from_secs(120))
+        );
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:00:00 GMT"),
+        );
+        assert_eq!(parse_retry_after(&headers, now), Some(Duration::ZERO));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+        assert_eq!(parse_retry_after(&headers, now), None);
+    }
+}
@@ -7,4 +7,30 @@ pub enum FetchError {
     ReqwestError { source: reqwest::Error },
     #[snafu(transparent)]
     RequestMiddlewareError { source: reqwest_middleware::Error },
+    #[snafu(display("Response body of {size} bytes exceeds the limit of {max_size} bytes"))]
+    ContentTooLarge { max_size: u64, size: u64 },
+    #[snafu(display("Unexpected content type {content_type}, expected one of {expected:?}"))]
+    UnexpectedContentType {
+        content_type: String,
+        expected: Vec<String>,
+    },
+    #[snafu(display("Failed to resume download of {url}, server responded with {status}"))]
+    ResumeUnsupported { url: String, status: u16 },
+    #[snafu(display("Response is not a recognized image, starts with {prefix:?}"))]
+    UnrecognizedImage { prefix: String },
+    #[snafu(display("Unexpected image format {format:?}, expected one of {expected:?}"))]
+    UnexpectedImageFormat {
+        format: image::ImageFormat,
+        expected: Vec<image::ImageFormat>,
+    },
+    #[snafu(display("Image of format {format:?} is truncated"))]
+    TruncatedImage { format: image::ImageFormat },
+    #[snafu(display("Image of {width}x{height} exceeds the limit of {max_pixels} pixels"))]
+    ImageTooLarge {
+        width: u32,
+        height: u32,
+        max_pixels: u64,
+    },
+    #[snafu(display("Failed to decode image header: {source}"))]
+    ImageDecodeError { source: image::ImageError },
 }
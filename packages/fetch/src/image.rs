@@ -1,12 +1,422 @@
-use bytes::Bytes;
+use std::io::Cursor;
+
+use bytes::{Bytes, BytesMut};
+use futures::{Stream, TryStreamExt};
+pub use image::ImageFormat;
+use image::ImageReader;
 use reqwest::IntoUrl;
 
-use super::{bytes::fetch_bytes, client::HttpClientTrait};
+use super::{
+    client::HttpClientTrait,
+    stream::{FetchStreamOptions, fetch_stream},
+};
 use crate::FetchError;
 
+/// Bytes `image::guess_format` needs to tell the supported formats apart
+const IMAGE_MAGIC_SIZE: usize = 16;
+/// Leading bytes buffered while streaming until the dimensions can be read
+const IMAGE_HEADER_MAX_SIZE: usize = 256 * 1024;
+/// Trailing bytes kept while streaming to check the end-of-image marker
+const IMAGE_TAIL_SIZE: usize = 1024;
+
+#[derive(Debug, Clone)]
+pub struct FetchImageOptions {
+    pub max_bytes: Option<u64>,
+    /// Upper bound of `width * height`
+    pub max_pixels: Option<u64>,
+    /// Accepted formats, any format the `image` crate recognizes if empty
+    pub formats: Vec<ImageFormat>,
+}
+
+impl Default for FetchImageOptions {
+    fn default() -> Self {
+        Self {
+            max_bytes: Some(32 * 1024 * 1024),
+            max_pixels: Some(64 * 1024 * 1024),
+            formats: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageHeader {
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ImageHeader {
+    /// Reads the format and dimensions of a complete image.
+    pub fn from_bytes(bytes: &[u8], options: &FetchImageOptions) -> Result<Self, FetchError> {
+        Ok(Self::sniff(bytes, options, true)?
+            .expect("complete image header should be sniffed or rejected"))
+    }
+
+    /// Reads the format and dimensions from the leading bytes of an image,
+    /// `None` means more bytes are needed, which is never the case once
+    /// `complete` is set.
+    fn sniff(
+        bytes: &[u8],
+        options: &FetchImageOptions,
+        complete: bool,
+    ) -> Result<Option<Self>, FetchError> {
+        if !complete && bytes.len() < IMAGE_MAGIC_SIZE {
+            return Ok(None);
+        }
+
+        let format = image::guess_format(bytes).map_err(|_| FetchError::UnrecognizedImage {
+            prefix: String::from_utf8_lossy(&bytes[..bytes.len().min(32)]).into_owned(),
+        })?;
+
+        if !options.formats.is_empty() && !options.formats.contains(&format) {
+            return Err(FetchError::UnexpectedImageFormat {
+                format,
+                expected: options.formats.clone(),
+            });
+        }
+
+        let (width, height) =
+            match ImageReader::with_format(Cursor::new(bytes), format).into_dimensions() {
+                Ok(dimensions) => dimensions,
+                Err(_) if !complete && bytes.len() < IMAGE_HEADER_MAX_SIZE => return Ok(None),
+                Err(source) => return Err(FetchError::ImageDecodeError { source }),
+            };
+
+        if let Some(max_pixels) = options.max_pixels
+            && width as u64 * height as u64 > max_pixels
+        {
+            return Err(FetchError::ImageTooLarge {
+                width,
+                height,
+                max_pixels,
+            });
+        }
+
+        Ok(Some(Self {
+            format,
+            width,
+            height,
+        }))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FetchedImage {
+    pub bytes: Bytes,
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FetchedImage {
+    /// Sniffs the format from magic bytes and reads dimensions from the image
+    /// header, without decoding pixel data.
+    pub fn from_bytes(bytes: Bytes, options: &FetchImageOptions) -> Result<Self, FetchError> {
+        if let Some(max_bytes) = options.max_bytes
+            && bytes.len() as u64 > max_bytes
+        {
+            return Err(FetchError::ContentTooLarge {
+                max_size: max_bytes,
+                size: bytes.len() as u64,
+            });
+        }
+
+        let ImageHeader {
+            format,
+            width,
+            height,
+        } = ImageHeader::from_bytes(&bytes, options)?;
+
+        if is_truncated(format, &bytes, &bytes, bytes.len()) {
+            return Err(FetchError::TruncatedImage { format });
+        }
+
+        Ok(Self {
+            bytes,
+            format,
+            width,
+            height,
+        })
+    }
+
+    pub fn extension(&self) -> &'static str {
+        self.format
+            .extensions_str()
+            .first()
+            .copied()
+            .unwrap_or("bin")
+    }
+}
+
+/// Checks the end-of-image marker of formats that have one, so partially
+/// transferred bodies are not stored as images. `head` and `tail` are the
+/// leading and trailing bytes of an image of `len` bytes.
+fn is_truncated(format: ImageFormat, head: &[u8], tail: &[u8], len: usize) -> bool {
+    match format {
+        ImageFormat::Jpeg => {
+            let trimmed = tail
+                .iter()
+                .rposition(|b| *b != 0)
+                .map(|idx| &tail[..=idx])
+                .unwrap_or_default();
+            !trimmed.ends_with(&[0xFF, 0xD9])
+        }
+        ImageFormat::Png => !tail.ends_with(&[0x49, 0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82]),
+        ImageFormat::Gif => !tail.ends_with(&[0x3B]),
+        ImageFormat::WebP => head
+            .get(4..8)
+            .map(|size| u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as usize + 8)
+            .is_none_or(|riff_len| len < riff_len),
+        _ => false,
+    }
+}
+
+/// Leading and trailing bytes of a streamed image, for [`is_truncated`].
+struct ImageBounds {
+    head: Bytes,
+    tail: Vec<u8>,
+    len: usize,
+}
+
+impl ImageBounds {
+    fn new(head: &Bytes) -> Self {
+        let mut bounds = Self {
+            head: head.slice(..head.len().min(IMAGE_MAGIC_SIZE)),
+            tail: vec![],
+            len: 0,
+        };
+        bounds.push(head);
+        bounds
+    }
+
+    fn push(&mut self, chunk: &[u8]) {
+        self.len += chunk.len();
+        self.tail.extend_from_slice(chunk);
+        if self.tail.len() > IMAGE_TAIL_SIZE {
+            self.tail.drain(..self.tail.len() - IMAGE_TAIL_SIZE);
+        }
+    }
+
+    fn is_truncated(&self, format: ImageFormat) -> bool {
+        is_truncated(format, &self.head, &self.tail, self.len)
+    }
+}
+
+pub async fn fetch_image_with_options<T: IntoUrl, H: HttpClientTrait>(
+    client: &H,
+    url: T,
+    options: FetchImageOptions,
+) -> Result<FetchedImage, FetchError> {
+    let stream = fetch_stream(
+        client,
+        url,
+        FetchStreamOptions {
+            max_size: options.max_bytes,
+            max_resume_attempts: 1,
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let bytes = stream
+        .try_fold(BytesMut::new(), |mut acc, chunk| async move {
+            acc.extend_from_slice(&chunk);
+            Ok(acc)
+        })
+        .await?
+        .freeze();
+
+    FetchedImage::from_bytes(bytes, &options)
+}
+
+/// Streams an image without buffering it. The header is validated from the
+/// leading chunks before the stream is returned, so unwanted images fail
+/// before anything is written, and the stream fails at its end if the
+/// image is truncated. `options.max_bytes` overrides the stream size limit.
+pub async fn fetch_image_stream<T: IntoUrl, H: HttpClientTrait>(
+    client: &H,
+    url: T,
+    options: FetchImageOptions,
+    stream_options: FetchStreamOptions,
+) -> Result<
+    (
+        ImageHeader,
+        impl Stream<Item = Result<Bytes, FetchError>> + Send + 'static,
+    ),
+    FetchError,
+> {
+    let mut stream = Box::pin(
+        fetch_stream(
+            client,
+            url,
+            FetchStreamOptions {
+                max_size: options.max_bytes.or(stream_options.max_size),
+                ..stream_options
+            },
+        )
+        .await?,
+    );
+
+    let mut head = BytesMut::new();
+    let header = loop {
+        let chunk = stream.try_next().await?;
+        let complete = chunk.is_none();
+        if let Some(chunk) = chunk {
+            head.extend_from_slice(&chunk);
+        }
+        if let Some(header) = ImageHeader::sniff(&head, &options, complete)? {
+            break header;
+        }
+    };
+    let head = head.freeze();
+
+    let stream = async_stream::try_stream! {
+        let mut bounds = ImageBounds::new(&head);
+        if !head.is_empty() {
+            yield head;
+        }
+        while let Some(chunk) = stream.try_next().await? {
+            bounds.push(&chunk);
+            yield chunk;
+        }
+        if bounds.is_truncated(header.format) {
+            Err(FetchError::TruncatedImage {
+                format: header.format,
+            })?;
+        }
+    };
+
+    Ok((header, stream))
+}
+
 pub async fn fetch_image<T: IntoUrl, H: HttpClientTrait>(
     client: &H,
     url: T,
-) -> Result<Bytes, FetchError> {
-    fetch_bytes(client, url).await
+) -> Result<FetchedImage, FetchError> {
+    fetch_image_with_options(client, url, FetchImageOptions::default()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HttpClient;
+
+    fn encode_png(width: u32, height: u32) -> Bytes {
+        let mut buf = Cursor::new(vec![]);
+        image::RgbImage::new(width, height)
+            .write_to(&mut buf, ImageFormat::Png)
+            .unwrap();
+        Bytes::from(buf.into_inner())
+    }
+
+    #[test]
+    fn test_fetched_image_from_bytes() {
+        let png = encode_png(4, 3);
+
+        let image = FetchedImage::from_bytes(png.clone(), &Default::default()).unwrap();
+        assert_eq!(image.format, ImageFormat::Png);
+        assert_eq!((image.width, image.height), (4, 3));
+
+        let html = Bytes::from_static(b"<!DOCTYPE html><html><body>502</body></html>");
+        assert!(matches!(
+            FetchedImage::from_bytes(html, &Default::default()),
+            Err(FetchError::UnrecognizedImage { .. })
+        ));
+
+        let truncated = png.slice(..png.len() - 4);
+        assert!(matches!(
+            FetchedImage::from_bytes(truncated, &Default::default()),
+            Err(FetchError::TruncatedImage { .. })
+        ));
+
+        let options = FetchImageOptions {
+            max_pixels: Some(10),
+            ..Default::default()
+        };
+        assert!(matches!(
+            FetchedImage::from_bytes(png.clone(), &options),
+            Err(FetchError::ImageTooLarge { .. })
+        ));
+
+        let options = FetchImageOptions {
+            formats: vec![ImageFormat::Jpeg],
+            ..Default::default()
+        };
+        assert!(matches!(
+            FetchedImage::from_bytes(png, &options),
+            Err(FetchError::UnexpectedImageFormat { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_image_stream() -> Result<(), Box<dyn std::error::Error>> {
+        let png = encode_png(4, 3);
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/poster.png")
+            .with_body(png.clone())
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/truncated.png")
+            .with_body(png.slice(..png.len() - 4))
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/error.png")
+            .with_body("<!DOCTYPE html><html><body>502</body></html>")
+            .create_async()
+            .await;
+
+        let client = HttpClient::default();
+
+        let (header, stream) = fetch_image_stream(
+            &client,
+            format!("{}/poster.png", server.url()),
+            Default::default(),
+            Default::default(),
+        )
+        .await?;
+        assert_eq!(
+            header,
+            ImageHeader {
+                format: ImageFormat::Png,
+                width: 4,
+                height: 3,
+            }
+        );
+        let bytes = stream
+            .try_fold(BytesMut::new(), |mut acc, chunk| async move {
+                acc.extend_from_slice(&chunk);
+                Ok(acc)
+            })
+            .await?;
+        assert_eq!(bytes.freeze(), png);
+
+        let (_, truncated) = fetch_image_stream(
+            &client,
+            format!("{}/truncated.png", server.url()),
+            Default::default(),
+            Default::default(),
+        )
+        .await?;
+        assert!(matches!(
+            truncated.try_collect::<Vec<_>>().await,
+            Err(FetchError::TruncatedImage { .. })
+        ));
+
+        let html = fetch_image_stream(
+            &client,
+            format!("{}/error.png", server.url()),
+            Default::default(),
+            Default::default(),
+        )
+        .await;
+        assert!(matches!(
+            html.err(),
+            Some(FetchError::UnrecognizedImage { .. })
+        ));
+
+        Ok(())
+    }
 }
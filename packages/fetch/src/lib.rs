@@ -4,6 +4,8 @@ pub mod core;
 pub mod errors;
 pub mod html;
 pub mod image;
+pub mod replay;
+pub mod stream;
 pub mod test_util;
 
 pub use core::get_random_ua;
@@ -12,6 +14,10 @@ pub use bytes::fetch_bytes;
 pub use client::{HttpClient, HttpClientConfig, HttpClientError, HttpClientTrait};
 pub use errors::FetchError;
 pub use html::fetch_html;
-pub use image::fetch_image;
+pub use image::{
+    FetchImageOptions, FetchedImage, ImageHeader, fetch_image, fetch_image_stream,
+    fetch_image_with_options,
+};
 pub use reqwest::{self, IntoUrl};
 pub use reqwest_middleware;
+pub use stream::{FetchStreamOptions, fetch_stream};
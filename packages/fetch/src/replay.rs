@@ -0,0 +1,436 @@
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use axum::http::{self, Extensions, HeaderMap};
+use percent_encoding::{AsciiSet, CONTROLS, percent_decode, utf8_percent_encode};
+use reqwest::{Method, Request, Response, ResponseBuilderExt};
+use reqwest_middleware::{Middleware, Next};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+const FILE_UNSAFE: &AsciiSet = &CONTROLS
+    .add(b'<')
+    .add(b'>')
+    .add(b':')
+    .add(b'"')
+    .add(b'|')
+    .add(b'?')
+    .add(b'*')
+    .add(b'\\')
+    .add(b'/')
+    .add(b'&')
+    .add(b'=')
+    .add(b'#');
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HttpReplayMode {
+    /// Send requests and save every response to disk
+    Record,
+    /// Serve responses from disk, requests without a recording fail
+    Replay,
+    /// Send requests without touching the recordings
+    Passthrough,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HttpReplayConfig {
+    pub mode: HttpReplayMode,
+    pub path: PathBuf,
+}
+
+/// Request line of a recorded response.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct HttpReplayRequest {
+    pub method: String,
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HttpReplayMeta {
+    #[serde(default)]
+    pub request: Option<HttpReplayRequest>,
+    pub status: u16,
+    #[serde(default, with = "http_serde::header_map")]
+    pub headers: HeaderMap,
+}
+
+/// Location of a recorded response. The url path is mapped below the
+/// recordings root, `index` standing for an empty path, the query is
+/// percent-encoded into the file name and `.html` is assumed when the url has
+/// no extension. Status and headers live next to the body in a `.meta.json`
+/// file.
+#[derive(Debug, Clone)]
+pub struct HttpReplayPath {
+    path: PathBuf,
+}
+
+impl HttpReplayPath {
+    /// Location of the response to `method url`, kept below a folder per host
+    /// and port, with the method appended to that folder for anything but
+    /// `GET`, e.g. `mikanani.me@POST`.
+    pub fn from_request(root: impl AsRef<Path>, method: &Method, url: &Url) -> Self {
+        let mut namespace = url.host_str().unwrap_or_default().to_string();
+        if let Some(port) = url.port() {
+            namespace.push_str(&format!(":{port}"));
+        }
+        if method != Method::GET {
+            namespace.push_str(&format!("@{method}"));
+        }
+
+        Self::from_url(
+            root.as_ref().join(Self::encode_path_component(&namespace)),
+            url,
+        )
+    }
+
+    /// Location of the response to `url` below `root` by path and query only.
+    pub fn from_url(root: impl AsRef<Path>, url: &Url) -> Self {
+        let url_path = url.path().trim_matches('/');
+        let base_path = root.as_ref().join(if url_path.is_empty() {
+            "index"
+        } else {
+            url_path
+        });
+        let dirname = base_path.parent();
+        let stem = base_path.file_stem();
+        debug_assert!(dirname.is_some() && stem.is_some());
+        let extension = if let Some(ext) = base_path.extension() {
+            ext.to_string_lossy().to_string()
+        } else {
+            String::from("html")
+        };
+        let mut filename = stem.unwrap().to_string_lossy().to_string();
+        if let Some(query) = url.query() {
+            filename.push_str(&format!("-{}", Self::encode_path_component(query)));
+        }
+        filename.push_str(&format!(".{extension}"));
+
+        Self {
+            path: dirname.unwrap().join(filename),
+        }
+    }
+
+    pub fn encode_path_component(component: &str) -> String {
+        utf8_percent_encode(component, FILE_UNSAFE).to_string()
+    }
+
+    pub fn decode_path_component(component: &str) -> Result<String, std::str::Utf8Error> {
+        Ok(percent_decode(component.as_bytes())
+            .decode_utf8()?
+            .to_string())
+    }
+
+    pub fn meta_path(&self) -> PathBuf {
+        let extension = if let Some(ext) = self.path().extension() {
+            format!("{}.meta.json", ext.to_string_lossy())
+        } else {
+            String::from("meta.json")
+        };
+        self.path.to_path_buf().with_extension(extension)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn into_path(self) -> PathBuf {
+        self.path
+    }
+
+    pub fn exists_any(&self) -> bool {
+        self.path().exists() || self.meta_path().exists()
+    }
+
+    pub async fn write(&self, meta: &HttpReplayMeta, body: &[u8]) -> std::io::Result<()> {
+        if let Some(parent) = self.path().parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(self.path(), body).await?;
+        tokio::fs::write(self.meta_path(), serde_json::to_vec(meta)?).await?;
+        Ok(())
+    }
+
+    /// Reads a recording, a body without meta is treated as `200 OK` and a
+    /// meta without body as an empty body.
+    pub async fn read(&self) -> std::io::Result<(HttpReplayMeta, Vec<u8>)> {
+        let meta_path = self.meta_path();
+        let meta = if meta_path.exists() {
+            serde_json::from_slice(&tokio::fs::read(meta_path).await?)?
+        } else {
+            HttpReplayMeta {
+                request: None,
+                status: 200,
+                headers: HeaderMap::new(),
+            }
+        };
+        let body = if self.path().exists() {
+            tokio::fs::read(self.path()).await?
+        } else {
+            vec![]
+        };
+        Ok((meta, body))
+    }
+}
+
+impl AsRef<Path> for HttpReplayPath {
+    fn as_ref(&self) -> &Path {
+        self.path()
+    }
+}
+
+/// Records responses to, or replays them from, a directory of fixtures keyed
+/// by request method and url.
+pub struct HttpReplayMiddleware {
+    config: HttpReplayConfig,
+}
+
+impl HttpReplayMiddleware {
+    pub fn new(config: HttpReplayConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn replay_path(&self, method: &Method, url: &Url) -> HttpReplayPath {
+        HttpReplayPath::from_request(&self.config.path, method, url)
+    }
+
+    async fn replay(&self, req: Request) -> reqwest_middleware::Result<Response> {
+        let replay_path = self.replay_path(req.method(), req.url());
+
+        if !replay_path.exists_any() {
+            return Err(reqwest_middleware::Error::middleware(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!(
+                    "No recorded response for {} {} at {}",
+                    req.method(),
+                    req.url(),
+                    replay_path.path().display()
+                ),
+            )));
+        }
+
+        let (meta, body) = replay_path
+            .read()
+            .await
+            .map_err(reqwest_middleware::Error::middleware)?;
+
+        let mut builder = http::Response::builder()
+            .status(meta.status)
+            .url(req.url().clone());
+        if let Some(headers) = builder.headers_mut() {
+            headers.extend(meta.headers);
+        }
+        let response = builder
+            .body(body)
+            .map_err(reqwest_middleware::Error::middleware)?;
+
+        Ok(Response::from(response))
+    }
+
+    async fn record(
+        &self,
+        req: Request,
+        extensions: &'_ mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let method = req.method().clone();
+        let url = req.url().clone();
+        let response = next.run(req, extensions).await?;
+
+        let status = response.status();
+        let version = response.version();
+        let headers = response.headers().clone();
+        let body = response.bytes().await?;
+
+        let meta = HttpReplayMeta {
+            request: Some(HttpReplayRequest {
+                method: method.to_string(),
+                url: url.to_string(),
+            }),
+            status: status.as_u16(),
+            headers: headers.clone(),
+        };
+        self.replay_path(&method, &url)
+            .write(&meta, &body)
+            .await
+            .map_err(reqwest_middleware::Error::middleware)?;
+
+        let mut builder = http::Response::builder()
+            .status(status)
+            .version(version)
+            .url(url);
+        if let Some(h) = builder.headers_mut() {
+            h.extend(headers);
+        }
+        let response = builder
+            .body(body)
+            .map_err(reqwest_middleware::Error::middleware)?;
+
+        Ok(Response::from(response))
+    }
+}
+
+#[async_trait]
+impl Middleware for HttpReplayMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &'_ mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        match self.config.mode {
+            HttpReplayMode::Passthrough => next.run(req, extensions).await,
+            HttpReplayMode::Replay => self.replay(req).await,
+            HttpReplayMode::Record => self.record(req, extensions, next).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HttpClient, HttpClientConfig};
+
+    #[test]
+    fn test_replay_path_from_url() {
+        let root = PathBuf::from("/fixtures");
+
+        let path = HttpReplayPath::from_url(
+            &root,
+            &Url::parse("https://mikanani.me/Home/Bangumi/3416").unwrap(),
+        );
+        assert_eq!(path.path(), Path::new("/fixtures/Home/Bangumi/3416.html"));
+        assert_eq!(
+            path.meta_path(),
+            Path::new("/fixtures/Home/Bangumi/3416.html.meta.json")
+        );
+
+        let path = HttpReplayPath::from_url(
+            &root,
+            &Url::parse("https://mikanani.me/RSS/Bangumi?bangumiId=3416&subgroupid=370").unwrap(),
+        );
+        assert_eq!(
+            path.path(),
+            Path::new("/fixtures/RSS/Bangumi-bangumiId%3D3416%26subgroupid%3D370.html")
+        );
+
+        let path = HttpReplayPath::from_url(&root, &Url::parse("https://mikanani.me/").unwrap());
+        assert_eq!(path.path(), Path::new("/fixtures/index.html"));
+    }
+
+    #[test]
+    fn test_replay_path_from_request() {
+        let root = PathBuf::from("/fixtures");
+        let from_request = |method: Method, url: &str| {
+            HttpReplayPath::from_request(&root, &method, &Url::parse(url).unwrap()).into_path()
+        };
+
+        assert_eq!(
+            from_request(Method::GET, "https://mikanani.me/Home/Bangumi/3416"),
+            Path::new("/fixtures/mikanani.me/Home/Bangumi/3416.html")
+        );
+        assert_eq!(
+            from_request(Method::GET, "https://mikanime.tv/Home/Bangumi/3416"),
+            Path::new("/fixtures/mikanime.tv/Home/Bangumi/3416.html")
+        );
+        assert_eq!(
+            from_request(Method::POST, "https://mikanani.me/Account/Login"),
+            Path::new("/fixtures/mikanani.me@POST/Account/Login.html")
+        );
+        assert_eq!(
+            from_request(Method::GET, "http://localhost:5001/"),
+            Path::new("/fixtures/localhost%3A5001/index.html")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay_middleware_serves_recordings() -> Result<(), Box<dyn std::error::Error>> {
+        let root = std::env::temp_dir().join(format!("fetch-replay-{}", fastrand::u64(..)));
+        let url = Url::parse("https://example.com/feed.xml?page=1")?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(http::header::CONTENT_TYPE, "application/xml".parse()?);
+        HttpReplayPath::from_request(&root, &Method::GET, &url)
+            .write(
+                &HttpReplayMeta {
+                    request: None,
+                    status: 200,
+                    headers,
+                },
+                b"<rss/>",
+            )
+            .await?;
+
+        let client = HttpClient::from_config(HttpClientConfig {
+            replay: Some(HttpReplayConfig {
+                mode: HttpReplayMode::Replay,
+                path: root.clone(),
+            }),
+            ..Default::default()
+        })?;
+
+        let response = client.get(url).send().await?;
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            response.headers().get(http::header::CONTENT_TYPE),
+            Some(&"application/xml".parse()?)
+        );
+        assert_eq!(response.text().await?, "<rss/>");
+
+        let missing = client.get("https://example.com/missing").send().await;
+        assert!(missing.is_err());
+
+        let _ = std::fs::remove_dir_all(root);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_replay_middleware_records_by_method() -> Result<(), Box<dyn std::error::Error>> {
+        let root = std::env::temp_dir().join(format!("fetch-replay-{}", fastrand::u64(..)));
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/login")
+            .with_body("login page")
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/login")
+            .with_status(302)
+            .create_async()
+            .await;
+
+        let client = HttpClient::from_config(HttpClientConfig {
+            replay: Some(HttpReplayConfig {
+                mode: HttpReplayMode::Record,
+                path: root.clone(),
+            }),
+            ..Default::default()
+        })?;
+        let url = Url::parse(&format!("{}/login", server.url()))?;
+
+        client.get(url.clone()).send().await?;
+        client.post(url.clone()).send().await?;
+
+        let (meta, body) = HttpReplayPath::from_request(&root, &Method::GET, &url)
+            .read()
+            .await?;
+        assert_eq!(meta.status, 200);
+        assert_eq!(body, b"login page");
+        assert_eq!(
+            meta.request,
+            Some(HttpReplayRequest {
+                method: "GET".to_string(),
+                url: url.to_string(),
+            })
+        );
+
+        let (meta, _) = HttpReplayPath::from_request(&root, &Method::POST, &url)
+            .read()
+            .await?;
+        assert_eq!(meta.status, 302);
+        assert_eq!(meta.request.map(|r| r.method), Some("POST".to_string()));
+
+        let _ = std::fs::remove_dir_all(root);
+        Ok(())
+    }
+}
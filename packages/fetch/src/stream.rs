@@ -0,0 +1,340 @@
+use bytes::Bytes;
+use futures::Stream;
+use reqwest::{
+    IntoUrl, Response, StatusCode, Url,
+    header::{
+        ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_RANGE, LAST_MODIFIED,
+        RANGE,
+    },
+};
+use reqwest_middleware::ClientWithMiddleware;
+
+use super::client::HttpClientTrait;
+use crate::FetchError;
+
+#[derive(Debug, Clone, Default)]
+pub struct FetchStreamOptions {
+    /// Fail once the body grows past this many bytes
+    pub max_size: Option<u64>,
+    /// Accepted content types, either exact (`application/x-bittorrent`) or a
+    /// top level wildcard (`image/*`). Responses without a content type are
+    /// accepted, any content type is accepted if empty.
+    pub content_types: Vec<String>,
+    /// How many times an interrupted body is resumed with a `Range` request
+    pub max_resume_attempts: u32,
+}
+
+impl FetchStreamOptions {
+    fn check_content_type(&self, response: &Response) -> Result<(), FetchError> {
+        if self.content_types.is_empty() {
+            return Ok(());
+        }
+        let Some(content_type) = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+        else {
+            return Ok(());
+        };
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+
+        let accepted = self.content_types.iter().any(|expected| {
+            let expected = expected.to_ascii_lowercase();
+            match expected.strip_suffix("/*") {
+                Some(top_level) => essence
+                    .split_once('/')
+                    .is_some_and(|(essence_top_level, _)| essence_top_level == top_level),
+                None => essence == expected,
+            }
+        });
+
+        if accepted {
+            Ok(())
+        } else {
+            Err(FetchError::UnexpectedContentType {
+                content_type: content_type.to_string(),
+                expected: self.content_types.clone(),
+            })
+        }
+    }
+
+    fn check_size(&self, size: u64) -> Result<(), FetchError> {
+        match self.max_size {
+            Some(max_size) if size > max_size => {
+                Err(FetchError::ContentTooLarge { max_size, size })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+struct ResumeValidator {
+    if_range: Option<String>,
+    accept_ranges: bool,
+}
+
+impl ResumeValidator {
+    fn from_response(response: &Response) -> Self {
+        let headers = response.headers();
+        let if_range = headers
+            .get(ETAG)
+            .filter(|etag| etag.to_str().is_ok_and(|etag| !etag.starts_with("W/")))
+            .or_else(|| headers.get(LAST_MODIFIED))
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+        let accept_ranges = headers
+            .get(ACCEPT_RANGES)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.eq_ignore_ascii_case("bytes"));
+        Self {
+            if_range,
+            accept_ranges,
+        }
+    }
+}
+
+async fn resume(
+    client: &ClientWithMiddleware,
+    url: &Url,
+    validator: &ResumeValidator,
+    offset: u64,
+) -> Result<Response, FetchError> {
+    let mut request = client
+        .get(url.clone())
+        .header(RANGE, format!("bytes={offset}-"));
+    if let Some(if_range) = validator.if_range.as_deref() {
+        request = request.header(IF_RANGE, if_range);
+    }
+    let response = request.send().await?.error_for_status()?;
+
+    let resumed_at = response
+        .headers()
+        .get(CONTENT_RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("bytes "))
+        .and_then(|v| v.split_once('-'))
+        .and_then(|(start, _)| start.parse::<u64>().ok());
+
+    if response.status() != StatusCode::PARTIAL_CONTENT || resumed_at != Some(offset) {
+        return Err(FetchError::ResumeUnsupported {
+            url: url.to_string(),
+            status: response.status().as_u16(),
+        });
+    }
+
+    Ok(response)
+}
+
+/// Streams a response body chunk by chunk instead of buffering it. Status,
+/// content type and declared length are checked before the stream is
+/// returned, the size limit is enforced again while streaming and
+/// interrupted bodies are resumed with `Range` requests.
+pub async fn fetch_stream<T: IntoUrl, H: HttpClientTrait>(
+    client: &H,
+    url: T,
+    options: FetchStreamOptions,
+) -> Result<impl Stream<Item = Result<Bytes, FetchError>> + Send + 'static, FetchError> {
+    let client: ClientWithMiddleware = (**client).clone();
+    let url = url.into_url()?;
+
+    let response = client.get(url.clone()).send().await?.error_for_status()?;
+
+    options.check_content_type(&response)?;
+    if let Some(content_length) = response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+    {
+        options.check_size(content_length)?;
+    }
+
+    let validator = ResumeValidator::from_response(&response);
+
+    Ok(async_stream::try_stream! {
+        let mut response = response;
+        let mut received: u64 = 0;
+        let mut resume_attempts = 0;
+
+        loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    received += chunk.len() as u64;
+                    options.check_size(received)?;
+                    yield chunk;
+                }
+                Ok(None) => break,
+                Err(err)
+                    if resume_attempts >= options.max_resume_attempts
+                        || !validator.accept_ranges =>
+                {
+                    Err(err)?
+                }
+                Err(err) => {
+                    resume_attempts += 1;
+                    tracing::warn!(
+                        url = %url,
+                        received,
+                        attempt = resume_attempts,
+                        error = %err,
+                        "Response body interrupted, resuming with range request"
+                    );
+                    response = resume(&client, &url, &validator, received).await?;
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use futures::TryStreamExt;
+    use mockito::Matcher;
+
+    use super::*;
+    use crate::HttpClient;
+
+    async fn collect(
+        stream: impl Stream<Item = Result<Bytes, FetchError>>,
+    ) -> Result<Vec<u8>, FetchError> {
+        stream
+            .try_fold(vec![], |mut acc, chunk| async move {
+                acc.extend_from_slice(&chunk);
+                Ok(acc)
+            })
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_fetch_stream_rejects_oversized_body() -> Result<(), Box<dyn std::error::Error>> {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/declared")
+            .with_body(vec![0u8; 64])
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/chunked")
+            .with_chunked_body(|w| w.write_all(&[0u8; 64]))
+            .create_async()
+            .await;
+
+        let client = HttpClient::default();
+        let options = FetchStreamOptions {
+            max_size: Some(32),
+            ..Default::default()
+        };
+
+        let declared = fetch_stream(
+            &client,
+            format!("{}/declared", server.url()),
+            options.clone(),
+        )
+        .await;
+        assert!(matches!(
+            declared.err(),
+            Some(FetchError::ContentTooLarge { size: 64, .. })
+        ));
+
+        let chunked = fetch_stream(&client, format!("{}/chunked", server.url()), options).await?;
+        assert!(matches!(
+            collect(chunked).await,
+            Err(FetchError::ContentTooLarge { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_stream_rejects_unexpected_content_type()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/poster.jpg")
+            .with_header("content-type", "text/html; charset=utf-8")
+            .with_body("<html>502</html>")
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/poster.png")
+            .with_header("content-type", "image/png")
+            .with_body("png")
+            .create_async()
+            .await;
+
+        let client = HttpClient::default();
+        let options = FetchStreamOptions {
+            content_types: vec![String::from("image/*")],
+            ..Default::default()
+        };
+
+        let html = fetch_stream(
+            &client,
+            format!("{}/poster.jpg", server.url()),
+            options.clone(),
+        )
+        .await;
+        assert!(matches!(
+            html.err(),
+            Some(FetchError::UnexpectedContentType { .. })
+        ));
+
+        let png = fetch_stream(&client, format!("{}/poster.png", server.url()), options).await?;
+        assert_eq!(collect(png).await?, b"png");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_stream_resumes_cut_off_body() -> Result<(), Box<dyn std::error::Error>> {
+        let mut server = mockito::Server::new_async().await;
+        let cut_off = server
+            .mock("GET", "/a.torrent")
+            .match_header("range", Matcher::Missing)
+            .with_header("accept-ranges", "bytes")
+            .with_header("etag", "\"v1\"")
+            .with_chunked_body(|w| {
+                w.write_all(b"hello")?;
+                w.flush()?;
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::ConnectionReset,
+                    "cut off",
+                ))
+            })
+            .create_async()
+            .await;
+        let resumed = server
+            .mock("GET", "/a.torrent")
+            .match_header("range", "bytes=5-")
+            .match_header("if-range", "\"v1\"")
+            .with_status(206)
+            .with_header("content-range", "bytes 5-10/11")
+            .with_body(" world")
+            .create_async()
+            .await;
+
+        let client = HttpClient::default();
+        let stream = fetch_stream(
+            &client,
+            format!("{}/a.torrent", server.url()),
+            FetchStreamOptions {
+                max_resume_attempts: 1,
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        assert_eq!(collect(stream).await?, b"hello world");
+        cut_off.assert_async().await;
+        resumed.assert_async().await;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+
+/// Minimal bencode value, only what is needed to write `.torrent` files and
+/// tracker responses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BencodeValue {
+    Integer(i64),
+    Bytes(Vec<u8>),
+    List(Vec<BencodeValue>),
+    Dict(BTreeMap<Vec<u8>, BencodeValue>),
+}
+
+impl BencodeValue {
+    pub fn dict<K, I>(entries: I) -> Self
+    where
+        K: Into<Vec<u8>>,
+        I: IntoIterator<Item = (K, BencodeValue)>,
+    {
+        Self::Dict(entries.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode_to(&mut buf);
+        buf
+    }
+
+    fn encode_to(&self, buf: &mut Vec<u8>) {
+        match self {
+            Self::Integer(i) => {
+                buf.push(b'i');
+                buf.extend(i.to_string().as_bytes());
+                buf.push(b'e');
+            }
+            Self::Bytes(bytes) => {
+                buf.extend(bytes.len().to_string().as_bytes());
+                buf.push(b':');
+                buf.extend(bytes);
+            }
+            Self::List(list) => {
+                buf.push(b'l');
+                for item in list {
+                    item.encode_to(buf);
+                }
+                buf.push(b'e');
+            }
+            // BTreeMap keeps keys in the raw byte order required by the spec
+            Self::Dict(dict) => {
+                buf.push(b'd');
+                for (key, value) in dict {
+                    Self::Bytes(key.clone()).encode_to(buf);
+                    value.encode_to(buf);
+                }
+                buf.push(b'e');
+            }
+        }
+    }
+}
+
+impl From<i64> for BencodeValue {
+    fn from(value: i64) -> Self {
+        Self::Integer(value)
+    }
+}
+
+impl From<u64> for BencodeValue {
+    fn from(value: u64) -> Self {
+        Self::Integer(value as i64)
+    }
+}
+
+impl From<&str> for BencodeValue {
+    fn from(value: &str) -> Self {
+        Self::Bytes(value.as_bytes().to_vec())
+    }
+}
+
+impl From<String> for BencodeValue {
+    fn from(value: String) -> Self {
+        Self::Bytes(value.into_bytes())
+    }
+}
+
+impl From<Vec<u8>> for BencodeValue {
+    fn from(value: Vec<u8>) -> Self {
+        Self::Bytes(value)
+    }
+}
+
+impl From<Vec<BencodeValue>> for BencodeValue {
+    fn from(value: Vec<BencodeValue>) -> Self {
+        Self::List(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BencodeValue;
+
+    #[test]
+    fn test_encode_sorts_dict_keys() {
+        let value = BencodeValue::dict([
+            ("name", BencodeValue::from("test")),
+            ("length", BencodeValue::from(1024u64)),
+            (
+                "path",
+                BencodeValue::from(vec![BencodeValue::from("a"), BencodeValue::from("b.mkv")]),
+            ),
+        ]);
+
+        assert_eq!(
+            value.encode(),
+            b"d6:lengthi1024e4:name4:test4:pathl1:a5:b.mkvee".to_vec()
+        );
+    }
+}
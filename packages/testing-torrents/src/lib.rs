@@ -6,20 +6,26 @@ use testcontainers::{
 use testcontainers_ext::{ImageDefaultLogConsumerExt, ImagePruneExistedLabelExt};
 use testcontainers_modules::testcontainers::ImageExt;
 
-#[derive(Serialize)]
+pub mod bencode;
+pub mod seeder;
+pub mod tracker;
+
+pub use seeder::TestingTorrentSeeder;
+
+#[derive(Debug, Clone, Serialize)]
 pub struct TestingTorrentFileItem {
     pub path: String,
     pub size: u64,
 }
 
-#[derive(Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TestTorrentRequest {
     pub id: String,
     pub file_list: Vec<TestingTorrentFileItem>,
 }
 
-#[derive(Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TestTorrentResponse {
     pub torrent_url: String,
@@ -0,0 +1,229 @@
+use std::{
+    io::Read,
+    net::Ipv4Addr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use bytes::Bytes;
+use librqbit::{
+    AddTorrent, AddTorrentOptions, ListenerOptions, Session, SessionOptions, api::TorrentIdOrHash,
+};
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+use sha1::{Digest, Sha1};
+
+use crate::{
+    TestTorrentRequest, TestTorrentResponse, bencode::BencodeValue, tracker::TrackerServer,
+};
+
+pub const TESTING_TORRENT_PIECE_LENGTH: usize = 256 * 1024;
+
+/// In-process replacement of the testing torrents container.
+///
+/// Generated files are seeded by an embedded rqbit session which announces to
+/// a tracker on loopback, so tests need neither docker nor network access.
+pub struct TestingTorrentSeeder {
+    workspace: PathBuf,
+    session: Arc<Session>,
+    tracker: TrackerServer,
+}
+
+impl TestingTorrentSeeder {
+    pub async fn start() -> anyhow::Result<Self> {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos();
+        let workspace = std::env::temp_dir()
+            .join("konobangu-testing-torrents")
+            .join(format!("{}-{nanos}", std::process::id()));
+
+        Self::start_with_workspace(workspace).await
+    }
+
+    pub async fn start_with_workspace(workspace: PathBuf) -> anyhow::Result<Self> {
+        tokio::fs::create_dir_all(&workspace).await?;
+
+        let tracker = TrackerServer::start().await?;
+
+        let session = Session::new_with_opts(
+            workspace.clone(),
+            SessionOptions {
+                disable_dht: true,
+                disable_dht_persistence: true,
+                persistence: None,
+                listen: Some(ListenerOptions {
+                    listen_addr: (Ipv4Addr::LOCALHOST, 0).into(),
+                    enable_upnp_port_forwarding: false,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        )
+        .await
+        .context("failed to start testing torrents seeding session")?;
+
+        Ok(Self {
+            workspace,
+            session,
+            tracker,
+        })
+    }
+
+    pub fn workspace(&self) -> &Path {
+        &self.workspace
+    }
+
+    pub fn tracker_url(&self) -> String {
+        self.tracker.announce_url()
+    }
+
+    /// Same as `POST /api/torrents/mock` of the container: creates the files,
+    /// writes the `.torrent` and starts seeding it.
+    pub async fn mock_torrent(
+        &self,
+        request: TestTorrentRequest,
+    ) -> anyhow::Result<TestTorrentResponse> {
+        let content_dir = self.workspace.join(&request.id);
+
+        let mut files = vec![];
+        for item in &request.file_list {
+            let path = content_dir.join(&item.path);
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            let file = tokio::fs::File::create(&path).await?;
+            file.set_len(item.size).await?;
+            files.push(path);
+        }
+
+        let pieces = tokio::task::spawn_blocking(move || hash_pieces(&files)).await??;
+
+        let info = BencodeValue::dict([
+            ("name", BencodeValue::from(request.id.as_str())),
+            (
+                "piece length",
+                BencodeValue::from(TESTING_TORRENT_PIECE_LENGTH as u64),
+            ),
+            ("pieces", BencodeValue::from(pieces)),
+            (
+                "files",
+                BencodeValue::List(
+                    request
+                        .file_list
+                        .iter()
+                        .map(|item| {
+                            BencodeValue::dict([
+                                ("length", BencodeValue::from(item.size)),
+                                (
+                                    "path",
+                                    BencodeValue::List(
+                                        item.path
+                                            .split('/')
+                                            .filter(|s| !s.is_empty())
+                                            .map(BencodeValue::from)
+                                            .collect(),
+                                    ),
+                                ),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+        ]);
+        let hash = Sha1::digest(info.encode())
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>();
+
+        let tracker_url = self.tracker_url();
+        let torrent = BencodeValue::dict([
+            ("announce", BencodeValue::from(tracker_url.as_str())),
+            (
+                "announce-list",
+                BencodeValue::List(vec![BencodeValue::List(vec![BencodeValue::from(
+                    tracker_url.as_str(),
+                )])]),
+            ),
+            (
+                "created by",
+                BencodeValue::from("konobangu-testing-torrents"),
+            ),
+            ("info", info),
+        ]);
+        let payload = Bytes::from(torrent.encode());
+
+        let torrent_file_name = format!("{}.torrent", request.id);
+        tokio::fs::write(self.workspace.join(&torrent_file_name), &payload).await?;
+        self.tracker
+            .state
+            .insert_torrent_file(torrent_file_name.clone(), payload.clone());
+
+        // files already exist with the final content, so the initial check
+        // finishes the torrent and it goes straight to seeding
+        let handle = self
+            .session
+            .add_torrent(
+                AddTorrent::TorrentFileBytes(payload),
+                Some(AddTorrentOptions {
+                    output_folder: Some(content_dir.to_string_lossy().into_owned()),
+                    overwrite: true,
+                    ..Default::default()
+                }),
+            )
+            .await?
+            .into_handle()
+            .context("failed to get handle of seeding torrent")?;
+        handle.wait_until_initialized().await?;
+
+        Ok(TestTorrentResponse {
+            torrent_url: format!("{}/api/static/{torrent_file_name}", self.tracker.base_url()),
+            magnet_url: format!(
+                "magnet:?xt=urn:btih:{hash}&dn={}&tr={}",
+                utf8_percent_encode(&request.id, NON_ALPHANUMERIC),
+                utf8_percent_encode(&tracker_url, NON_ALPHANUMERIC)
+            ),
+            hash,
+        })
+    }
+
+    pub async fn stop_seeding(&self, hash: &str) -> anyhow::Result<()> {
+        let hash = hash.parse()?;
+        self.session
+            .delete(TorrentIdOrHash::Hash(hash), false)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn shutdown(self) -> anyhow::Result<()> {
+        self.session.stop().await;
+        tokio::fs::remove_dir_all(&self.workspace).await?;
+        Ok(())
+    }
+}
+
+fn hash_pieces(files: &[PathBuf]) -> std::io::Result<Vec<u8>> {
+    let mut pieces = vec![];
+    let mut buf = vec![0u8; TESTING_TORRENT_PIECE_LENGTH];
+    let mut filled = 0;
+
+    // pieces span file boundaries, files are hashed as one continuous stream
+    for path in files {
+        let mut file = std::fs::File::open(path)?;
+        loop {
+            let read = file.read(&mut buf[filled..])?;
+            if read == 0 {
+                break;
+            }
+            filled += read;
+            if filled == buf.len() {
+                pieces.extend(Sha1::digest(&buf));
+                filled = 0;
+            }
+        }
+    }
+    if filled > 0 {
+        pieces.extend(Sha1::digest(&buf[..filled]));
+    }
+
+    Ok(pieces)
+}
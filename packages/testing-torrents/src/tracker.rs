@@ -0,0 +1,175 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::{Arc, RwLock},
+};
+
+use axum::{
+    Router,
+    extract::{ConnectInfo, Path, RawQuery, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use bytes::Bytes;
+use percent_encoding::percent_decode_str;
+use tokio::{net::TcpListener, task::JoinHandle};
+
+use crate::bencode::BencodeValue;
+
+pub const TRACKER_ANNOUNCE_INTERVAL_SECS: i64 = 5;
+
+type InfoHash = [u8; 20];
+
+#[derive(Default)]
+pub struct TrackerState {
+    torrent_files: RwLock<HashMap<String, Bytes>>,
+    peers: RwLock<HashMap<InfoHash, HashMap<Vec<u8>, SocketAddr>>>,
+}
+
+impl TrackerState {
+    pub fn insert_torrent_file(&self, file_name: String, payload: Bytes) {
+        self.torrent_files
+            .write()
+            .expect("torrent files lock poisoned")
+            .insert(file_name, payload);
+    }
+
+    fn announce(&self, query: &str, remote: SocketAddr) -> Result<BencodeValue, String> {
+        let params = query
+            .split('&')
+            .filter_map(|pair| {
+                let (key, value) = pair.split_once('=')?;
+                Some((key, percent_decode_str(value).collect::<Vec<u8>>()))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let info_hash: InfoHash = params
+            .get("info_hash")
+            .and_then(|v| v.as_slice().try_into().ok())
+            .ok_or("missing or invalid info_hash")?;
+        let peer_id = params.get("peer_id").ok_or("missing peer_id")?.clone();
+        let port = params
+            .get("port")
+            .and_then(|v| std::str::from_utf8(v).ok()?.parse::<u16>().ok())
+            .ok_or("missing or invalid port")?;
+        let stopped = params.get("event").is_some_and(|e| e == b"stopped");
+
+        let mut peers = self.peers.write().expect("tracker peers lock poisoned");
+        let swarm = peers.entry(info_hash).or_default();
+        if stopped {
+            swarm.remove(&peer_id);
+        } else {
+            swarm.insert(peer_id.clone(), SocketAddr::new(remote.ip(), port));
+        }
+
+        // compact peer list, only loopback ipv4 peers are expected here
+        let compact = swarm
+            .iter()
+            .filter(|(id, _)| **id != peer_id)
+            .filter_map(|(_, addr)| match addr.ip() {
+                IpAddr::V4(ip) => Some((ip, addr.port())),
+                IpAddr::V6(ip) => ip.to_ipv4_mapped().map(|ip| (ip, addr.port())),
+            })
+            .flat_map(|(ip, port)| {
+                let mut peer = ip.octets().to_vec();
+                peer.extend(port.to_be_bytes());
+                peer
+            })
+            .collect::<Vec<u8>>();
+
+        Ok(BencodeValue::dict([
+            (
+                "interval",
+                BencodeValue::from(TRACKER_ANNOUNCE_INTERVAL_SECS),
+            ),
+            ("complete", BencodeValue::from(swarm.len() as i64)),
+            ("incomplete", BencodeValue::from(0i64)),
+            ("peers", BencodeValue::from(compact)),
+        ]))
+    }
+}
+
+async fn announce(
+    State(state): State<Arc<TrackerState>>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    RawQuery(query): RawQuery,
+) -> Response {
+    let body = state
+        .announce(query.as_deref().unwrap_or_default(), remote)
+        .unwrap_or_else(|reason| BencodeValue::dict([("failure reason", reason.into())]));
+
+    ([(header::CONTENT_TYPE, "text/plain")], body.encode()).into_response()
+}
+
+async fn torrent_file(
+    State(state): State<Arc<TrackerState>>,
+    Path(file_name): Path<String>,
+) -> Response {
+    let payload = state
+        .torrent_files
+        .read()
+        .expect("torrent files lock poisoned")
+        .get(&file_name)
+        .cloned();
+
+    match payload {
+        Some(payload) => (
+            [(header::CONTENT_TYPE, "application/x-bittorrent")],
+            payload,
+        )
+            .into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Loopback http server acting as both the tracker and the static server of
+/// generated `.torrent` files.
+pub struct TrackerServer {
+    pub addr: SocketAddr,
+    pub state: Arc<TrackerState>,
+    handle: JoinHandle<()>,
+}
+
+impl TrackerServer {
+    pub async fn start() -> anyhow::Result<Self> {
+        let state = Arc::new(TrackerState::default());
+        let router = Router::new()
+            .route("/announce", get(announce))
+            .route("/api/static/{file_name}", get(torrent_file))
+            .with_state(state.clone());
+
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+        let addr = listener.local_addr()?;
+        let handle = tokio::spawn(async move {
+            if let Err(e) = axum::serve(
+                listener,
+                router.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+            {
+                tracing::error!(error = %e, "testing torrents tracker stopped");
+            }
+        });
+
+        Ok(Self {
+            addr,
+            state,
+            handle,
+        })
+    }
+
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn announce_url(&self) -> String {
+        format!("{}/announce", self.base_url())
+    }
+}
+
+impl Drop for TrackerServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}